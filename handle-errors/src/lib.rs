@@ -1,17 +1,83 @@
+//! Errors of the Q&A service and how they are reported to clients.
+//!
+//! Every rejection is answered with a JSON body
+//! `{"code": ..., "message": ..., "details": ..., "request_id": ...}`.
+//! `code` is stable and meant for clients to branch on, `message` is for
+//! humans and may change. `request_id` is also logged with the error.
+
+use serde::Serialize;
+use warp::http::StatusCode;
+use warp::Reply;
+
 #[derive(Debug)]
 pub enum Error {
+    /// `parse_error` (400): a parameter is not a valid number
     ParseError(std::num::ParseIntError),
+    /// `missing_parameters` (400): a required parameter is absent or empty
     MissingParameters,
+    /// `invalid_parameter` (400): a parameter has an unsupported value
+    InvalidParameter(String),
+    /// `invalid_range` (400): a range whose start lies after its end
     InvalidRange,
+    /// `question_not_found` (404)
     QuestionNotFound,
+    /// `answer_not_found` (404)
+    AnswerNotFound,
+    /// `comment_not_found` (404)
+    CommentNotFound,
+    /// `wrong_credentials` (401): unknown e-mail or wrong password
     WrongPassword,
+    /// `unauthorized` (401): the request is not allowed to do this
     Unauthorized,
+    /// `invalid_token` (401): the token is missing, malformed or expired
     TokenError,
+    /// `forbidden` (403): authenticated, but the role or ownership is lacking
+    Forbidden,
+    /// `email_not_verified` (403): the account has to verify its e-mail first
+    EmailNotVerified,
+    /// `own_content_vote` (403): accounts can't vote on their own questions
+    /// and answers
+    OwnContentVote,
+    /// `account_exists` (409)
+    AccountAlreadyExists,
+    /// `two_factor_enabled` (409): two-factor authentication is set up
+    /// already and has to be disabled first
+    TwoFactorEnabled,
+    /// `two_factor_not_enabled` (409): there is no two-factor authentication
+    /// to confirm or disable
+    TwoFactorNotEnabled,
+    /// `validation_failed` (422): one or more fields are invalid, listed in
+    /// `details.fields`
+    ValidationFailed(Vec<FieldError>),
+    /// `too_many_attempts` (429): too many failed logins, retry after the
+    /// given number of seconds, also sent as `Retry-After` header
+    TooManyAttempts(u64),
+    /// `not_found` (404) when no row matched, `account_exists` (409) on a
+    /// duplicate account, `conflict` (409) on other unique violations,
+    /// `invalid_reference` (422) when a referenced row does not exist,
+    /// `database_error` (500) otherwise
     DatabaseQueryError(sqlx::Error),
+    /// `external_api_error` (502): the profanity API could not be reached
     ExternalAPIError(reqwest::Error),
+    /// `external_api_error` (502): the profanity API rejected our request
     ClientError(APILayerError),
+    /// `external_api_error` (502): the profanity API failed
     ServerError(APILayerError),
+    /// `internal_error` (500): password hashing failed
     ArgonLibraryError(argon2::Error),
+    /// `mail_error` (502): an e-mail could not be sent
+    MailError(String),
+    /// `identity_provider_error` (502): the OpenID Connect provider could not
+    /// be reached or gave an unusable answer
+    IdentityProviderError(String),
+}
+/// Problem with a single field of a request body. `code` is stable like
+/// the error codes, e.g. `too_short`, `invalid` or `common`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: String,
+    pub message: String,
 }
 #[derive(Debug, Clone)]
 pub struct APILayerError {
@@ -20,127 +86,305 @@ pub struct APILayerError {
 }
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &*self {
+        match self {
             Error::ParseError(ref err) => write!(f, "Can't parse parameter: {}", err),
             Error::MissingParameters => write!(f, "Missing parameter"),
+            Error::InvalidParameter(ref name) => write!(f, "Invalid parameter: {}", name),
             Error::InvalidRange => write!(f, "Invalid range"),
             Error::QuestionNotFound => write!(f, "Question not found"),
+            Error::AnswerNotFound => write!(f, "Answer not found"),
+            Error::CommentNotFound => write!(f, "Comment not found"),
             Error::WrongPassword => write!(f, "Wrong Password"),
             Error::Unauthorized => write!(f, "Unauthorized"),
             Error::TokenError => write!(f, "Token Error"),
+            Error::Forbidden => write!(f, "Forbidden"),
+            Error::EmailNotVerified => write!(f, "E-mail address not verified"),
+            Error::OwnContentVote => write!(f, "Can't vote on your own content"),
+            Error::AccountAlreadyExists => write!(f, "Account already exists"),
+            Error::TwoFactorEnabled => write!(f, "Two-factor authentication is already enabled"),
+            Error::TwoFactorNotEnabled => write!(f, "Two-factor authentication is not set up"),
+            Error::ValidationFailed(_) => write!(f, "Validation failed"),
+            Error::TooManyAttempts(_) => write!(f, "Too many failed attempts, try again later"),
             Error::DatabaseQueryError(_) => write!(f, "Query could not be executed"),
             Error::ExternalAPIError(ref err) => write!(f, "External api error: {}", err),
             Error::ClientError(ref err) => write!(f, "Client error: {}, status: {}", err.message, err.status),
             Error::ServerError(ref err) => write!(f, "Server error: {}, status: {}", err.message, err.status),
             Error::ArgonLibraryError(ref err) => write!(f, "Auth error: {}", err),
+            Error::MailError(ref err) => write!(f, "Mail error: {}", err),
+            Error::IdentityProviderError(ref err) => write!(f, "Identity provider error: {}", err),
         }
     }
 }
 impl warp::reject::Reject for Error {}
 
+const DUPLICATE_KEY: &str = "23505";
+const FOREIGN_KEY_VIOLATION: &str = "23503";
+/// Unique constraints on accounts that are violated by a taken address.
+const ACCOUNT_EMAIL_CONSTRAINTS: [&str; 2] = ["accounts_pkey", "accounts_email_lower_idx"];
+
+impl Error {
+    /// Stable code identifying the error, see the variant docs.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::ParseError(_) => "parse_error",
+            Error::MissingParameters => "missing_parameters",
+            Error::InvalidParameter(_) => "invalid_parameter",
+            Error::InvalidRange => "invalid_range",
+            Error::QuestionNotFound => "question_not_found",
+            Error::AnswerNotFound => "answer_not_found",
+            Error::CommentNotFound => "comment_not_found",
+            Error::WrongPassword => "wrong_credentials",
+            Error::Unauthorized => "unauthorized",
+            Error::TokenError => "invalid_token",
+            Error::Forbidden => "forbidden",
+            Error::EmailNotVerified => "email_not_verified",
+            Error::OwnContentVote => "own_content_vote",
+            Error::AccountAlreadyExists => "account_exists",
+            Error::TwoFactorEnabled => "two_factor_enabled",
+            Error::TwoFactorNotEnabled => "two_factor_not_enabled",
+            Error::ValidationFailed(_) => "validation_failed",
+            Error::TooManyAttempts(_) => "too_many_attempts",
+            Error::DatabaseQueryError(sqlx::Error::RowNotFound) => "not_found",
+            Error::DatabaseQueryError(sqlx::Error::Database(err)) => {
+                match (err.code().as_deref(), err.constraint()) {
+                    (Some(DUPLICATE_KEY), Some(constraint)) if ACCOUNT_EMAIL_CONSTRAINTS.contains(&constraint) => "account_exists",
+                    (Some(DUPLICATE_KEY), _) => "conflict",
+                    (Some(FOREIGN_KEY_VIOLATION), _) => "invalid_reference",
+                    _ => "database_error",
+                }
+            },
+            Error::DatabaseQueryError(_) => "database_error",
+            Error::ExternalAPIError(_) | Error::ClientError(_) | Error::ServerError(_) => "external_api_error",
+            Error::ArgonLibraryError(_) => "internal_error",
+            Error::MailError(_) => "mail_error",
+            Error::IdentityProviderError(_) => "identity_provider_error",
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self.code() {
+            "parse_error" | "missing_parameters" | "invalid_parameter" | "invalid_range" => StatusCode::BAD_REQUEST,
+            "question_not_found" | "answer_not_found" | "comment_not_found" | "not_found" => StatusCode::NOT_FOUND,
+            "wrong_credentials" | "unauthorized" | "invalid_token" => StatusCode::UNAUTHORIZED,
+            "forbidden" | "email_not_verified" | "own_content_vote" => StatusCode::FORBIDDEN,
+            "account_exists" | "conflict" | "two_factor_enabled" | "two_factor_not_enabled" => StatusCode::CONFLICT,
+            "invalid_reference" | "validation_failed" => StatusCode::UNPROCESSABLE_ENTITY,
+            "too_many_attempts" => StatusCode::TOO_MANY_REQUESTS,
+            "external_api_error" | "mail_error" | "identity_provider_error" => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Message shown to clients; internals such as SQL errors are left out.
+    fn public_message(&self) -> String {
+        match self {
+            Error::WrongPassword => "Wrong E-Mail/Password combination".to_string(),
+            Error::ExternalAPIError(_) | Error::ClientError(_) | Error::ServerError(_) => {
+                "Profanity check is unavailable".to_string()
+            },
+            Error::ArgonLibraryError(_) => "Internal server error".to_string(),
+            Error::MailError(_) => "E-mail could not be sent".to_string(),
+            Error::IdentityProviderError(_) => "Sign-in with the identity provider failed".to_string(),
+            _ => self.to_string(),
+        }
+    }
+
+    fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            Error::ParseError(err) => Some(serde_json::json!({ "reason": err.to_string() })),
+            Error::InvalidParameter(name) => Some(serde_json::json!({ "parameter": name })),
+            Error::ValidationFailed(fields) => Some(serde_json::json!({ "fields": fields })),
+            Error::TooManyAttempts(seconds) => Some(serde_json::json!({ "retry_after": seconds })),
+            Error::ClientError(err) | Error::ServerError(err) => {
+                Some(serde_json::json!({ "upstream_status": err.status }))
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize)]
+pub struct ErrorResponse {
+    pub code: &'static str,
+    pub message: String,
+    pub details: Option<serde_json::Value>,
+    pub request_id: String,
+}
+
 #[derive(Debug)]
 pub struct InvalidId;
 impl warp::reject::Reject for InvalidId {}
 
-const DUPLICATE_KEY: u32 = 23505;
+fn reply(
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    details: Option<serde_json::Value>,
+) -> warp::reply::Response {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    if status.is_server_error() {
+        tracing::event!(tracing::Level::ERROR, request_id = %request_id, code, "{}", message);
+    } else {
+        tracing::event!(tracing::Level::INFO, request_id = %request_id, code, "{}", message);
+    }
+
+    warp::reply::with_status(
+        warp::reply::json(&ErrorResponse { code, message, details, request_id }),
+        status,
+    ).into_response()
+}
 
 pub async fn return_error(r: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
-    if let Some(error) = r.find::<warp::filters::cors::CorsForbidden>() {
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            warp::hyper::StatusCode::FORBIDDEN,
-        ))
+    if let Some(error) = r.find::<Error>() {
+        if let Error::DatabaseQueryError(e) = error {
+            tracing::event!(tracing::Level::ERROR, "database error: {:?}", e);
+        }
+        let mut response = reply(error.status(), error.code(), error.public_message(), error.details());
+        if let Error::TooManyAttempts(seconds) = error {
+            response.headers_mut().insert(warp::http::header::RETRY_AFTER, (*seconds).into());
+        }
+        Ok(response)
+    } else if let Some(error) = r.find::<warp::filters::cors::CorsForbidden>() {
+        Ok(reply(StatusCode::FORBIDDEN, "cors_forbidden", error.to_string(), None))
     } else if let Some(error) = r.find::<warp::body::BodyDeserializeError>() {
-        Ok(warp::reply::with_status(
-            error.to_string(),
-            warp::hyper::StatusCode::NOT_FOUND,
+        Ok(reply(
+            StatusCode::BAD_REQUEST,
+            "invalid_body",
+            "Request body could not be read".to_string(),
+            Some(serde_json::json!({ "reason": error.to_string() })),
         ))
+    } else if let Some(error) = r.find::<warp::reject::InvalidQuery>() {
+        Ok(reply(StatusCode::BAD_REQUEST, "invalid_query", error.to_string(), None))
     } else if let Some(InvalidId) = r.find() {
-        Ok(warp::reply::with_status(
-            "No valid ID presented".to_string(),
-            warp::hyper::StatusCode::UNPROCESSABLE_ENTITY,
-        ))
-    } else if let Some(Error::MissingParameters) = r.find() {
-        Ok(warp::reply::with_status(
-            "Missing Parameters".to_string(),
-            warp::hyper::StatusCode::EXPECTATION_FAILED,
-        ))
-    } else if let Some(Error::InvalidRange) = r.find() {
-        Ok(warp::reply::with_status(
-            "Invalid range".to_string(),
-            warp::hyper::StatusCode::RANGE_NOT_SATISFIABLE,
-        ))
-    } else if let Some(Error::ParseError(err)) = r.find() {
-        Ok(warp::reply::with_status(
-            format!("Parse error: {}", err),
-            warp::hyper::StatusCode::EXPECTATION_FAILED,
-        ))
-    } else if let Some(Error::QuestionNotFound) = r.find() {
-        Ok(warp::reply::with_status(
-            "Question not found".to_string(),
-            warp::hyper::StatusCode::NOT_FOUND,
-        ))
-    } else if let Some(Error::WrongPassword) = r.find() {
-        Ok(warp::reply::with_status(
-            "Wrong E-Mail/Password combination".to_string(),
-            warp::hyper::StatusCode::UNAUTHORIZED,
-        ))
-    } else if let Some(Error::Unauthorized) = r.find() {
-        Ok(warp::reply::with_status(
-            "Unauthorized".to_string(),
-            warp::hyper::StatusCode::UNAUTHORIZED,
-        ))
-    } else if let Some(Error::TokenError) = r.find() {
-        Ok(warp::reply::with_status(
-            "Token Error".to_string(),
-            warp::hyper::StatusCode::UNAUTHORIZED,
-        ))
-    } else if let Some(Error::DatabaseQueryError(e)) = r.find() {
-        match e {
-            sqlx::Error::Database(err) => {
-                if err.code().unwrap().parse::<u32>().unwrap() == DUPLICATE_KEY {
-                    Ok(warp::reply::with_status(
-                        "Account already exists".to_string(),
-                        warp::hyper::StatusCode::UNPROCESSABLE_ENTITY,
-                    ))
-                } else {
-                    Ok(warp::reply::with_status(
-                        "Database Query Error".to_string(),
-                        warp::hyper::StatusCode::INTERNAL_SERVER_ERROR,
-                    ))
-                }
-            },
-            _ => {
-                Ok(warp::reply::with_status(
-                    "Database Query Error".to_string(),
-                    warp::hyper::StatusCode::INTERNAL_SERVER_ERROR,
-                ))
-            }
+        Ok(reply(StatusCode::BAD_REQUEST, "invalid_id", "No valid ID presented".to_string(), None))
+    } else if let Some(error) = r.find::<warp::reject::MissingHeader>() {
+        if error.name().eq_ignore_ascii_case("authorization") {
+            Ok(reply(StatusCode::UNAUTHORIZED, "invalid_token", "Token Error".to_string(), None))
+        } else {
+            Ok(reply(
+                StatusCode::BAD_REQUEST,
+                "missing_header",
+                error.to_string(),
+                Some(serde_json::json!({ "header": error.name() })),
+            ))
         }
-    } else if let Some(Error::ExternalAPIError(_)) = r.find() {
-        Ok(warp::reply::with_status(
-            "External api error".to_string(),
-            warp::hyper::StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(Error::ClientError(_)) = r.find() {
-        Ok(warp::reply::with_status(
-            "Internal server error".to_string(),
-            warp::hyper::StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(Error::ServerError(_)) = r.find() {
-        Ok(warp::reply::with_status(
-            "Internal server error".to_string(),
-            warp::hyper::StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    } else if let Some(Error::ArgonLibraryError(_)) = r.find() {
-        Ok(warp::reply::with_status(
-            "Internal server error".to_string(),
-            warp::hyper::StatusCode::INTERNAL_SERVER_ERROR,
-        ))
-    }  else {
-        Ok(warp::reply::with_status(
-            "Route not found".to_string(),
-            warp::hyper::StatusCode::NOT_FOUND,
-        ))
+    } else {
+        Ok(reply(StatusCode::NOT_FOUND, "not_found", "Route not found".to_string(), None))
+    }
+}
+
+
+#[cfg(test)]
+mod error_tests {
+    use super::*;
+
+    async fn respond(rejection: warp::Rejection) -> (StatusCode, serde_json::Value) {
+        let response = return_error(rejection).await.unwrap().into_response();
+        let status = response.status();
+        let body = warp::hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn json_body_with_code() {
+        // act
+        let (status, body) = respond(warp::reject::custom(Error::InvalidParameter("sort".to_string()))).await;
+
+        // assert
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "invalid_parameter");
+        assert_eq!(body["message"], "Invalid parameter: sort");
+        assert_eq!(body["details"]["parameter"], "sort");
+        assert_eq!(body["request_id"].as_str().unwrap().len(), 36);
+    }
+
+    #[tokio::test]
+    async fn status_codes() {
+        // arrange
+        let cases = [
+            (Error::MissingParameters, StatusCode::BAD_REQUEST, "missing_parameters"),
+            (Error::InvalidRange, StatusCode::BAD_REQUEST, "invalid_range"),
+            (Error::QuestionNotFound, StatusCode::NOT_FOUND, "question_not_found"),
+            (Error::AnswerNotFound, StatusCode::NOT_FOUND, "answer_not_found"),
+            (Error::CommentNotFound, StatusCode::NOT_FOUND, "comment_not_found"),
+            (Error::WrongPassword, StatusCode::UNAUTHORIZED, "wrong_credentials"),
+            (Error::Unauthorized, StatusCode::UNAUTHORIZED, "unauthorized"),
+            (Error::TokenError, StatusCode::UNAUTHORIZED, "invalid_token"),
+            (Error::Forbidden, StatusCode::FORBIDDEN, "forbidden"),
+            (Error::EmailNotVerified, StatusCode::FORBIDDEN, "email_not_verified"),
+            (Error::OwnContentVote, StatusCode::FORBIDDEN, "own_content_vote"),
+            (Error::MailError("timeout".to_string()), StatusCode::BAD_GATEWAY, "mail_error"),
+            (Error::IdentityProviderError("timeout".to_string()), StatusCode::BAD_GATEWAY, "identity_provider_error"),
+            (Error::AccountAlreadyExists, StatusCode::CONFLICT, "account_exists"),
+            (Error::TwoFactorEnabled, StatusCode::CONFLICT, "two_factor_enabled"),
+            (Error::TwoFactorNotEnabled, StatusCode::CONFLICT, "two_factor_not_enabled"),
+            (Error::DatabaseQueryError(sqlx::Error::RowNotFound), StatusCode::NOT_FOUND, "not_found"),
+            (Error::DatabaseQueryError(sqlx::Error::PoolTimedOut), StatusCode::INTERNAL_SERVER_ERROR, "database_error"),
+            (
+                Error::ServerError(APILayerError { status: 503, message: "down".to_string() }),
+                StatusCode::BAD_GATEWAY,
+                "external_api_error",
+            ),
+        ];
+
+        for (error, expected_status, expected_code) in cases {
+            // act
+            let (status, body) = respond(warp::reject::custom(error)).await;
+
+            // assert
+            assert_eq!(status, expected_status);
+            assert_eq!(body["code"], expected_code);
+        }
+    }
+
+    #[tokio::test]
+    async fn internals_are_not_leaked() {
+        // act
+        let (_, body) = respond(warp::reject::custom(Error::ClientError(APILayerError {
+            status: 401,
+            message: "Invalid API key abc123".to_string(),
+        }))).await;
+
+        // assert
+        assert_eq!(body["message"], "Profanity check is unavailable");
+        assert_eq!(body["details"]["upstream_status"], 401);
+    }
+
+    #[tokio::test]
+    async fn field_errors() {
+        // act
+        let (status, body) = respond(warp::reject::custom(Error::ValidationFailed(vec![FieldError {
+            field: "password".to_string(),
+            code: "too_short".to_string(),
+            message: "must have at least 10 characters".to_string(),
+        }]))).await;
+
+        // assert
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["details"]["fields"][0]["field"], "password");
+        assert_eq!(body["details"]["fields"][0]["code"], "too_short");
+    }
+
+    #[tokio::test]
+    async fn retry_after_header() {
+        // act
+        let response = return_error(warp::reject::custom(Error::TooManyAttempts(30))).await.unwrap().into_response();
+
+        // assert
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[warp::http::header::RETRY_AFTER], "30");
+    }
+
+    #[tokio::test]
+    async fn unknown_route() {
+        // act
+        let (status, body) = respond(warp::reject::not_found()).await;
+
+        // assert
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["code"], "not_found");
+        assert!(body["details"].is_null());
     }
 }
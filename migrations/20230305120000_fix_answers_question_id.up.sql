@@ -0,0 +1,17 @@
+-- Add up migration script here
+ALTER TABLE answers
+RENAME COLUMN corresponding_question TO question_id;
+
+ALTER TABLE answers
+DROP CONSTRAINT IF EXISTS answers_corresponding_question_fkey;
+
+-- Answers could never be posted against the old column, so any row without a
+-- question is unreachable and can safely be dropped before tightening it.
+DELETE FROM answers WHERE question_id IS NULL;
+
+ALTER TABLE answers
+ALTER COLUMN question_id SET NOT NULL;
+
+ALTER TABLE answers
+ADD CONSTRAINT answers_question_id_fkey
+FOREIGN KEY (question_id) REFERENCES questions (id) ON DELETE CASCADE;
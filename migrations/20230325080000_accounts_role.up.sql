@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE accounts
+    ADD COLUMN IF NOT EXISTS role VARCHAR(16) NOT NULL DEFAULT 'user'
+    CHECK (role IN ('user', 'moderator', 'admin'));
@@ -0,0 +1,11 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS sessions (
+    id VARCHAR(36) PRIMARY KEY,
+    account_id integer NOT NULL,
+    refresh_token_hash VARCHAR(64) NOT NULL UNIQUE,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    expires_on TIMESTAMP NOT NULL,
+    revoked_on TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS sessions_account_id_idx ON sessions (account_id);
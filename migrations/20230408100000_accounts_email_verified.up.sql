@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE accounts ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- accounts registered before verification existed keep working
+UPDATE accounts SET email_verified = TRUE;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+-- addresses stay lowercased; the original case isn't kept anywhere
+DROP INDEX IF EXISTS accounts_email_lower_idx;
@@ -0,0 +1,28 @@
+-- Add up migration script here
+
+-- accounts whose addresses differ only in case or surrounding spaces can't
+-- be told apart any more; they have to be merged or renamed by hand first
+DO $$
+DECLARE
+    duplicates TEXT;
+BEGIN
+    SELECT string_agg(format('%s (accounts %s)', address, ids), ', ')
+    INTO duplicates
+    FROM (
+        SELECT LOWER(TRIM(email)) AS address, string_agg(id::TEXT, ', ' ORDER BY id) AS ids
+        FROM accounts
+        GROUP BY LOWER(TRIM(email))
+        HAVING COUNT(*) > 1
+    ) AS clashes;
+
+    IF duplicates IS NOT NULL THEN
+        RAISE EXCEPTION 'accounts share an e-mail address apart from case: %', duplicates
+            USING HINT = 'Merge the accounts or change all but one address, then run the migration again.';
+    END IF;
+END
+$$;
+
+-- stored the way registration normalizes new addresses
+UPDATE accounts SET email = LOWER(TRIM(email)) WHERE email <> LOWER(TRIM(email));
+
+CREATE UNIQUE INDEX IF NOT EXISTS accounts_email_lower_idx ON accounts (LOWER(email));
@@ -0,0 +1,5 @@
+-- Add up migration script here
+ALTER TABLE accounts
+    ADD COLUMN IF NOT EXISTS display_name VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS bio TEXT,
+    ADD COLUMN IF NOT EXISTS avatar_url TEXT;
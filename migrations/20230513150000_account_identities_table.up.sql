@@ -0,0 +1,10 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS account_identities (
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    account_id integer NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (issuer, subject)
+);
+
+CREATE INDEX IF NOT EXISTS account_identities_account_id_idx ON account_identities (account_id);
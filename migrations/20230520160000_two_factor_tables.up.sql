@@ -0,0 +1,16 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS two_factor (
+    account_id integer PRIMARY KEY,
+    secret VARCHAR(64) NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    last_used_step BIGINT
+);
+
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id serial PRIMARY KEY,
+    account_id integer NOT NULL,
+    code_hash VARCHAR(64) NOT NULL,
+    used_on TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS recovery_codes_account_id_idx ON recovery_codes (account_id);
@@ -0,0 +1,9 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS answer_votes;
+DROP TABLE IF EXISTS question_votes;
+
+ALTER TABLE answers
+DROP COLUMN IF EXISTS score;
+
+ALTER TABLE questions
+DROP COLUMN IF EXISTS score;
@@ -0,0 +1,27 @@
+-- Add up migration script here
+ALTER TABLE questions
+ADD COLUMN IF NOT EXISTS score integer NOT NULL DEFAULT 0;
+
+ALTER TABLE answers
+ADD COLUMN IF NOT EXISTS score integer NOT NULL DEFAULT 0;
+
+-- value is 1 for an up and -1 for a down vote, cleared votes are deleted
+CREATE TABLE IF NOT EXISTS question_votes (
+    question_id integer NOT NULL REFERENCES questions (id) ON DELETE CASCADE,
+    account_id integer NOT NULL,
+    value smallint NOT NULL CHECK (value IN (-1, 1)),
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS question_votes_question_id_account_id_idx ON question_votes (question_id, account_id);
+CREATE INDEX IF NOT EXISTS question_votes_account_id_idx ON question_votes (account_id);
+
+CREATE TABLE IF NOT EXISTS answer_votes (
+    answer_id integer NOT NULL REFERENCES answers (id) ON DELETE CASCADE,
+    account_id integer NOT NULL,
+    value smallint NOT NULL CHECK (value IN (-1, 1)),
+    created_on TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS answer_votes_answer_id_account_id_idx ON answer_votes (answer_id, account_id);
+CREATE INDEX IF NOT EXISTS answer_votes_account_id_idx ON answer_votes (account_id);
@@ -0,0 +1,15 @@
+-- Add up migration script here
+-- every comment belongs to exactly one question or answer
+CREATE TABLE IF NOT EXISTS comments (
+    id serial PRIMARY KEY,
+    content TEXT NOT NULL,
+    question_id integer REFERENCES questions (id) ON DELETE CASCADE,
+    answer_id integer REFERENCES answers (id) ON DELETE CASCADE,
+    account_id integer NOT NULL,
+    created_on TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK ((question_id IS NULL) <> (answer_id IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS comments_question_id_idx ON comments (question_id);
+CREATE INDEX IF NOT EXISTS comments_answer_id_idx ON comments (answer_id);
+CREATE INDEX IF NOT EXISTS comments_account_id_idx ON comments (account_id);
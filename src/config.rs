@@ -1,5 +1,44 @@
 use std::env;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+
+/// Which implementation censors questions and answers
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfanityFilterKind {
+    /// APILayer bad words API, needs BAD_WORDS_API_KEY
+    ApiLayer,
+    /// Local list of bad words read from --bad-words-file
+    WordList,
+    /// Leave content unchanged
+    None,
+}
+
+/// How e-mails such as verification links are delivered
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MailSenderKind {
+    /// SMTP relay configured with --smtp-host, needs SMTP_USER/SMTP_PASSWORD if it requires login
+    Smtp,
+    /// One .eml file per e-mail in --mail-dir
+    File,
+}
+
+/// What happens to the questions and answers of a deleted account
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccountDeletionKind {
+    /// Keep the content, strip every personal detail from the account
+    Anonymize,
+    /// Delete the content together with the account
+    Cascade,
+}
+
+/// Which kind of PASETO access tokens are issued
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessTokenFormat {
+    /// v2.local, encrypted with the PASETO_KEYS, readable by this service only
+    Local,
+    /// v2.public, signed with the PASETO_SIGNING_KEYS, verifiable by anyone
+    /// with the keys from GET /.well-known/paseto-keys
+    Public,
+}
 
 /// Q&A web service API
 #[derive(Parser, Debug)]
@@ -26,29 +65,115 @@ pub struct Config {
     /// Database name
     #[clap(long, default_value = "rustwebdev")]
     pub db_name: String,
+    /// Keep all data in memory (seeded from questions.json) instead of Postgres
+    #[clap(long)]
+    pub in_memory: bool,
+    /// Which profanity filter to use
+    #[clap(long, value_enum, default_value = "api-layer")]
+    pub profanity_filter: ProfanityFilterKind,
+    /// API key for the APILayer bad words API
+    #[clap(long)]
+    pub bad_words_api_key: Option<String>,
+    /// File with one bad word per line, used by the word-list filter
+    #[clap(long, default_value = "bad_words.txt")]
+    pub bad_words_file: String,
+    /// URL the service is reachable at, used for links in e-mails
+    #[clap(long, default_value = "http://localhost:8080")]
+    pub public_url: String,
+    /// How to send e-mails
+    #[clap(long, value_enum, default_value = "file")]
+    pub mail_sender: MailSenderKind,
+    /// Directory the file mail sender writes to
+    #[clap(long, default_value = "mail_outbox")]
+    pub mail_dir: String,
+    /// Sender address of all e-mails
+    #[clap(long, default_value = "Q&A <noreply@localhost>")]
+    pub mail_from: String,
+    /// SMTP relay host
+    #[clap(long, default_value = "localhost")]
+    pub smtp_host: String,
+    /// SMTP relay port
+    #[clap(long, default_value = "587")]
+    pub smtp_port: u16,
+    /// SMTP user, the password is read from SMTP_PASSWORD
+    #[clap(long)]
+    pub smtp_user: Option<String>,
+    #[clap(skip)]
+    pub smtp_password: Option<String>,
+    /// Failed logins in a row after which an account is locked
+    #[clap(long, default_value = "5")]
+    pub login_max_failures: u32,
+    /// Failed logins in a row after which an IP address is locked
+    #[clap(long, default_value = "50")]
+    pub login_ip_max_failures: u32,
+    /// Wait in milliseconds after a failed login, doubled with every further failure
+    #[clap(long, default_value = "500")]
+    pub login_backoff_ms: u64,
+    /// How long an account or IP address stays locked, in minutes
+    #[clap(long, default_value = "15")]
+    pub login_lockout_minutes: u64,
+    /// Memory used to hash a password with Argon2id, in KiB
+    #[clap(long, default_value = "19456")]
+    pub argon2_memory_kib: u32,
+    /// Argon2id iterations
+    #[clap(long, default_value = "2")]
+    pub argon2_iterations: u32,
+    /// Argon2id lanes
+    #[clap(long, default_value = "1")]
+    pub argon2_parallelism: u32,
+    /// Minimum number of characters of new passwords
+    #[clap(long, default_value = "10")]
+    pub password_min_length: usize,
+    /// Minimum strength of new passwords, from 0 (anything) to 4 (strong)
+    #[clap(long, default_value = "3", value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub password_min_score: u8,
+    /// What `DELETE /account` does with the questions and answers of the account
+    #[clap(long, value_enum, default_value = "anonymize")]
+    pub account_deletion: AccountDeletionKind,
+    /// Kind of access tokens issued; tokens of either kind are accepted
+    #[clap(long, value_enum, default_value = "local")]
+    pub access_token_format: AccessTokenFormat,
+    /// Issuer URL of an OpenID Connect provider to sign in with, disabled if unset
+    #[clap(long)]
+    pub oidc_issuer: Option<String>,
+    /// Client id registered with the OpenID Connect provider, the secret is
+    /// read from OIDC_CLIENT_SECRET if the provider requires one
+    #[clap(long)]
+    pub oidc_client_id: Option<String>,
+    #[clap(skip)]
+    pub oidc_client_secret: Option<String>,
 }
 
 impl Config {
     pub fn new() -> Result<Config, handle_errors::Error> {
         let config = Config::parse();
 
-        if let Err(_) = env::var("BAD_WORDS_API_KEY") {
+        let bad_words_api_key = env::var("BAD_WORDS_API_KEY")
+            .ok()
+            .or(config.bad_words_api_key);
+        if config.profanity_filter == ProfanityFilterKind::ApiLayer && bad_words_api_key.is_none() {
             panic!("BadWords API key not set");
         }
+        let bad_words_file = env::var("BAD_WORDS_FILE")
+            .unwrap_or(config.bad_words_file);
 
-        if let Err(_) = env::var("PASETO_KEY") {
+        if env::var("PASETO_KEY").is_err() && env::var("PASETO_KEYS").is_err() {
             panic!("PASETO_KEY not set");
         }
+        if config.access_token_format == AccessTokenFormat::Public && env::var("PASETO_SIGNING_KEYS").is_err() {
+            panic!("PASETO_SIGNING_KEYS not set");
+        }
 
         let port = std::env::var("PORT")
             .ok()
             .map(|val| val.parse::<u16>())
             .unwrap_or(Ok(config.port))
-            .map_err(|e| handle_errors::Error::ParseError(e))?;
+            .map_err(handle_errors::Error::ParseError)?;
 
         let db_user = env::var("POSTGRES_USER")
             .unwrap_or(config.db_user.to_owned());
-        let db_password = env::var("POSTGRES_PASSWORD").unwrap();
+        let db_password = env::var("POSTGRES_PASSWORD")
+            .unwrap_or(config.db_password.to_owned());
         let db_host = env::var("POSTGRES_HOST")
             .unwrap_or(config.db_host.to_owned());
         let db_port = env::var("POSTGRES_PORT")
@@ -56,6 +181,23 @@ impl Config {
         let db_name = env::var("POSTGRES_DB")
             .unwrap_or(config.db_name.to_owned());
 
+        let public_url = env::var("PUBLIC_URL")
+            .unwrap_or(config.public_url);
+        let smtp_user = env::var("SMTP_USER")
+            .ok()
+            .or(config.smtp_user);
+        let smtp_password = env::var("SMTP_PASSWORD").ok();
+        let oidc_issuer = env::var("OIDC_ISSUER")
+            .ok()
+            .or(config.oidc_issuer);
+        let oidc_client_id = env::var("OIDC_CLIENT_ID")
+            .ok()
+            .or(config.oidc_client_id);
+        if oidc_issuer.is_some() && oidc_client_id.is_none() {
+            panic!("OIDC client id not set");
+        }
+        let oidc_client_secret = env::var("OIDC_CLIENT_SECRET").ok();
+
         Ok(Config {
             log_level: config.log_level,
             port,
@@ -66,6 +208,32 @@ impl Config {
                 handle_errors::Error::ParseError(e)
             })?,
             db_name,
+            in_memory: config.in_memory,
+            profanity_filter: config.profanity_filter,
+            bad_words_api_key,
+            bad_words_file,
+            public_url: public_url.trim_end_matches('/').to_string(),
+            mail_sender: config.mail_sender,
+            mail_dir: config.mail_dir,
+            mail_from: config.mail_from,
+            smtp_host: config.smtp_host,
+            smtp_port: config.smtp_port,
+            smtp_user,
+            smtp_password,
+            login_max_failures: config.login_max_failures,
+            login_ip_max_failures: config.login_ip_max_failures,
+            login_backoff_ms: config.login_backoff_ms,
+            login_lockout_minutes: config.login_lockout_minutes,
+            argon2_memory_kib: config.argon2_memory_kib,
+            argon2_iterations: config.argon2_iterations,
+            argon2_parallelism: config.argon2_parallelism,
+            password_min_length: config.password_min_length,
+            password_min_score: config.password_min_score,
+            account_deletion: config.account_deletion,
+            access_token_format: config.access_token_format,
+            oidc_issuer: oidc_issuer.map(|issuer| issuer.trim_end_matches('/').to_string()),
+            oidc_client_id,
+            oidc_client_secret,
         })
     }
 }
@@ -78,13 +246,13 @@ mod config_tests {
     #[test]
     fn test_config() {
         // Act - no environment exists
-        let result = std::panic::catch_unwind(|| Config::new());
+        let result = std::panic::catch_unwind(Config::new);
         // Assert
         assert!(result.is_err());
 
         // Arrange
         env::set_var("BAD_WORDS_API_KEY", "yes");
-        env::set_var("PASETO_KEY", "yes");
+        env::set_var("PASETO_KEY", "RANDOM WORDS WINTER MACINTOSH PC");
         env::set_var("POSTGRES_USER", "user");
         env::set_var("POSTGRES_PASSWORD", "pass");
         env::set_var("POSTGRES_HOST", "localhost");
@@ -101,5 +269,10 @@ mod config_tests {
         assert_eq!(config.db_name, String::from("rustwebdev"));
         assert_eq!(config.db_port, 5432_u16);
         assert_eq!(config.port, 8080_u16);
+        assert_eq!(config.profanity_filter, ProfanityFilterKind::ApiLayer);
+        assert_eq!(config.bad_words_api_key, Some(String::from("yes")));
+        assert_eq!(config.public_url, String::from("http://localhost:8080"));
+        assert_eq!(config.mail_sender, MailSenderKind::File);
+        assert_eq!(config.login_max_failures, 5);
     }
 }
\ No newline at end of file
@@ -0,0 +1,354 @@
+//! Keys PASETO tokens are issued and checked with, loaded once at startup.
+//!
+//! `PASETO_KEYS` lists the 32 byte keys for `v2.local` tokens as `id:key`
+//! pairs separated by commas. The first key encrypts new tokens, the others
+//! only decrypt tokens issued before the last rotation. A lone `PASETO_KEY`
+//! is the key `default`. `PASETO_SIGNING_KEYS` lists the Ed25519 keys for
+//! `v2.public` tokens the same way, each a base64url encoded 32 byte seed.
+//! Tokens name their key in the footer, `{"kid":"<id>"}`.
+
+use std::env;
+
+use base64::Engine;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AccessTokenFormat;
+
+/// Id of the key given as `PASETO_KEY`.
+pub const DEFAULT_KEY_ID: &str = "default";
+const KEY_LENGTH: usize = 32;
+
+#[derive(Debug)]
+struct LocalKey {
+    id: String,
+    key: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct SigningKey {
+    id: String,
+    key_pair: Ed25519KeyPair,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Footer {
+    kid: String,
+}
+
+/// Key to verify `v2.public` tokens with, as listed by
+/// `GET /.well-known/paseto-keys`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PublicKey {
+    pub kid: String,
+    pub version: String,
+    pub purpose: String,
+    /// Base64url encoded Ed25519 public key.
+    pub public_key: String,
+}
+
+/// Reply to `GET /.well-known/paseto-keys`, the current signing key first.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PublicKeys {
+    pub keys: Vec<PublicKey>,
+}
+
+#[derive(Debug)]
+pub struct Keyring {
+    /// Current key first.
+    local_keys: Vec<LocalKey>,
+    /// Current key first.
+    signing_keys: Vec<SigningKey>,
+    access_token_format: AccessTokenFormat,
+}
+
+impl Keyring {
+    /// Keyring of `(id, key)` and `(id, seed)` pairs, the current ones first.
+    pub fn new(
+        local_keys: Vec<(String, Vec<u8>)>,
+        signing_keys: Vec<(String, Vec<u8>)>,
+        access_token_format: AccessTokenFormat,
+    ) -> Result<Self, String> {
+        if local_keys.is_empty() {
+            return Err("PASETO_KEY not set".to_string());
+        }
+        if access_token_format == AccessTokenFormat::Public && signing_keys.is_empty() {
+            return Err("PASETO_SIGNING_KEYS not set".to_string());
+        }
+        let mut ids = std::collections::HashSet::new();
+        for (id, _) in local_keys.iter().chain(signing_keys.iter()) {
+            if id.is_empty() || !ids.insert(id.as_str()) {
+                return Err(format!("PASETO key id {:?} is empty or used twice", id));
+            }
+        }
+
+        let local_keys = local_keys
+            .into_iter()
+            .map(|(id, key)| match key.len() {
+                KEY_LENGTH => Ok(LocalKey { id, key }),
+                _ => Err(format!("PASETO key {} must be {} bytes long", id, KEY_LENGTH)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let signing_keys = signing_keys
+            .into_iter()
+            .map(|(id, seed)| match Ed25519KeyPair::from_seed_unchecked(&seed) {
+                Ok(key_pair) => Ok(SigningKey { id, key_pair }),
+                Err(_) => Err(format!("PASETO signing key {} must be a {} byte seed", id, KEY_LENGTH)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Keyring { local_keys, signing_keys, access_token_format })
+    }
+
+    /// Keyring from the `PASETO_*` environment variables, see the module docs.
+    pub fn from_env(access_token_format: AccessTokenFormat) -> Result<Self, String> {
+        let local_keys = match env::var("PASETO_KEYS") {
+            Ok(keys) => parse_keys(&keys)?
+                .into_iter()
+                .map(|(id, key)| (id, key.into_bytes()))
+                .collect(),
+            Err(_) => match env::var("PASETO_KEY") {
+                Ok(key) => vec![(DEFAULT_KEY_ID.to_string(), key.into_bytes())],
+                Err(_) => Vec::new(),
+            },
+        };
+        let signing_keys = match env::var("PASETO_SIGNING_KEYS") {
+            Ok(keys) => parse_keys(&keys)?
+                .into_iter()
+                .map(|(id, seed)| match base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(seed.trim_end_matches('=')) {
+                    Ok(seed) => Ok((id, seed)),
+                    Err(_) => Err(format!("PASETO signing key {} is not base64url encoded", id)),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Err(_) => Vec::new(),
+        };
+
+        Keyring::new(local_keys, signing_keys, access_token_format)
+    }
+
+    pub fn access_token_format(&self) -> AccessTokenFormat {
+        self.access_token_format
+    }
+
+    /// Token carrying `claims`, valid from now for `ttl`, encrypted or
+    /// signed with the current key for `format`.
+    pub fn issue(
+        &self,
+        format: AccessTokenFormat,
+        claims: &[(&str, serde_json::Value)],
+        ttl: chrono::Duration,
+    ) -> String {
+        let current_date_time = chrono::Utc::now();
+        let dt = current_date_time + ttl;
+        let kid = match format {
+            AccessTokenFormat::Local => &self.local_keys[0].id,
+            AccessTokenFormat::Public => &self.signing_keys.first().expect("no PASETO signing key").id,
+        };
+        let footer = serde_json::to_string(&Footer { kid: kid.clone() }).expect("footer is serializable");
+
+        let mut builder = paseto::tokens::PasetoBuilder::new();
+        let mut builder = builder
+            .set_expiration(&dt)
+            .set_not_before(&current_date_time)
+            .set_footer(&footer);
+        for (name, value) in claims {
+            builder = builder.set_claim(name, value.clone());
+        }
+        match format {
+            AccessTokenFormat::Local => builder.set_encryption_key(&self.local_keys[0].key),
+            AccessTokenFormat::Public => builder.set_ed25519_key(&self.signing_keys[0].key_pair),
+        }.build().expect("Failed to create token")
+    }
+
+    /// Claims of a valid, unexpired token of either format.
+    pub fn validate(&self, token: &str) -> Result<serde_json::Value, handle_errors::Error> {
+        if token.starts_with("v2.public.") {
+            self.validate_public(token)
+        } else {
+            self.validate_local(token)
+        }
+    }
+
+    /// Like `validate`, but only for `v2.local` tokens, whose claims are
+    /// not readable by anyone else.
+    pub fn validate_local(&self, token: &str) -> Result<serde_json::Value, handle_errors::Error> {
+        let footer = footer(token)?;
+        // tokens from before key ids were introduced have no footer
+        let kid = footer.as_deref().map(kid).transpose()?;
+        self.local_keys
+            .iter()
+            .filter(|key| kid.as_ref().is_none_or(|kid| &key.id == kid))
+            .find_map(|key| paseto::tokens::validate_local_token(
+                token,
+                footer.as_deref(),
+                &key.key,
+                &paseto::tokens::TimeBackend::Chrono,
+            ).ok())
+            .ok_or(handle_errors::Error::TokenError)
+    }
+
+    fn validate_public(&self, token: &str) -> Result<serde_json::Value, handle_errors::Error> {
+        let footer = footer(token)?.ok_or(handle_errors::Error::TokenError)?;
+        let kid = kid(&footer)?;
+        let key = self.signing_keys
+            .iter()
+            .find(|key| key.id == kid)
+            .ok_or(handle_errors::Error::TokenError)?;
+        paseto::tokens::validate_public_token(
+            token,
+            Some(&footer),
+            &paseto::tokens::PasetoPublicKey::ED25519PublicKey(key.key_pair.public_key().as_ref()),
+            &paseto::tokens::TimeBackend::Chrono,
+        ).map_err(|_| handle_errors::Error::TokenError)
+    }
+
+    /// Keys other services verify `v2.public` tokens with.
+    pub fn public_keys(&self) -> PublicKeys {
+        PublicKeys {
+            keys: self.signing_keys
+                .iter()
+                .map(|key| PublicKey {
+                    kid: key.id.clone(),
+                    version: "v2".to_string(),
+                    purpose: "public".to_string(),
+                    public_key: base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(key.key_pair.public_key().as_ref()),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `id:key` pairs separated by commas.
+fn parse_keys(keys: &str) -> Result<Vec<(String, String)>, String> {
+    keys.split(',')
+        .map(|entry| match entry.split_once(':') {
+            Some((id, key)) => Ok((id.trim().to_string(), key.to_string())),
+            None => Err(format!("PASETO key {:?} is not an id:key pair", entry.split(':').next().unwrap_or_default())),
+        })
+        .collect()
+}
+
+/// Decoded footer of the token, if it has one.
+fn footer(token: &str) -> Result<Option<String>, handle_errors::Error> {
+    match token.split('.').nth(3) {
+        Some(footer) => base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(footer)
+            .ok()
+            .and_then(|footer| String::from_utf8(footer).ok())
+            .map(Some)
+            .ok_or(handle_errors::Error::TokenError),
+        None => Ok(None),
+    }
+}
+
+fn kid(footer: &str) -> Result<String, handle_errors::Error> {
+    serde_json::from_str::<Footer>(footer)
+        .map(|footer| footer.kid)
+        .map_err(|_| handle_errors::Error::TokenError)
+}
+
+
+#[cfg(test)]
+mod keyring_tests {
+    use super::*;
+
+    const OLD_KEY: &[u8] = b"RANDOM WORDS WINTER MACINTOSH PC";
+    const NEW_KEY: &[u8] = b"ANOTHER 32 BYTES OF KEY MATERIAL";
+    const SEED: [u8; 32] = [7; 32];
+
+    fn keyring(local_keys: &[(&str, &[u8])], signing_keys: &[(&str, &[u8])], format: AccessTokenFormat) -> Keyring {
+        let pairs = |keys: &[(&str, &[u8])]| keys.iter().map(|(id, key)| (id.to_string(), key.to_vec())).collect();
+        Keyring::new(pairs(local_keys), pairs(signing_keys), format).unwrap()
+    }
+
+    fn claims() -> Vec<(&'static str, serde_json::Value)> {
+        vec![("account_id", serde_json::json!(1))]
+    }
+
+    #[test]
+    fn rotation_keeps_previous_keys_working() {
+        // arrange
+        let before = keyring(&[("k1", OLD_KEY)], &[], AccessTokenFormat::Local);
+        let during = keyring(&[("k2", NEW_KEY), ("k1", OLD_KEY)], &[], AccessTokenFormat::Local);
+        let after = keyring(&[("k2", NEW_KEY)], &[], AccessTokenFormat::Local);
+
+        // act
+        let old_token = before.issue(AccessTokenFormat::Local, &claims(), chrono::Duration::minutes(5));
+        let new_token = during.issue(AccessTokenFormat::Local, &claims(), chrono::Duration::minutes(5));
+
+        // assert
+        assert_eq!(footer(&old_token).unwrap().as_deref(), Some(r#"{"kid":"k1"}"#));
+        assert_eq!(footer(&new_token).unwrap().as_deref(), Some(r#"{"kid":"k2"}"#));
+        assert_eq!(during.validate(&old_token).unwrap()["account_id"], 1);
+        assert_eq!(during.validate(&new_token).unwrap()["account_id"], 1);
+        assert!(after.validate(&new_token).is_ok());
+        assert!(after.validate(&old_token).is_err());
+        assert!(before.validate(&new_token).is_err());
+    }
+
+    #[test]
+    fn accepts_tokens_without_key_id() {
+        // arrange
+        let keyring = keyring(&[("k2", NEW_KEY), ("k1", OLD_KEY)], &[], AccessTokenFormat::Local);
+        let token = paseto::tokens::PasetoBuilder::new()
+            .set_encryption_key(OLD_KEY)
+            .set_expiration(&(chrono::Utc::now() + chrono::Duration::minutes(5)))
+            .set_claim("account_id", serde_json::json!(1))
+            .build()
+            .unwrap();
+        let expired = paseto::tokens::PasetoBuilder::new()
+            .set_encryption_key(OLD_KEY)
+            .set_expiration(&(chrono::Utc::now() - chrono::Duration::minutes(5)))
+            .build()
+            .unwrap();
+
+        // assert
+        assert_eq!(keyring.validate(&token).unwrap()["account_id"], 1);
+        assert!(keyring.validate(&expired).is_err());
+        assert!(keyring.validate("v2.local.garbage.bm90IGpzb24").is_err());
+    }
+
+    #[test]
+    fn public_tokens_verify_with_the_published_key() {
+        // arrange
+        let signer = keyring(&[("k1", OLD_KEY)], &[("s1", &SEED[..])], AccessTokenFormat::Public);
+        let other = keyring(&[("k1", OLD_KEY)], &[("s1", &[8; 32][..])], AccessTokenFormat::Public);
+
+        // act
+        let token = signer.issue(signer.access_token_format(), &claims(), chrono::Duration::minutes(5));
+        let published = signer.public_keys();
+        let public_key = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(&published.keys[0].public_key).unwrap();
+        let verified = paseto::tokens::validate_public_token(
+            &token,
+            Some(r#"{"kid":"s1"}"#),
+            &paseto::tokens::PasetoPublicKey::ED25519PublicKey(&public_key),
+            &paseto::tokens::TimeBackend::Chrono,
+        );
+
+        // assert
+        assert!(token.starts_with("v2.public."));
+        assert_eq!(published.keys[0].kid, "s1");
+        assert_eq!(verified.unwrap()["account_id"], 1);
+        assert_eq!(signer.validate(&token).unwrap()["account_id"], 1);
+        assert!(signer.validate_local(&token).is_err());
+        assert!(other.validate(&token).is_err());
+    }
+
+    #[test]
+    fn rejects_unusable_keys() {
+        // arrange
+        let key = |id: &str, key: &[u8]| (id.to_string(), key.to_vec());
+
+        // assert
+        assert!(Keyring::new(vec![], vec![], AccessTokenFormat::Local).is_err());
+        assert!(Keyring::new(vec![key("k1", b"short")], vec![], AccessTokenFormat::Local).is_err());
+        assert!(Keyring::new(vec![key("k1", OLD_KEY), key("k1", NEW_KEY)], vec![], AccessTokenFormat::Local).is_err());
+        assert!(Keyring::new(vec![key("k1", OLD_KEY)], vec![], AccessTokenFormat::Public).is_err());
+        assert!(Keyring::new(vec![key("k1", OLD_KEY)], vec![key("s1", b"short")], AccessTokenFormat::Public).is_err());
+        assert_eq!(parse_keys("k2:new,k1:old:with:colons").unwrap(), vec![
+            ("k2".to_string(), "new".to_string()),
+            ("k1".to_string(), "old:with:colons".to_string()),
+        ]);
+        assert!(parse_keys("no id").is_err());
+    }
+}
@@ -2,14 +2,20 @@ use warp::{http::Method, Filter};
 use tracing_subscriber::fmt::format::FmtSpan;
 
 pub mod config;
-mod store;
+pub mod keyring;
+pub mod store;
 mod routes;
-mod profanity;
-mod types;
+pub mod profanity;
+pub mod mail;
+pub mod oidc;
+pub mod throttle;
+pub mod totp;
+pub mod types;
+pub mod validation;
 
 pub async fn setup_store(
     config: &config::Config
-) -> Result<store::Store, handle_errors::Error> {
+) -> Result<store::DynStore, handle_errors::Error> {
     let log_filter = std::env::var("RUST_LOG")
         .unwrap_or_else(|_| "web_questions=info,warp=error".to_owned());
 
@@ -18,6 +24,11 @@ pub async fn setup_store(
         .with_span_events(FmtSpan::CLOSE)
         .init();
 
+    if config.in_memory {
+        tracing::event!(tracing::Level::INFO, "using in-memory store seeded from questions.json");
+        return Ok(std::sync::Arc::new(store::InMemoryStore::seeded()));
+    }
+
     let store = store::Store::new(
         &format!(
             "postgres://{}:{}@{}:{}/{}",
@@ -29,13 +40,91 @@ pub async fn setup_store(
         )
     ).await;
 
-    Ok(store)
+    Ok(std::sync::Arc::new(store))
+}
+
+pub fn setup_profanity_filter(
+    config: &config::Config
+) -> profanity::DynProfanityFilter {
+    match config.profanity_filter {
+        config::ProfanityFilterKind::ApiLayer => std::sync::Arc::new(
+            profanity::ApiLayerFilter::new(
+                config.bad_words_api_key.clone().expect("BadWords API key not set")
+            )
+        ),
+        config::ProfanityFilterKind::WordList => std::sync::Arc::new(
+            profanity::WordListFilter::from_file(&config.bad_words_file)
+                .unwrap_or_else(|e| panic!("Unable to read {}: {}", config.bad_words_file, e))
+        ),
+        config::ProfanityFilterKind::None => std::sync::Arc::new(profanity::NoopFilter),
+    }
+}
+
+pub fn setup_mail_sender(
+    config: &config::Config
+) -> Result<mail::DynMailSender, handle_errors::Error> {
+    match config.mail_sender {
+        config::MailSenderKind::Smtp => {
+            let credentials = config.smtp_user.clone().map(|user| {
+                (user, config.smtp_password.clone().unwrap_or_default())
+            });
+            Ok(std::sync::Arc::new(mail::SmtpMailSender::new(
+                &config.smtp_host,
+                config.smtp_port,
+                credentials,
+                config.mail_from.clone(),
+            )?))
+        },
+        config::MailSenderKind::File => Ok(std::sync::Arc::new(mail::FileMailSender::new(&config.mail_dir))),
+    }
 }
 
-async fn build_routes(
-    store: store::Store
+pub fn setup_keyring(
+    config: &config::Config
+) -> std::sync::Arc<keyring::Keyring> {
+    std::sync::Arc::new(
+        keyring::Keyring::from_env(config.access_token_format)
+            .unwrap_or_else(|e| panic!("PASETO keys can't be loaded: {}", e))
+    )
+}
+
+pub async fn build_routes(
+    config: &config::Config,
+    store: store::DynStore,
+    profanity: profanity::DynProfanityFilter,
+    mailer: mail::DynMailSender,
+    keyring: std::sync::Arc<keyring::Keyring>,
 ) -> impl Filter<Extract = (impl warp::Reply,)> + Clone {
-    let store_filter = warp::any().map(move || store.clone());
+    let store_filter = {
+        let store = store.clone();
+        warp::any().map(move || store.clone())
+    };
+    let profanity_filter = warp::any().map(move || profanity.clone());
+    let mailer_filter = warp::any().map(move || mailer.clone());
+    let public_url = config.public_url.clone();
+    let public_url_filter = warp::any().map(move || public_url.clone());
+    let reset_mail_limit = std::sync::Arc::new(routes::password::reset_mail_limit());
+    let reset_mail_limit_filter = warp::any().map(move || reset_mail_limit.clone());
+    let throttle = std::sync::Arc::new(throttle::LoginThrottle::new(
+        throttle::ThrottleSettings::from_config(config)
+    ));
+    let throttle_filter = warp::any().map(move || throttle.clone());
+    let keyring_filter = {
+        let keyring = keyring.clone();
+        warp::any().map(move || keyring.clone())
+    };
+    let password_policy = routes::authentication::PasswordPolicy::from_config(config);
+    let password_policy_filter = warp::any().map(move || password_policy);
+    let password_rules = validation::PasswordRules::from_config(config);
+    let password_rules_filter = warp::any().map(move || password_rules);
+    let account_deletion = config.account_deletion;
+    let account_deletion_filter = warp::any().map(move || account_deletion);
+    // the OpenID Connect routes do not exist unless a provider is configured
+    let oidc_provider = oidc::OidcProvider::from_config(config).map(std::sync::Arc::new);
+    let oidc_filter = warp::any().and_then(move || {
+        let provider = oidc_provider.clone();
+        async move { provider.ok_or_else(warp::reject::not_found) }
+    });
 
     let cors = warp::cors()
         .allow_any_origin()
@@ -47,6 +136,8 @@ async fn build_routes(
     let get_questions = warp::get()
         .and(question_path)
         .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::QuestionsRead))
+        .and(warp::path::full())
         .and(warp::query())
         .and(store_filter.clone())
         .and_then(routes::question::get_questions)
@@ -63,14 +154,16 @@ async fn build_routes(
         .and(question_path)
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::QuestionsRead))
         .and(store_filter.clone())
         .and_then(routes::question::get_question);
 
     let add_question = warp::post()
         .and(question_path)
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(routes::authentication::auth_verified_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
         .and(store_filter.clone())
+        .and(profanity_filter.clone())
         .and(warp::body::json())
         .and_then(routes::question::add_question);
 
@@ -78,8 +171,9 @@ async fn build_routes(
         .and(question_path)
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
         .and(store_filter.clone())
+        .and(profanity_filter.clone())
         .and(warp::body::json())
         .and_then(routes::question::update_question);
 
@@ -87,46 +181,527 @@ async fn build_routes(
         .and(question_path)
         .and(warp::path::param::<i32>())
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
         .and(store_filter.clone())
         .and_then(routes::question::delete_question);
 
     let add_answer = warp::post()
         .and(warp::path("answers"))
         .and(warp::path::end())
-        .and(routes::authentication::auth())
+        .and(routes::authentication::auth_verified_scoped(store.clone(), keyring.clone(), types::api_key::Scope::AnswersWrite))
         .and(store_filter.clone())
+        .and(profanity_filter.clone())
         .and(warp::body::form())
         .and_then(routes::answer::add_answer);
 
+    let accept_answer = warp::post()
+        .and(question_path)
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accept"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
+        .and(store_filter.clone())
+        .and_then(routes::question::accept_answer);
+
+    let unaccept_answer = warp::delete()
+        .and(question_path)
+        .and(warp::path::param::<i32>())
+        .and(warp::path("accept"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
+        .and(store_filter.clone())
+        .and_then(routes::question::unaccept_answer);
+
+    let vote_question = warp::post()
+        .and(question_path)
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth_verified_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::vote::vote_question);
+
+    let get_answers = warp::get()
+        .and(question_path)
+        .and(warp::path::param::<i32>())
+        .and(warp::path("answers"))
+        .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::AnswersRead))
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::answer::get_answers);
+
+    let get_answer = warp::get()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::AnswersRead))
+        .and(store_filter.clone())
+        .and_then(routes::answer::get_answer);
+
+    let vote_answer = warp::post()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("vote"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth_verified_scoped(store.clone(), keyring.clone(), types::api_key::Scope::AnswersWrite))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::vote::vote_answer);
+
+    let update_answer = warp::put()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::AnswersWrite))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::answer::update_answer);
+
+    let delete_answer = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::AnswersWrite))
+        .and(store_filter.clone())
+        .and_then(routes::answer::delete_answer);
+
+    let get_question_comments = warp::get()
+        .and(question_path)
+        .and(warp::path::param::<i32>().map(types::comment::CommentTarget::question))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::QuestionsRead))
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::comment::get_comments);
+
+    let add_question_comment = warp::post()
+        .and(question_path)
+        .and(warp::path::param::<i32>().map(types::comment::CommentTarget::question))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth_verified_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::comment::add_comment);
+
+    let update_question_comment = warp::put()
+        .and(question_path)
+        .and(warp::path::param::<i32>().map(types::comment::CommentTarget::question))
+        .and(warp::path("comments"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::comment::update_comment);
+
+    let delete_question_comment = warp::delete()
+        .and(question_path)
+        .and(warp::path::param::<i32>().map(types::comment::CommentTarget::question))
+        .and(warp::path("comments"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::QuestionsWrite))
+        .and(store_filter.clone())
+        .and_then(routes::comment::delete_comment);
+
+    let get_answer_comments = warp::get()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>().map(types::comment::CommentTarget::answer))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::AnswersRead))
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::comment::get_comments);
+
+    let add_answer_comment = warp::post()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>().map(types::comment::CommentTarget::answer))
+        .and(warp::path("comments"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth_verified_scoped(store.clone(), keyring.clone(), types::api_key::Scope::AnswersWrite))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::comment::add_comment);
+
+    let update_answer_comment = warp::put()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>().map(types::comment::CommentTarget::answer))
+        .and(warp::path("comments"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::AnswersWrite))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::comment::update_comment);
+
+    let delete_answer_comment = warp::delete()
+        .and(warp::path("answers"))
+        .and(warp::path::param::<i32>().map(types::comment::CommentTarget::answer))
+        .and(warp::path("comments"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth_scoped(store.clone(), keyring.clone(), types::api_key::Scope::AnswersWrite))
+        .and(store_filter.clone())
+        .and_then(routes::comment::delete_comment);
+
+    let get_tags = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::QuestionsRead))
+        .and(store_filter.clone())
+        .and_then(routes::tag::get_tags);
+
+    let get_tag_questions = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("questions"))
+        .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::QuestionsRead))
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and_then(routes::tag::get_tag_questions);
+
     let registration = warp::post()
         .and(warp::path("registration"))
         .and(warp::path::end())
         .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(mailer_filter.clone())
+        .and(public_url_filter.clone())
+        .and(password_policy_filter)
+        .and(password_rules_filter)
         .and(warp::body::json())
         .and_then(routes::authentication::register);
 
     let login = warp::post()
         .and(warp::path("login"))
         .and(warp::path::end())
+        .and(warp::addr::remote())
         .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(throttle_filter.clone())
+        .and(password_policy_filter)
         .and(warp::body::json())
         .and_then(routes::authentication::login);
 
-    get_questions
+    let login_two_factor = warp::post()
+        .and(warp::path("login"))
+        .and(warp::path("2fa"))
+        .and(warp::path::end())
+        .and(warp::addr::remote())
+        .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(throttle_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::two_factor::login);
+
+    let verify = warp::get()
+        .and(warp::path("verify"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and_then(routes::verification::verify);
+
+    let resend_verification = warp::post()
+        .and(warp::path("verify"))
+        .and(warp::path("resend"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and(mailer_filter.clone())
+        .and(keyring_filter.clone())
+        .and(public_url_filter.clone())
+        .and_then(routes::verification::resend);
+
+    let forgot_password = warp::post()
+        .and(warp::path("password"))
+        .and(warp::path("forgot"))
+        .and(warp::path::end())
+        .and(warp::addr::remote())
+        .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(mailer_filter.clone())
+        .and(reset_mail_limit_filter)
+        .and(public_url_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::password::forgot_password);
+
+    let reset_password = warp::post()
+        .and(warp::path("password"))
+        .and(warp::path("reset"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(password_policy_filter)
+        .and(password_rules_filter)
+        .and(warp::body::json())
+        .and_then(routes::password::reset_password);
+
+    let change_password = warp::put()
+        .and(warp::path("account"))
+        .and(warp::path("password"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(warp::addr::remote())
+        .and(store_filter.clone())
+        .and(throttle_filter.clone())
+        .and(password_policy_filter)
+        .and(password_rules_filter)
+        .and(warp::body::json())
+        .and_then(routes::password::change_password);
+
+    let refresh = warp::post()
+        .and(warp::path("token"))
+        .and(warp::path("refresh"))
+        .and(warp::path::end())
+        .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::authentication::refresh);
+
+    let logout = warp::post()
+        .and(warp::path("logout"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::authentication::logout);
+
+    let logout_all = warp::post()
+        .and(warp::path("logout"))
+        .and(warp::path("all"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::authentication::logout_all);
+
+    let oidc_login = warp::get()
+        .and(warp::path("oidc"))
+        .and(warp::path("login"))
+        .and(warp::path::end())
+        .and(oidc_filter.clone())
+        .and(public_url_filter.clone())
+        .and(keyring_filter.clone())
+        .and_then(routes::oidc::login);
+
+    let oidc_callback = warp::get()
+        .and(warp::path("oidc"))
+        .and(warp::path("callback"))
+        .and(warp::path::end())
+        .and(warp::query())
+        .and(warp::cookie::optional("oidc_login"))
+        .and(oidc_filter)
+        .and(public_url_filter.clone())
+        .and(store_filter.clone())
+        .and(keyring_filter.clone())
+        .and(password_policy_filter)
+        .and_then(routes::oidc::callback);
+
+    let paseto_keys = warp::get()
+        .and(warp::path(".well-known"))
+        .and(warp::path("paseto-keys"))
+        .and(warp::path::end())
+        .and(keyring_filter.clone())
+        .map(|keyring: std::sync::Arc<keyring::Keyring>| warp::reply::json(&keyring.public_keys()));
+
+    let get_accounts = warp::get()
+        .and(warp::path("accounts"))
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(store.clone(), keyring.clone(), types::account::Role::Admin))
+        .and(store_filter.clone())
+        .and_then(routes::account::get_accounts);
+
+    let update_account_role = warp::put()
+        .and(warp::path("accounts"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path("role"))
+        .and(warp::path::end())
+        .and(routes::authentication::require_role(store.clone(), keyring.clone(), types::account::Role::Admin))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::account::update_account_role);
+
+    let get_own_account = warp::get()
+        .and(warp::path("account"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::account::get_own_account);
+
+    let update_own_account = warp::put()
+        .and(warp::path("account"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and(profanity_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::account::update_own_account);
+
+    let delete_own_account = warp::delete()
+        .and(warp::path("account"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(warp::addr::remote())
+        .and(store_filter.clone())
+        .and(throttle_filter.clone())
+        .and(password_policy_filter)
+        .and(account_deletion_filter)
+        .and(warp::body::json())
+        .and_then(routes::account::delete_own_account);
+
+    let export_own_account = warp::get()
+        .and(warp::path("account"))
+        .and(warp::path("export"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::account::export_own_account);
+
+    let create_api_key = warp::post()
+        .and(warp::path("account"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth_verified(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::api_key::create_api_key);
+
+    let get_api_keys = warp::get()
+        .and(warp::path("account"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::api_key::get_api_keys);
+
+    let revoke_api_key = warp::delete()
+        .and(warp::path("account"))
+        .and(warp::path("api-keys"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::api_key::revoke_api_key);
+
+    let setup_two_factor = warp::post()
+        .and(warp::path("account"))
+        .and(warp::path("2fa"))
+        .and(warp::path("setup"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and_then(routes::two_factor::setup);
+
+    let confirm_two_factor = warp::post()
+        .and(warp::path("account"))
+        .and(warp::path("2fa"))
+        .and(warp::path("confirm"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(store_filter.clone())
+        .and(warp::body::json())
+        .and_then(routes::two_factor::confirm);
+
+    let disable_two_factor = warp::delete()
+        .and(warp::path("account"))
+        .and(warp::path("2fa"))
+        .and(warp::path::end())
+        .and(routes::authentication::auth(store.clone(), keyring.clone()))
+        .and(warp::addr::remote())
+        .and(store_filter.clone())
+        .and(throttle_filter)
+        .and(warp::body::json())
+        .and_then(routes::two_factor::disable);
+
+    let get_user = warp::get()
+        .and(warp::path("users"))
+        .and(warp::path::param::<i32>())
+        .and(warp::path::end())
+        .and(routes::authentication::api_key_scope(store.clone(), types::api_key::Scope::QuestionsRead))
+        .and(store_filter.clone())
+        .and_then(routes::account::get_user);
+
+    // Grouped so the nested filter type stays within the compiler's limits.
+    let question_routes = get_questions
         .or(get_question)
         .or(add_question)
         .or(update_question)
         .or(delete_question)
         .or(add_answer)
-        .or(registration)
+        .or(get_answers)
+        .or(get_answer)
+        .or(update_answer)
+        .or(delete_answer)
+        .or(accept_answer)
+        .or(unaccept_answer)
+        .or(vote_question)
+        .or(vote_answer)
+        .or(get_tags)
+        .or(get_tag_questions);
+
+    let comment_routes = get_question_comments
+        .or(add_question_comment)
+        .or(update_question_comment)
+        .or(delete_question_comment)
+        .or(get_answer_comments)
+        .or(add_answer_comment)
+        .or(update_answer_comment)
+        .or(delete_answer_comment);
+
+    let authentication_routes = registration
         .or(login)
+        .or(login_two_factor)
+        .or(verify)
+        .or(resend_verification)
+        .or(forgot_password)
+        .or(reset_password)
+        .or(change_password)
+        .or(refresh)
+        .or(logout)
+        .or(logout_all)
+        .or(oidc_login)
+        .or(oidc_callback)
+        .or(paseto_keys);
+
+    let account_routes = get_accounts
+        .or(update_account_role)
+        .or(get_own_account)
+        .or(update_own_account)
+        .or(delete_own_account)
+        .or(export_own_account)
+        .or(create_api_key)
+        .or(get_api_keys)
+        .or(revoke_api_key)
+        .or(setup_two_factor)
+        .or(confirm_two_factor)
+        .or(disable_two_factor)
+        .or(get_user);
+
+    question_routes
+        .or(comment_routes)
+        .or(authentication_routes)
+        .or(account_routes)
         .with(cors)
         .with(warp::trace::request())
         .recover(handle_errors::return_error)
 }
 
-pub async fn run(config: config::Config, store: store::Store) {
-    let routes = build_routes(store).await;
+pub async fn run(config: config::Config, store: store::DynStore) {
+    let profanity = setup_profanity_filter(&config);
+    let mailer = setup_mail_sender(&config).expect("Mail sender can't be set up");
+    let keyring = setup_keyring(&config);
+    let routes = build_routes(&config, store, profanity, mailer, keyring).await;
     warp::serve(routes).run(([0, 0, 0, 0], config.port)).await;
 }
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+
+use super::{Email, MailSender};
+
+/// Writes every e-mail to its own `.eml` file in a directory and logs it,
+/// for local development without a mail server.
+#[derive(Debug, Clone)]
+pub struct FileMailSender {
+    dir: PathBuf,
+}
+
+impl FileMailSender {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        FileMailSender { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl MailSender for FileMailSender {
+    async fn send(&self, email: Email) -> Result<(), handle_errors::Error> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| handle_errors::Error::MailError(e.to_string()))?;
+
+        let path = self.dir.join(format!(
+            "{}-{}.eml",
+            chrono::Utc::now().format("%Y%m%dT%H%M%S%.6f"),
+            uuid::Uuid::new_v4()
+        ));
+        let message = format!("To: {}\nSubject: {}\n\n{}", email.to, email.subject, email.body);
+        tokio::fs::write(&path, message)
+            .await
+            .map_err(|e| handle_errors::Error::MailError(e.to_string()))?;
+
+        tracing::event!(tracing::Level::INFO, "mail to {} written to {}", email.to, path.display());
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod file_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn writes_one_file_per_mail() {
+        // arrange
+        let dir = std::env::temp_dir().join(format!("web_questions_mail_{}", uuid::Uuid::new_v4()));
+        let sender = FileMailSender::new(&dir);
+        let email = Email {
+            to: "test@example.com".to_string(),
+            subject: "Hello".to_string(),
+            body: "Body".to_string(),
+        };
+
+        // act
+        sender.send(email.clone()).await.unwrap();
+        sender.send(email).await.unwrap();
+
+        // assert
+        let files = std::fs::read_dir(&dir).unwrap().map(|f| f.unwrap().path()).collect::<Vec<_>>();
+        assert_eq!(files.len(), 2);
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert_eq!(content, "To: test@example.com\nSubject: Hello\n\nBody");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Caps how many mails unauthenticated requests can trigger, per recipient
+/// and per requesting address, within a fixed window. Kept in memory, so
+/// the counts start over when the server restarts.
+pub struct MailLimit {
+    per_address: u32,
+    per_ip: u32,
+    window: Duration,
+    sent: Mutex<HashMap<Key, Window>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Address(String),
+    Ip(IpAddr),
+}
+
+struct Window {
+    start: Instant,
+    count: u32,
+}
+
+impl MailLimit {
+    pub fn new(per_address: u32, per_ip: u32, window: Duration) -> Self {
+        MailLimit {
+            per_address,
+            per_ip,
+            window,
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Counts a mail to `address` asked for from `ip`. Returns `false`,
+    /// counting nothing, if either already had its share of the window.
+    pub fn allow(&self, address: &str, ip: Option<IpAddr>, now: Instant) -> bool {
+        let mut sent = self.sent.lock().unwrap();
+        sent.retain(|_, window| now.duration_since(window.start) < self.window);
+
+        let mut keys = vec![(Key::Address(address.to_lowercase()), self.per_address)];
+        if let Some(ip) = ip {
+            keys.push((Key::Ip(ip), self.per_ip));
+        }
+        if keys.iter().any(|(key, max)| sent.get(key).is_some_and(|window| window.count >= *max)) {
+            return false;
+        }
+        for (key, _) in keys {
+            sent.entry(key).or_insert(Window { start: now, count: 0 }).count += 1;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod limit_tests {
+    use super::*;
+
+    fn ip(last: u8) -> Option<IpAddr> {
+        Some(IpAddr::from([10, 0, 0, last]))
+    }
+
+    #[test]
+    fn caps_mails_per_address() {
+        // arrange
+        let limit = MailLimit::new(2, 10, Duration::from_secs(60));
+        let now = Instant::now();
+
+        // act
+        let allowed = (1..=3).map(|i| limit.allow("Test@example.com", ip(i), now)).collect::<Vec<_>>();
+
+        // assert
+        assert_eq!(allowed, vec![true, true, false]);
+        assert!(!limit.allow("test@example.com", None, now));
+        assert!(limit.allow("other@example.com", ip(1), now));
+    }
+
+    #[test]
+    fn caps_mails_per_ip() {
+        // arrange
+        let limit = MailLimit::new(10, 2, Duration::from_secs(60));
+        let now = Instant::now();
+
+        // act
+        let allowed = ["a@example.com", "b@example.com", "c@example.com"]
+            .iter()
+            .map(|address| limit.allow(address, ip(1), now))
+            .collect::<Vec<_>>();
+
+        // assert
+        assert_eq!(allowed, vec![true, true, false]);
+        assert!(limit.allow("c@example.com", ip(2), now));
+    }
+
+    #[test]
+    fn refused_mails_are_not_counted() {
+        // arrange
+        let limit = MailLimit::new(1, 2, Duration::from_secs(60));
+        let now = Instant::now();
+        limit.allow("a@example.com", ip(1), now);
+
+        // act
+        let refused = limit.allow("a@example.com", ip(1), now);
+        let other = limit.allow("b@example.com", ip(1), now);
+
+        // assert
+        assert!(!refused);
+        assert!(other);
+    }
+
+    #[test]
+    fn window_starts_over() {
+        // arrange
+        let limit = MailLimit::new(1, 1, Duration::from_secs(60));
+        let now = Instant::now();
+        limit.allow("a@example.com", ip(1), now);
+
+        // act
+        let during = limit.allow("a@example.com", ip(1), now + Duration::from_secs(59));
+        let after = limit.allow("a@example.com", ip(1), now + Duration::from_secs(60));
+
+        // assert
+        assert!(!during);
+        assert!(after);
+    }
+}
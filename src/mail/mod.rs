@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+
+mod file;
+mod limit;
+mod smtp;
+
+pub use file::FileMailSender;
+pub use limit::MailLimit;
+pub use smtp::SmtpMailSender;
+
+/// Mail sender handed to every route handler that notifies users.
+pub type DynMailSender = std::sync::Arc<dyn MailSender>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), handle_errors::Error>;
+}
+
+pub fn verification_email(to: &str, link: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Please verify your e-mail address".to_string(),
+        body: format!(
+            "Welcome!\n\nOpen the following link to verify your e-mail address:\n\n{}\n\n\
+             If you did not create an account, you can ignore this e-mail.\n",
+            link
+        ),
+    }
+}
+
+pub fn password_reset_email(to: &str, link: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Somebody asked to reset the password of your account.\n\n\
+             Open the following link within the next hour to choose a new one:\n\n{}\n\n\
+             If this was not you, you can ignore this e-mail.\n",
+            link
+        ),
+    }
+}
@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::{Email, MailSender};
+
+/// Sends e-mails through an SMTP relay using STARTTLS.
+#[derive(Clone)]
+pub struct SmtpMailSender {
+    from: String,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailSender {
+    pub fn new(
+        host: &str,
+        port: u16,
+        credentials: Option<(String, String)>,
+        from: String,
+    ) -> Result<Self, handle_errors::Error> {
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .map_err(|e| handle_errors::Error::MailError(e.to_string()))?
+            .port(port);
+        if let Some((user, password)) = credentials {
+            builder = builder.credentials(Credentials::new(user, password));
+        }
+
+        Ok(SmtpMailSender { from, transport: builder.build() })
+    }
+}
+
+#[async_trait]
+impl MailSender for SmtpMailSender {
+    async fn send(&self, email: Email) -> Result<(), handle_errors::Error> {
+        let message = Message::builder()
+            .from(self.from.parse().map_err(|_| handle_errors::Error::MailError("invalid sender address".to_string()))?)
+            .to(email.to.parse().map_err(|_| handle_errors::Error::InvalidParameter("email".to_string()))?)
+            .subject(email.subject)
+            .body(email.body)
+            .map_err(|e| handle_errors::Error::MailError(e.to_string()))?;
+
+        match self.transport.send(message).await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "mail::smtp {:?}", e);
+                Err(handle_errors::Error::MailError(e.to_string()))
+            }
+        }
+    }
+}
@@ -1,65 +0,0 @@
-use serde::{Deserialize, Serialize};
-
-#[derive(Deserialize, Serialize, Debug, Clone)]
-pub struct APIResponse {
-    message: String
-}
-
-#[derive(Deserialize, Serialize, Debug, Clone)]
-struct BadWord {
-    original: String,
-    word: String,
-    deviations: i64,
-    info: i64,
-    #[serde(rename = "replacedLen")]
-    replaced_len: i64,
-}
-
-#[derive(Deserialize, Serialize, Debug, Clone)]
-struct BadWordsResponse {
-    content: String,
-    bad_words_total: i64,
-    bad_words_list: Vec<BadWord>,
-    censored_content: String,
-}
-
-pub async fn check_profanity(
-    content: String
-) -> Result<String, handle_errors::Error> {
-    tracing::event!(tracing::Level::INFO, "check_profanity: {}", content);
-
-    let key = std::env::var("BAD_WORDS_API_KEY").unwrap();
-    let client = reqwest::Client::new();
-    let res = client
-        .post("https://api.apilayer.com/bad_words?censor_characters=*")
-        .header("apikey", key)
-        .body(content)
-        .send()
-        .await
-        .map_err(|e| handle_errors::Error::ExternalAPIError(e))?;
-
-    if !res.status().is_success() {
-        if res.status().is_client_error() {
-            let err = transform_error(res).await;
-            return Err(handle_errors::Error::ClientError(err));
-        } else {
-            let err = transform_error(res).await;
-            return Err(handle_errors::Error::ServerError(err));
-        }
-    }
-    tracing::event!(tracing::Level::INFO, "check_profanity done for {:?}", res);
-    match res.json::<BadWordsResponse>()
-        .await {
-            Ok(res) => Ok(res.censored_content),
-            Err(e) => Err(handle_errors::Error::ExternalAPIError(e)),
-        }
-}
-
-async fn transform_error(
-    res: reqwest::Response
-) -> handle_errors::APILayerError {
-    handle_errors::APILayerError {
-        status: res.status().as_u16(),
-        message: res.json::<APIResponse>().await.unwrap().message,
-    }
-}
\ No newline at end of file
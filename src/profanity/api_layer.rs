@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::ProfanityFilter;
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct APIResponse {
+    message: String
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct BadWord {
+    original: String,
+    word: String,
+    deviations: i64,
+    info: i64,
+    #[serde(rename = "replacedLen")]
+    replaced_len: i64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+struct BadWordsResponse {
+    content: String,
+    bad_words_total: i64,
+    bad_words_list: Vec<BadWord>,
+    censored_content: String,
+}
+
+/// Censors content through the APILayer bad words API.
+#[derive(Debug, Clone)]
+pub struct ApiLayerFilter {
+    api_key: String,
+    client: reqwest::Client,
+}
+
+impl ApiLayerFilter {
+    pub fn new(api_key: String) -> Self {
+        ApiLayerFilter {
+            api_key,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for ApiLayerFilter {
+    async fn censor(&self, content: String) -> Result<String, handle_errors::Error> {
+        let res = self.client
+            .post("https://api.apilayer.com/bad_words?censor_characters=*")
+            .header("apikey", &self.api_key)
+            .body(content)
+            .send()
+            .await
+            .map_err(handle_errors::Error::ExternalAPIError)?;
+
+        if !res.status().is_success() {
+            if res.status().is_client_error() {
+                let err = transform_error(res).await;
+                return Err(handle_errors::Error::ClientError(err));
+            } else {
+                let err = transform_error(res).await;
+                return Err(handle_errors::Error::ServerError(err));
+            }
+        }
+        tracing::event!(tracing::Level::INFO, "check_profanity done for {:?}", res);
+        match res.json::<BadWordsResponse>()
+            .await {
+                Ok(res) => Ok(res.censored_content),
+                Err(e) => Err(handle_errors::Error::ExternalAPIError(e)),
+            }
+    }
+}
+
+async fn transform_error(
+    res: reqwest::Response
+) -> handle_errors::APILayerError {
+    handle_errors::APILayerError {
+        status: res.status().as_u16(),
+        message: res.json::<APIResponse>().await.unwrap().message,
+    }
+}
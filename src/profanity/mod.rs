@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+
+mod api_layer;
+mod word_list;
+
+pub use api_layer::ApiLayerFilter;
+pub use word_list::WordListFilter;
+
+/// Profanity filter handed to every route handler that accepts user content.
+pub type DynProfanityFilter = std::sync::Arc<dyn ProfanityFilter>;
+
+#[async_trait]
+pub trait ProfanityFilter: Send + Sync {
+    /// Returns `content` with every bad word replaced by `*` characters.
+    async fn censor(&self, content: String) -> Result<String, handle_errors::Error>;
+}
+
+/// Lets everything through unchanged, for local development.
+#[derive(Debug, Clone, Default)]
+pub struct NoopFilter;
+
+#[async_trait]
+impl ProfanityFilter for NoopFilter {
+    async fn censor(&self, content: String) -> Result<String, handle_errors::Error> {
+        Ok(content)
+    }
+}
+
+pub async fn check_profanity(
+    filter: &dyn ProfanityFilter,
+    content: String
+) -> Result<String, handle_errors::Error> {
+    tracing::event!(tracing::Level::INFO, "check_profanity: {}", content);
+
+    filter.censor(content).await
+}
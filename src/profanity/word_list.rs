@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use regex::{Regex, RegexBuilder};
+
+use super::ProfanityFilter;
+
+/// Censors content locally against a list of bad words, one per line.
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, Clone)]
+pub struct WordListFilter {
+    pattern: Option<Regex>,
+}
+
+impl WordListFilter {
+    pub fn new<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let alternatives = words
+            .into_iter()
+            .map(str::trim)
+            .filter(|word| !word.is_empty() && !word.starts_with('#'))
+            .map(regex::escape)
+            .collect::<Vec<_>>();
+
+        if alternatives.is_empty() {
+            return WordListFilter { pattern: None };
+        }
+
+        let pattern = RegexBuilder::new(&format!(r"\b(?:{})\b", alternatives.join("|")))
+            .case_insensitive(true)
+            .build()
+            .expect("escaped word list is a valid regex");
+
+        WordListFilter { pattern: Some(pattern) }
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, std::io::Error> {
+        let words = std::fs::read_to_string(path)?;
+        Ok(WordListFilter::new(words.lines()))
+    }
+
+    fn censor_str(&self, content: &str) -> String {
+        match &self.pattern {
+            Some(pattern) => pattern
+                .replace_all(content, |caps: &regex::Captures| "*".repeat(caps[0].chars().count()))
+                .into_owned(),
+            None => content.to_owned(),
+        }
+    }
+}
+
+#[async_trait]
+impl ProfanityFilter for WordListFilter {
+    async fn censor(&self, content: String) -> Result<String, handle_errors::Error> {
+        Ok(self.censor_str(&content))
+    }
+}
+
+
+#[cfg(test)]
+mod word_list_tests {
+    use super::WordListFilter;
+
+    #[test]
+    fn censors_whole_words_case_insensitive() {
+        // arrange
+        let filter = WordListFilter::new(["darn", "heck"]);
+
+        // act
+        let censored = filter.censor_str("Darn it, what the HECK is darning?");
+
+        // assert
+        assert_eq!(censored, "**** it, what the **** is darning?");
+    }
+
+    #[test]
+    fn ignores_comments_and_blank_lines() {
+        // arrange
+        let filter = WordListFilter::new("# bad words\n\n  darn  \n".lines());
+
+        // act
+        let censored = filter.censor_str("darn # bad words");
+
+        // assert
+        assert_eq!(censored, "**** # bad words");
+    }
+
+    #[test]
+    fn empty_list_keeps_content() {
+        // arrange
+        let filter = WordListFilter::new([]);
+
+        // act
+        let censored = filter.censor_str("nothing to see");
+
+        // assert
+        assert_eq!(censored, "nothing to see");
+    }
+
+    #[test]
+    fn escapes_regex_characters() {
+        // arrange
+        let filter = WordListFilter::new(["a.b"]);
+
+        // act
+        let censored = filter.censor_str("a.b axb");
+
+        // assert
+        assert_eq!(censored, "*** axb");
+    }
+}
@@ -0,0 +1,374 @@
+use rand::Rng;
+use warp::Reply;
+
+use crate::config::AccountDeletionKind;
+use crate::types::account::{AccountDeletion, AccountExport, AccountId, AccountInfo, Profile, RoleUpdate};
+
+/// Every account with its role, for admins.
+pub async fn get_accounts(
+    _session: crate::types::account::Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_accounts().await {
+        Ok(accounts) => {
+            let accounts = accounts.into_iter().map(AccountInfo::from).collect::<Vec<_>>();
+            Ok(warp::reply::json(&accounts))
+        },
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Changes the role of an account. Tokens already issued keep the old role
+/// until they expire.
+pub async fn update_account_role(
+    id: i32,
+    _session: crate::types::account::Session,
+    store: crate::store::DynStore,
+    update: RoleUpdate,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.set_account_role(&AccountId(id), update.role).await {
+        Ok(account) => Ok(warp::reply::json(&AccountInfo::from(account))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// The account of the caller, including the e-mail address.
+pub async fn get_own_account(
+    session: crate::types::account::Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_account_by_id(&session.account_id).await {
+        Ok(account) => Ok(warp::reply::json(&AccountInfo::from(account))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Replaces the profile of the caller. Display name and bio go through the
+/// profanity filter like any other user content.
+pub async fn update_own_account(
+    session: crate::types::account::Session,
+    store: crate::store::DynStore,
+    profanity: crate::profanity::DynProfanityFilter,
+    profile: Profile,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let profile = crate::validation::normalize_profile(profile);
+    crate::validation::validate(crate::validation::check_profile(&profile))
+        .map_err(warp::reject::custom)?;
+
+    let display_name = match profile.display_name {
+        Some(display_name) => Some(crate::profanity::check_profanity(profanity.as_ref(), display_name).await?),
+        None => None,
+    };
+    let bio = match profile.bio {
+        Some(bio) => Some(crate::profanity::check_profanity(profanity.as_ref(), bio).await?),
+        None => None,
+    };
+    let profile = Profile { display_name, bio, avatar_url: profile.avatar_url };
+
+    match store.update_profile(&session.account_id, &profile).await {
+        Ok(account) => Ok(warp::reply::json(&AccountInfo::from(account))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Public profile of any account, without the e-mail address.
+pub async fn get_user(
+    id: i32,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_public_profile(&AccountId(id)).await {
+        Ok(profile) => Ok(warp::reply::json(&profile)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Deletes the account of the caller after checking the password or a
+/// second factor code. Its content is anonymized or deleted as well,
+/// depending on `kind`.
+pub async fn delete_own_account(
+    session: crate::types::account::Session,
+    remote: Option<std::net::SocketAddr>,
+    store: crate::store::DynStore,
+    throttle: std::sync::Arc<crate::throttle::LoginThrottle>,
+    policy: crate::routes::authentication::PasswordPolicy,
+    kind: AccountDeletionKind,
+    request: AccountDeletion,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account = match store.get_account_by_id(&session.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    crate::routes::two_factor::confirm_password_or_code(
+        &store,
+        &throttle,
+        remote,
+        &account,
+        request.password.as_deref(),
+        request.code.as_deref(),
+    ).await?;
+
+    let result = match kind {
+        AccountDeletionKind::Anonymize => {
+            // nobody knows the password to this hash, so the address the
+            // account is left with can't be logged into
+            let password = rand::thread_rng().gen::<[u8; 32]>();
+            let hash = crate::routes::authentication::hash_password(&policy, &password);
+            store.anonymize_account(&session.account_id, &hash).await
+        },
+        AccountDeletionKind::Cascade => store.delete_account(&session.account_id).await,
+    };
+    match result {
+        Ok(_) => {
+            tracing::event!(target: "audit", tracing::Level::INFO, account_id = session.account_id.0, ?kind, "account deleted");
+            Ok(warp::reply::with_status("Account deleted", warp::http::StatusCode::OK))
+        },
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Profile, questions and answers of the caller as a JSON download.
+pub async fn export_own_account(
+    session: crate::types::account::Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account = match store.get_account_by_id(&session.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let questions = match store.get_questions_by_account(&session.account_id).await {
+        Ok(questions) => questions,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let answers = match store.get_answers_by_account(&session.account_id).await {
+        Ok(answers) => answers,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let comments = match store.get_comments_by_account(&session.account_id).await {
+        Ok(comments) => comments,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let export = AccountExport {
+        account: AccountInfo::from(account),
+        questions,
+        answers,
+        comments,
+        exported_on: chrono::Utc::now(),
+    };
+    let mut response = warp::reply::json(&export).into_response();
+    response.headers_mut().insert(
+        warp::http::header::CONTENT_DISPOSITION,
+        warp::http::HeaderValue::from_static("attachment; filename=\"account-export.json\""),
+    );
+    Ok(response)
+}
+
+
+#[cfg(test)]
+mod account_tests {
+    use crate::{store, types};
+    use crate::store::{AccountRepository, QuestionRepository};
+    use crate::routes::test_support::{login, register_and_login, test_routes, test_routes_with, TEST_PASSWORD};
+
+    #[tokio::test]
+    async fn roles_with_in_memory_store() {
+        // arrange
+        let store = std::sync::Arc::new(store::InMemoryStore::new());
+        let (routes, mail_dir) = test_routes(store.clone()).await;
+        let owner = register_and_login(&routes, &mail_dir, "owner@example.com").await;
+        let user = register_and_login(&routes, &mail_dir, "user@example.com").await;
+        register_and_login(&routes, &mail_dir, "moderator@example.com").await;
+        register_and_login(&routes, &mail_dir, "admin@example.com").await;
+        store.set_account_role(&types::account::AccountId(3), types::account::Role::Moderator).await.unwrap();
+        store.set_account_role(&types::account::AccountId(4), types::account::Role::Admin).await.unwrap();
+        let moderator = login(&routes, "moderator@example.com").await;
+        let admin = login(&routes, "admin@example.com").await;
+        warp::test::request()
+            .method("POST")
+            .path("/questions")
+            .header("Authorization", &owner)
+            .json(&serde_json::json!({ "title": "Title", "content": "Content" }))
+            .reply(&routes)
+            .await;
+        let delete = |token: Option<&str>| {
+            let request = warp::test::request().method("DELETE").path("/questions/1");
+            match token {
+                Some(token) => request.header("Authorization", token),
+                None => request,
+            }
+        };
+
+        // act
+        let anonymous = delete(None).reply(&routes).await;
+        let by_user = delete(Some(&user)).reply(&routes).await;
+        let by_moderator = delete(Some(&moderator)).reply(&routes).await;
+        let accounts_by_moderator = warp::test::request()
+            .method("GET")
+            .path("/accounts")
+            .header("Authorization", &moderator)
+            .reply(&routes)
+            .await;
+        let promote = warp::test::request()
+            .method("PUT")
+            .path("/accounts/2/role")
+            .header("Authorization", &admin)
+            .json(&serde_json::json!({ "role": "moderator" }))
+            .reply(&routes)
+            .await;
+
+        // assert
+        assert_eq!(anonymous.status(), 401);
+        assert_eq!(by_user.status(), 403);
+        let error: serde_json::Value = serde_json::from_slice(by_user.body()).unwrap();
+        assert_eq!(error["code"], "forbidden");
+        assert_eq!(by_moderator.status(), 200);
+        assert!(store.get_question(1).await.unwrap().is_none());
+        assert_eq!(accounts_by_moderator.status(), 403);
+        assert_eq!(promote.status(), 200);
+        let promoted: serde_json::Value = serde_json::from_slice(promote.body()).unwrap();
+        assert_eq!(promoted["email"], "user@example.com");
+        assert_eq!(promoted["role"], "moderator");
+        assert!(promoted.get("password").is_none());
+    }
+
+    #[tokio::test]
+    async fn profiles_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let access_token = register_and_login(&routes, &mail_dir, "jane@example.com").await;
+        let update = |profile: serde_json::Value| warp::test::request()
+            .method("PUT")
+            .path("/account")
+            .header("Authorization", &access_token)
+            .json(&profile);
+
+        // act
+        let invalid = update(serde_json::json!({ "avatar_url": "ftp://example.com/jane.png" })).reply(&routes).await;
+        let updated = update(serde_json::json!({ "display_name": " Jane ", "bio": "Rustacean" })).reply(&routes).await;
+        let own = warp::test::request()
+            .method("GET")
+            .path("/account")
+            .header("Authorization", &access_token)
+            .reply(&routes)
+            .await;
+        let own: serde_json::Value = serde_json::from_slice(own.body()).unwrap();
+        warp::test::request()
+            .method("POST")
+            .path("/questions")
+            .header("Authorization", &access_token)
+            .json(&serde_json::json!({ "title": "Profiles", "content": "Who asked this?" }))
+            .reply(&routes)
+            .await;
+        let user = warp::test::request().path(&format!("/users/{}", own["id"])).reply(&routes).await;
+        let missing = warp::test::request().path("/users/999").reply(&routes).await;
+        let question = warp::test::request().path("/questions/1").reply(&routes).await;
+
+        // assert
+        assert_eq!(invalid.status(), 422);
+        assert_eq!(updated.status(), 200);
+        assert_eq!(own["email"], "jane@example.com");
+        assert_eq!(own["display_name"], "Jane");
+        assert_eq!(user.status(), 200);
+        let user: serde_json::Value = serde_json::from_slice(user.body()).unwrap();
+        assert_eq!(user["display_name"], "Jane");
+        assert_eq!(user["question_count"], 1);
+        assert!(user.get("email").is_none());
+        assert_eq!(missing.status(), 404);
+        let question: serde_json::Value = serde_json::from_slice(question.body()).unwrap();
+        assert_eq!(question["author"], serde_json::json!({ "id": own["id"], "display_name": "Jane" }));
+        assert!(!question.to_string().contains("jane@example.com"));
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn account_deletion_and_export_with_in_memory_store() {
+        // arrange
+        let (anonymizing, anonymizing_mail) = test_routes_with(
+            std::sync::Arc::new(store::InMemoryStore::new()),
+            &["--login-backoff-ms", "0"],
+        ).await;
+        let (cascading, cascading_mail) = test_routes_with(
+            std::sync::Arc::new(store::InMemoryStore::new()),
+            &["--account-deletion", "cascade"],
+        ).await;
+        let mut tokens = Vec::new();
+        for (routes, mail_dir) in [(&anonymizing, &anonymizing_mail), (&cascading, &cascading_mail)] {
+            let access_token = register_and_login(routes, mail_dir, "jane@example.com").await;
+            warp::test::request()
+                .method("POST")
+                .path("/questions")
+                .header("Authorization", &access_token)
+                .json(&serde_json::json!({ "title": "Export", "content": "Is this mine?" }))
+                .reply(routes)
+                .await;
+            warp::test::request()
+                .method("POST")
+                .path("/questions/1/comments")
+                .header("Authorization", &access_token)
+                .json(&serde_json::json!({ "content": "It is." }))
+                .reply(routes)
+                .await;
+            tokens.push(access_token);
+        }
+        let delete = |access_token: &str, password: &str| warp::test::request()
+            .method("DELETE")
+            .path("/account")
+            .header("Authorization", access_token)
+            .json(&serde_json::json!({ "password": password }));
+
+        // act
+        let export = warp::test::request()
+            .method("GET")
+            .path("/account/export")
+            .header("Authorization", &tokens[0])
+            .reply(&anonymizing)
+            .await;
+        let wrong_password = delete(&tokens[0], "wrong").reply(&anonymizing).await;
+        let confirm = |body: serde_json::Value| warp::test::request()
+            .method("DELETE")
+            .path("/account")
+            .header("Authorization", &tokens[0])
+            .json(&body)
+            .reply(&anonymizing);
+        let unconfirmed = confirm(serde_json::json!({})).await;
+        let code_without_two_factor = confirm(serde_json::json!({ "code": "123456" })).await;
+        let anonymized = delete(&tokens[0], TEST_PASSWORD).reply(&anonymizing).await;
+        let cascaded = delete(&tokens[1], TEST_PASSWORD).reply(&cascading).await;
+        let anonymized_login = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&serde_json::json!({ "email": "deleted-1@invalid", "password": "" }))
+            .reply(&anonymizing)
+            .await;
+        let after_deletion = warp::test::request()
+            .method("GET")
+            .path("/account")
+            .header("Authorization", &tokens[0])
+            .reply(&anonymizing)
+            .await;
+        let kept = warp::test::request().path("/questions/1").reply(&anonymizing).await;
+        let removed = warp::test::request().path("/questions/1").reply(&cascading).await;
+
+        // assert
+        assert_eq!(export.status(), 200);
+        assert_eq!(export.headers()["content-disposition"], "attachment; filename=\"account-export.json\"");
+        let export: serde_json::Value = serde_json::from_slice(export.body()).unwrap();
+        assert_eq!(export["account"]["email"], "jane@example.com");
+        assert_eq!(export["questions"][0]["title"], "Export");
+        assert_eq!(export["answers"], serde_json::json!([]));
+        assert_eq!(export["comments"][0]["content"], "It is.");
+        assert_eq!(export["comments"][0]["question_id"], 1);
+        assert_eq!(wrong_password.status(), 401);
+        assert_eq!(unconfirmed.status(), 400);
+        assert_eq!(code_without_two_factor.status(), 409);
+        assert_eq!(anonymized.status(), 200);
+        assert_eq!(cascaded.status(), 200);
+        assert_eq!(anonymized_login.status(), 401);
+        assert_eq!(after_deletion.status(), 401);
+        let kept: serde_json::Value = serde_json::from_slice(kept.body()).unwrap();
+        assert_eq!(kept["author"]["display_name"], types::account::DELETED_ACCOUNT_NAME);
+        assert_eq!(removed.body(), "null");
+        std::fs::remove_dir_all(anonymizing_mail).unwrap();
+        std::fs::remove_dir_all(cascading_mail).unwrap();
+    }
+}
@@ -1,9 +1,13 @@
 pub async fn add_answer(
     session: crate::types::account::Session,
-    store: crate::store::Store,
+    store: crate::store::DynStore,
+    profanity: crate::profanity::DynProfanityFilter,
     new_answer: crate::types::answer::NewAnswer,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let content = match crate::profanity::check_profanity(new_answer.content).await {
+    if store.get_question(new_answer.question_id.0).await?.is_none() {
+        return Err(warp::reject::custom(handle_errors::Error::QuestionNotFound));
+    }
+    let content = match crate::profanity::check_profanity(profanity.as_ref(), new_answer.content).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
@@ -19,3 +23,96 @@ pub async fn add_answer(
 
     Ok(warp::reply::with_status("Answer added", warp::hyper::StatusCode::CREATED))
 }
+
+pub async fn get_answers(
+    question_id: i32,
+    params: std::collections::HashMap<String, String>,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let sort = crate::types::answer::AnswerSort::new(&params)?;
+    let mut limit = None;
+    let mut offset = 0;
+    if !params.is_empty() {
+        let pagination = crate::types::pagination::get_pagination(params);
+        limit = pagination.get_limit();
+        offset = pagination.get_offset();
+    }
+
+    if store.get_question(question_id).await?.is_none() {
+        return Err(warp::reject::custom(handle_errors::Error::QuestionNotFound));
+    }
+
+    let res: Vec<crate::types::answer::Answer> = match store.get_answers(question_id, sort, limit, offset).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
+
+pub async fn get_answer(
+    id: i32,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_answer(id).await {
+        Ok(Some(answer)) => Ok(warp::reply::json(&answer)),
+        Ok(None) => Err(warp::reject::custom(handle_errors::Error::AnswerNotFound)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn update_answer(
+    id: i32,
+    session: crate::types::account::Session,
+    store: crate::store::DynStore,
+    profanity: crate::profanity::DynProfanityFilter,
+    answer: crate::types::answer::Answer,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let owner = match store.get_answer_owner(id).await? {
+        Some(owner) => owner,
+        None => return Err(warp::reject::custom(handle_errors::Error::AnswerNotFound)),
+    };
+    if session.may_modify(&owner) {
+        let content = match crate::profanity::check_profanity(profanity.as_ref(), answer.content).await {
+            Ok(res) => res,
+            Err(e) => return Err(warp::reject::custom(e)),
+        };
+        if let Err(e) = store.update_answer(
+            id,
+            crate::types::answer::Answer {
+                id: answer.id,
+                content,
+                question_id: answer.question_id,
+                score: 0,
+                author: None,
+            },
+            &owner,
+        ).await {
+            return Err(warp::reject::custom(e))
+        };
+
+        Ok(warp::reply::with_status("Answer updated", warp::hyper::StatusCode::OK))
+    } else {
+        Err(warp::reject::custom(handle_errors::Error::Forbidden))
+    }
+}
+
+pub async fn delete_answer(
+    id: i32,
+    session: crate::types::account::Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let owner = match store.get_answer_owner(id).await? {
+        Some(owner) => owner,
+        None => return Err(warp::reject::custom(handle_errors::Error::AnswerNotFound)),
+    };
+    if session.may_modify(&owner) {
+        if let Err(e) = store.delete_answer(id, &owner).await {
+            return Err(warp::reject::custom(e))
+        };
+
+        Ok(warp::reply::with_status("Answer deleted", warp::hyper::StatusCode::OK))
+    } else {
+        Err(warp::reject::custom(handle_errors::Error::Forbidden))
+    }
+}
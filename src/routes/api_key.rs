@@ -0,0 +1,235 @@
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use warp::http::StatusCode;
+
+use crate::types::account::Session;
+use crate::types::api_key::{ApiKeyId, CreatedApiKey, NewApiKey};
+
+/// Marks API keys, so leaked ones are easy to search for.
+const KEY_PREFIX: &str = "qa_";
+/// Characters of the key kept in the clear to tell keys apart.
+const SHOWN_PREFIX_LENGTH: usize = 10;
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Creates an API key for the caller. The key is part of this reply only.
+pub async fn create_api_key(
+    session: Session,
+    store: crate::store::DynStore,
+    new_api_key: NewApiKey,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let new_api_key = NewApiKey {
+        name: new_api_key.name.trim().to_string(),
+        ..new_api_key
+    };
+    crate::validation::validate(check_api_key(&new_api_key)).map_err(warp::reject::custom)?;
+
+    let key = generate_api_key();
+    match store.add_api_key(&session.account_id, &new_api_key, &key[..SHOWN_PREFIX_LENGTH], &hash_api_key(&key)).await {
+        Ok(api_key) => Ok(warp::reply::with_status(
+            warp::reply::json(&CreatedApiKey { key, api_key }),
+            StatusCode::CREATED,
+        )),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Every API key of the caller, without the keys themselves.
+pub async fn get_api_keys(
+    session: Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_api_keys(&session.account_id).await {
+        Ok(api_keys) => Ok(warp::reply::json(&api_keys)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Revokes one of the caller's API keys; it stays listed.
+pub async fn revoke_api_key(
+    id: i32,
+    session: Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.revoke_api_key(&ApiKeyId(id), &session.account_id, chrono::Utc::now().naive_utc()).await {
+        Ok(true) => Ok(warp::reply::with_status("API key revoked", StatusCode::OK)),
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+fn check_api_key(new_api_key: &NewApiKey) -> Vec<handle_errors::FieldError> {
+    let error = |field: &str, code: &str, message: String| handle_errors::FieldError {
+        field: field.to_string(),
+        code: code.to_string(),
+        message,
+    };
+    let mut errors = Vec::new();
+    if new_api_key.name.is_empty() {
+        errors.push(error("name", "required", "must not be empty".to_string()));
+    } else if new_api_key.name.chars().count() > MAX_NAME_LENGTH {
+        errors.push(error("name", "too_long", format!("must have at most {} characters", MAX_NAME_LENGTH)));
+    }
+    if new_api_key.scopes.is_empty() {
+        errors.push(error("scopes", "required", "must name at least one scope".to_string()));
+    }
+    errors
+}
+
+fn generate_api_key() -> String {
+    use base64::Engine;
+
+    let bytes = rand::thread_rng().gen::<[u8; 32]>();
+    format!("{}{}", KEY_PREFIX, base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// API keys are random like refresh tokens, so an unsalted hash will do.
+pub(crate) fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+
+#[cfg(test)]
+mod api_key_tests {
+    use super::*;
+    use crate::{store, types};
+    use crate::routes::test_support::{register_and_login, test_routes};
+    use crate::types::api_key::Scope;
+
+    #[test]
+    fn keys_are_prefixed_and_unique() {
+        // act
+        let first = generate_api_key();
+        let second = generate_api_key();
+
+        // assert
+        assert!(first.starts_with(KEY_PREFIX));
+        assert_ne!(first, second);
+        assert_eq!(hash_api_key(&first).len(), 64);
+        assert_ne!(hash_api_key(&first), hash_api_key(&second));
+    }
+
+    #[test]
+    fn names_and_scopes_are_required() {
+        // arrange
+        let key = |name: &str, scopes: Vec<Scope>| NewApiKey { name: name.to_string(), scopes };
+
+        // act
+        let valid = check_api_key(&key("bot", vec![Scope::QuestionsWrite]));
+        let invalid = check_api_key(&key("", Vec::new()));
+        let long_name = check_api_key(&key(&"n".repeat(101), vec![Scope::AnswersRead]));
+
+        // assert
+        assert!(valid.is_empty());
+        assert_eq!(invalid.iter().map(|e| e.field.as_str()).collect::<Vec<_>>(), vec!["name", "scopes"]);
+        assert_eq!(long_name[0].code, "too_long");
+    }
+
+    #[tokio::test]
+    async fn api_keys_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let access_token = register_and_login(&routes, &mail_dir, "bot-owner@example.com").await;
+        let created = warp::test::request()
+            .method("POST")
+            .path("/account/api-keys")
+            .header("Authorization", &access_token)
+            .json(&serde_json::json!({ "name": "question bot", "scopes": ["questions:write"] }))
+            .reply(&routes)
+            .await;
+        let created_key: types::api_key::CreatedApiKey = serde_json::from_slice(created.body()).unwrap();
+        let key = created_key.key.as_str();
+        let reader = warp::test::request()
+            .method("POST")
+            .path("/account/api-keys")
+            .header("Authorization", &access_token)
+            .json(&serde_json::json!({ "name": "reader", "scopes": ["questions:read", "answers:read"] }))
+            .reply(&routes)
+            .await;
+        let reader_key = serde_json::from_slice::<types::api_key::CreatedApiKey>(reader.body()).unwrap().key;
+        let read = |path: &str, key: Option<&str>| {
+            let request = warp::test::request().method("GET").path(path);
+            match key {
+                Some(key) => request.header("X-API-Key", key),
+                None => request,
+            }
+        };
+        let post_question = |key: &str| warp::test::request()
+            .method("POST")
+            .path("/questions")
+            .header("X-API-Key", key)
+            .json(&serde_json::json!({ "title": "From a bot", "content": "Beep?" }));
+
+        // act
+        let question = post_question(key).reply(&routes).await;
+        let answer = warp::test::request()
+            .method("POST")
+            .path("/answers")
+            .header("X-API-Key", key)
+            .body("content=Boop&question_id=1")
+            .reply(&routes)
+            .await;
+        let read_without_scope = read("/questions/1", Some(key)).reply(&routes).await;
+        let read_with_scope = read("/questions/1", Some(&reader_key)).reply(&routes).await;
+        let answers_with_scope = read("/questions/1/answers", Some(&reader_key)).reply(&routes).await;
+        let read_anonymously = read("/questions/1", None).reply(&routes).await;
+        let read_with_unknown_key = read("/questions", Some("qa_unknown")).reply(&routes).await;
+        let tags_with_unknown_key = read("/tags", Some("qa_unknown")).reply(&routes).await;
+        let tags_with_scope = read("/tags", Some(&reader_key)).reply(&routes).await;
+        let user_without_scope = read("/users/1", Some(key)).reply(&routes).await;
+        let user_with_scope = read("/users/1", Some(&reader_key)).reply(&routes).await;
+        let account = warp::test::request()
+            .method("GET")
+            .path("/account")
+            .header("X-API-Key", key)
+            .reply(&routes)
+            .await;
+        let unknown = post_question("qa_unknown").reply(&routes).await;
+        let invalid = warp::test::request()
+            .method("POST")
+            .path("/account/api-keys")
+            .header("Authorization", &access_token)
+            .json(&serde_json::json!({ "name": " ", "scopes": [] }))
+            .reply(&routes)
+            .await;
+        let listed = warp::test::request()
+            .method("GET")
+            .path("/account/api-keys")
+            .header("Authorization", &access_token)
+            .reply(&routes)
+            .await;
+        let revoke = |id: i32| warp::test::request()
+            .method("DELETE")
+            .path(&format!("/account/api-keys/{}", id))
+            .header("Authorization", &access_token);
+        let revoked = revoke(created_key.api_key.id.0).reply(&routes).await;
+        let revoked_again = revoke(created_key.api_key.id.0).reply(&routes).await;
+        let after_revocation = post_question(key).reply(&routes).await;
+
+        // assert
+        assert_eq!(created.status(), 201);
+        assert!(key.starts_with(&created_key.api_key.prefix));
+        assert_eq!(question.status(), 201);
+        assert_eq!(answer.status(), 403);
+        assert_eq!(read_without_scope.status(), 403);
+        assert_eq!(read_with_scope.status(), 200);
+        assert_eq!(answers_with_scope.status(), 200);
+        assert_eq!(read_anonymously.status(), 200);
+        assert_eq!(read_with_unknown_key.status(), 401);
+        assert_eq!(tags_with_unknown_key.status(), 401);
+        assert_eq!(tags_with_scope.status(), 200);
+        assert_eq!(user_without_scope.status(), 403);
+        assert_eq!(user_with_scope.status(), 200);
+        assert_eq!(account.status(), 401);
+        assert_eq!(unknown.status(), 401);
+        assert_eq!(invalid.status(), 422);
+        let listed: serde_json::Value = serde_json::from_slice(listed.body()).unwrap();
+        assert_eq!(listed[0]["name"], "question bot");
+        assert!(listed[0]["last_used_on"].is_string());
+        assert!(listed[0].get("key").is_none());
+        assert!(!listed.to_string().contains(key));
+        assert_eq!(revoked.status(), 200);
+        assert_eq!(revoked_again.status(), 404);
+        assert_eq!(after_revocation.status(), 401);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+}
@@ -1,96 +1,819 @@
-use argon2::{self, Config};
+use argon2::{self, Config, Variant, Version};
 use rand::Rng;
+use sha2::{Digest, Sha256};
 use warp::Filter;
 
+use crate::types::account::{AccountId, Role, Session, StoredSession, TokenPair};
+use crate::types::api_key::Scope;
+
+/// Lifetime of an access token. Short, since only refreshing checks the account again.
+const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+/// Lifetime of a login session, after which the user has to log in again.
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
 
 pub async fn register(
-    store: crate::store::Store,
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    mailer: crate::mail::DynMailSender,
+    public_url: String,
+    policy: PasswordPolicy,
+    rules: crate::validation::PasswordRules,
     account: crate::types::account::Account
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let hashed_password = hash_password(account.password.as_bytes());
+    let email = crate::validation::normalize_email(&account.email);
+    let mut errors = crate::validation::check_email("email", &email);
+    errors.extend(rules.check("password", &account.password, &email));
+    crate::validation::validate(errors).map_err(warp::reject::custom)?;
+
+    let hashed_password = hash_password(&policy, account.password.as_bytes());
 
     let account = crate::types::account::Account {
         id: account.id,
-        email: account.email,
+        email,
         password: hashed_password,
+        role: Role::User,
+        email_verified: false,
+        profile: Default::default(),
 };
-  match store.add_account(account).await {
-    Ok(_) => {
-      Ok(warp::reply::with_status(
-        "Account added",
-        warp::http::StatusCode::OK
-      ))
-    },
-    Err(e) => Err(warp::reject::custom(e)),
+  let email = account.email.clone();
+  if let Err(e) = store.add_account(account).await {
+    return Err(warp::reject::custom(e));
   }
+  let account = match store.get_account(email).await {
+    Ok(account) => account,
+    Err(e) => return Err(warp::reject::custom(e)),
+  };
+  super::verification::send_verification(&mailer, &keyring, &public_url, &account).await?;
+
+  Ok(warp::reply::with_status(
+    "Account added, check your e-mail to verify it",
+    warp::http::StatusCode::OK
+  ))
 }
 
+/// Checks the credentials, throttled per account and per client address.
+/// Unknown addresses cost as much time as wrong passwords and give the
+/// same answer. Passwords hashed under an older policy are hashed again.
+/// Accounts with two factors get a challenge for `POST /login/2fa` instead
+/// of a session.
 pub async fn login(
-    store: crate::store::Store,
+    remote: Option<std::net::SocketAddr>,
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    throttle: std::sync::Arc<crate::throttle::LoginThrottle>,
+    policy: PasswordPolicy,
     login: crate::types::account::Account
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    match store.get_account(login.email).await {
-        Ok(account) => match verify_password(&account.password, login.password.as_bytes()) {
-            Ok(verified) => {
-                if verified {
-                    Ok(warp::reply::json(&issue_token(account.id.expect("id not found"))))
-                } else {
-                    Err(warp::reject::custom(handle_errors::Error::WrongPassword))
+    let login = crate::types::account::Account {
+        email: crate::validation::normalize_email(&login.email),
+        ..login
+    };
+    let ip = remote.map(|addr| addr.ip());
+    check_throttle(&throttle, &login.email, ip)?;
+
+    let account = match store.get_account(login.email.clone()).await {
+        Ok(account) => Some(account),
+        Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => None,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let hash = match &account {
+        Some(account) => account.password.clone(),
+        None => dummy_hash(&policy),
+    };
+    let verified = match verify_password(&hash, login.password.as_bytes()) {
+        Ok(verified) => verified,
+        Err(e) => {
+            tracing::event!(tracing::Level::WARN, "stored password hash can't be read: {:?}", e);
+            false
+        },
+    };
+
+    match account {
+        Some(account) if verified => {
+            throttle.record_success(&login.email);
+            let account_id = account.id.clone().expect("id not found");
+            if policy.needs_rehash(&account.password) {
+                let rehashed = hash_password(&policy, login.password.as_bytes());
+                if let Err(e) = store.update_password(&account_id, &rehashed).await {
+                    tracing::event!(tracing::Level::WARN, "rehashing password failed: {:?}", e);
                 }
-            },
-            Err(e) => Err(warp::reject::custom(handle_errors::Error::ArgonLibraryError(e)))
+            }
+            super::two_factor::session_or_challenge(&store, &keyring, account).await
+        },
+        _ => {
+            throttle.record_failure(&login.email, ip, std::time::Instant::now());
+            Err(warp::reject::custom(handle_errors::Error::WrongPassword))
+        }
+    }
+}
+
+/// Rejects with `TooManyAttempts` while the throttle holds `email` or `ip` back.
+pub(crate) fn check_throttle(
+    throttle: &crate::throttle::LoginThrottle,
+    email: &str,
+    ip: Option<std::net::IpAddr>,
+) -> Result<(), warp::Rejection> {
+    match throttle.retry_after(email, ip, std::time::Instant::now()) {
+        Some(wait) => {
+            let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+            Err(warp::reject::custom(handle_errors::Error::TooManyAttempts(seconds)))
+        },
+        None => Ok(()),
+    }
+}
+
+/// Checks the password of a logged in account before a sensitive change.
+/// Wrong ones count towards the login throttle, so a stolen access token
+/// can't be used to guess the password.
+pub(crate) fn confirm_password(
+    throttle: &crate::throttle::LoginThrottle,
+    remote: Option<std::net::SocketAddr>,
+    account: &crate::types::account::Account,
+    password: &str,
+) -> Result<(), warp::Rejection> {
+    let ip = remote.map(|addr| addr.ip());
+    check_throttle(throttle, &account.email, ip)?;
+    match verify_password(&account.password, password.as_bytes()) {
+        Ok(true) => {
+            throttle.record_success(&account.email);
+            Ok(())
         },
-        Err(e) => Err(warp::reject::custom(e))
+        Ok(false) => {
+            throttle.record_failure(&account.email, ip, std::time::Instant::now());
+            Err(warp::reject::custom(handle_errors::Error::WrongPassword))
+        },
+        Err(e) => {
+            tracing::event!(tracing::Level::WARN, "stored password hash can't be read: {:?}", e);
+            throttle.record_failure(&account.email, ip, std::time::Instant::now());
+            Err(warp::reject::custom(handle_errors::Error::WrongPassword))
+        },
+    }
+}
+
+/// Hash verified against when the account does not exist, so that takes
+/// as long as checking a real password. One per policy, so it keeps up
+/// when the policy changes.
+fn dummy_hash(policy: &PasswordPolicy) -> String {
+    static HASHES: std::sync::OnceLock<std::sync::Mutex<std::collections::HashMap<PasswordPolicy, String>>> = std::sync::OnceLock::new();
+    HASHES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .entry(*policy)
+        .or_insert_with(|| hash_password(policy, &rand::thread_rng().gen::<[u8; 32]>()))
+        .clone()
+}
+
+/// Argon2id cost parameters new password hashes are created with, see the
+/// `argon2_*` options in `Config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PasswordPolicy {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl PasswordPolicy {
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        PasswordPolicy {
+            memory_kib: config.argon2_memory_kib,
+            iterations: config.argon2_iterations,
+            parallelism: config.argon2_parallelism,
+        }
+    }
+
+    /// Whether `hash` was created with another algorithm or with any
+    /// parameter below the policy. Stronger hashes are left alone.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let mut parts = hash.split('$').skip(1);
+        if parts.next() != Some("argon2id") || parts.next() != Some("v=19") {
+            return true;
+        }
+        let mut params = (None, None, None);
+        for param in parts.next().unwrap_or_default().split(',') {
+            match param.split_once('=') {
+                Some(("m", value)) => params.0 = value.parse::<u32>().ok(),
+                Some(("t", value)) => params.1 = value.parse::<u32>().ok(),
+                Some(("p", value)) => params.2 = value.parse::<u32>().ok(),
+                _ => (),
+            }
+        }
+
+        match params {
+            (Some(memory_kib), Some(iterations), Some(parallelism)) => {
+                memory_kib < self.memory_kib || iterations < self.iterations || parallelism < self.parallelism
+            },
+            _ => true,
+        }
     }
 }
 
-fn hash_password(password: &[u8]) -> String {
+pub(crate) fn hash_password(policy: &PasswordPolicy, password: &[u8]) -> String {
     let salt = rand::thread_rng().gen::<[u8; 32]>();
-    let config = Config::default();
+    let config = Config {
+        variant: Variant::Argon2id,
+        version: Version::Version13,
+        mem_cost: policy.memory_kib,
+        time_cost: policy.iterations,
+        lanes: policy.parallelism,
+        ..Config::default()
+    };
     argon2::hash_encoded(password, &salt, &config).unwrap()
 }
 
-fn verify_password(hash: &str, password: &[u8]) -> Result<bool, argon2::Error> {
+pub(crate) fn verify_password(hash: &str, password: &[u8]) -> Result<bool, argon2::Error> {
     argon2::verify_encoded(hash, password)
 }
 
-fn issue_token(account_id: crate::types::account::AccountId) -> String {
-    let current_date_time = chrono::Utc::now();
-    let dt = current_date_time + chrono::Duration::days(1);
+/// Exchanges a refresh token for a new token pair. The old refresh token
+/// stops working, the session and its id stay the same.
+pub async fn refresh(
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    request: crate::types::account::RefreshRequest,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let refresh_token = generate_refresh_token();
+    let session = match store.rotate_refresh_token(
+        &hash_refresh_token(&request.refresh_token),
+        &hash_refresh_token(&refresh_token),
+        chrono::Utc::now().naive_utc(),
+    ).await {
+        Ok(Some(session)) => session,
+        Ok(None) => return Err(warp::reject::custom(handle_errors::Error::TokenError)),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let account = match store.get_account_by_id(&session.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
 
-    let key = std::env::var("PASETO_KEY").unwrap();
+    Ok(warp::reply::json(&TokenPair {
+        access_token: issue_token(&keyring, session.account_id, account.role, &session.id),
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+    }))
+}
 
-    paseto::tokens::PasetoBuilder::new()
-        .set_encryption_key(&Vec::from(key.as_bytes()))
-        .set_expiration(&dt)
-        .set_not_before(&current_date_time)
-        .set_claim("account_id", serde_json::json!(account_id))
-        .build()
-        .expect("Failed to create token")
+/// Revokes the session the presented access token belongs to.
+pub async fn logout(
+    session: Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.revoke_session(&session.jti, chrono::Utc::now().naive_utc()).await {
+        Ok(_) => Ok(warp::reply::with_status("Logged out", warp::http::StatusCode::OK)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Revokes every session of the account, logging it out on all devices.
+pub async fn logout_all(
+    session: Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.revoke_sessions(&session.account_id, chrono::Utc::now().naive_utc()).await {
+        Ok(revoked) => Ok(warp::reply::json(&serde_json::json!({ "revoked_sessions": revoked }))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub(crate) async fn start_session(
+    store: &crate::store::DynStore,
+    keyring: &crate::keyring::Keyring,
+    account_id: AccountId,
+    role: Role,
+) -> Result<TokenPair, warp::Rejection> {
+    let session = StoredSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        account_id,
+    };
+    let refresh_token = generate_refresh_token();
+    let expires_on = chrono::Utc::now().naive_utc() + chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS);
+    if let Err(e) = store.add_session(&session, &hash_refresh_token(&refresh_token), expires_on).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    Ok(TokenPair {
+        access_token: issue_token(keyring, session.account_id, role, &session.id),
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ACCESS_TOKEN_TTL_MINUTES * 60,
+    })
+}
+
+fn generate_refresh_token() -> String {
+    use base64::Engine;
+
+    let bytes = rand::thread_rng().gen::<[u8; 32]>();
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// Refresh tokens are random, so a fast unsalted hash is enough to keep
+/// them useless when read from the database.
+fn hash_refresh_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
 }
 
-fn verify_token(token: String) -> Result<crate::types::account::Session, handle_errors::Error> {
-    let key = std::env::var("PASETO_KEY").unwrap();
-    let token = paseto::tokens::validate_local_token(
-        &token,
-        None,
-        &key.as_bytes(),
-        &paseto::tokens::TimeBackend::Chrono
-    ).map_err(|_| handle_errors::Error::TokenError)?;
+fn issue_token(keyring: &crate::keyring::Keyring, account_id: AccountId, role: Role, session_id: &str) -> String {
+    keyring.issue(
+        keyring.access_token_format(),
+        &[
+            ("jti", serde_json::json!(session_id)),
+            ("account_id", serde_json::json!(account_id)),
+            ("role", serde_json::json!(role)),
+        ],
+        chrono::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES),
+    )
+}
 
-    serde_json::from_value::<crate::types::account::Session>(token)
+fn verify_token(keyring: &crate::keyring::Keyring, token: &str) -> Result<Session, handle_errors::Error> {
+    let token = keyring.validate(token)?;
+    // tokens for other purposes, like e-mail verification, must not authenticate
+    if token.get("purpose").is_some() {
+        return Err(handle_errors::Error::TokenError);
+    }
+
+    serde_json::from_value::<Session>(token)
         .map_err(|_| handle_errors::Error::TokenError)
 }
 
-pub fn auth() ->
-    impl warp::Filter<Extract = (crate::types::account::Session,), Error = warp::Rejection> + Clone
-{
-    warp::header::<String>("Authorization").and_then(|token: String| {
-        let token = match verify_token(token) {
-            Ok(t) => t,
-            Err(_) => return std::future::ready(Err(warp::reject::reject())),
-        };
+/// Token for a single task like verifying an e-mail address, carrying
+/// `claims` and the `purpose` it may be used for. Always encrypted, the
+/// claims may be secret.
+pub(crate) fn issue_purpose_token(
+    keyring: &crate::keyring::Keyring,
+    purpose: &str,
+    claims: &[(&str, serde_json::Value)],
+    ttl: chrono::Duration,
+) -> String {
+    let mut claims = claims.to_vec();
+    claims.push(("purpose", serde_json::json!(purpose)));
+    keyring.issue(crate::config::AccessTokenFormat::Local, &claims, ttl)
+}
+
+/// Claims of a token issued by `issue_purpose_token` for `purpose`.
+pub(crate) fn verify_purpose_token<T: serde::de::DeserializeOwned>(
+    keyring: &crate::keyring::Keyring,
+    token: &str,
+    purpose: &str,
+) -> Result<T, handle_errors::Error> {
+    let token = keyring.validate_local(token)?;
+    if token.get("purpose") != Some(&serde_json::json!(purpose)) {
+        return Err(handle_errors::Error::TokenError);
+    }
+
+    serde_json::from_value::<T>(token)
+        .map_err(|_| handle_errors::Error::TokenError)
+}
+
+/// Accepts the access token from the `Authorization` header, with or
+/// without a `Bearer ` prefix, as long as its session was not revoked.
+pub fn auth(
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+) -> impl warp::Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    warp::header::<String>("Authorization").and_then(move |token: String| {
+        let store = store.clone();
+        let keyring = keyring.clone();
+        async move { token_session(&store, &keyring, &token).await }
+    })
+}
+
+async fn token_session(
+    store: &crate::store::DynStore,
+    keyring: &crate::keyring::Keyring,
+    token: &str,
+) -> Result<Session, warp::Rejection> {
+    let token = token.strip_prefix("Bearer ").unwrap_or(token);
+    let session = verify_token(keyring, token).map_err(warp::reject::custom)?;
+    match store.is_session_active(&session.jti, chrono::Utc::now().naive_utc()).await {
+        Ok(true) => Ok(session),
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::TokenError)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
 
-        std::future::ready(Ok(token))
+/// Session of a request made with a live API key. The key acts with the
+/// current role of its account but only within `scope`.
+async fn api_key_session(store: &crate::store::DynStore, key: &str, scope: Scope) -> Result<Session, warp::Rejection> {
+    let now = chrono::Utc::now();
+    let hash = super::api_key::hash_api_key(key);
+    let api_key = match store.use_api_key(&hash, now.naive_utc()).await {
+        Ok(Some(api_key)) => api_key,
+        Ok(None) => return Err(warp::reject::custom(handle_errors::Error::TokenError)),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    if !api_key.has_scope(scope) {
+        return Err(warp::reject::custom(handle_errors::Error::Forbidden));
+    }
+    let account = match store.get_account_by_id(&api_key.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(Session {
+        exp: now,
+        account_id: api_key.account_id,
+        nbf: now,
+        jti: String::new(),
+        role: account.role,
+        api_key: Some(api_key.id),
+    })
+}
+
+/// Like `auth()`, but also accepts an API key with `scope` in the
+/// `X-API-Key` header. The key wins if both are sent.
+pub fn auth_scoped(
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    scope: Scope,
+) -> impl warp::Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("X-API-Key")
+        .and(warp::header::optional::<String>("Authorization"))
+        .and_then(move |api_key: Option<String>, token: Option<String>| {
+            let store = store.clone();
+            let keyring = keyring.clone();
+            async move {
+                match (api_key, token) {
+                    (Some(api_key), _) => api_key_session(&store, &api_key, scope).await,
+                    (None, Some(token)) => token_session(&store, &keyring, &token).await,
+                    (None, None) => Err(warp::reject::custom(handle_errors::Error::TokenError)),
+                }
+            }
+        })
+}
+
+/// Lets anonymous requests through, but requires a live API key with
+/// `scope` from requests that send an `X-API-Key` header.
+pub fn api_key_scope(
+    store: crate::store::DynStore,
+    scope: Scope,
+) -> impl warp::Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("X-API-Key")
+        .and_then(move |api_key: Option<String>| {
+            let store = store.clone();
+            async move {
+                match api_key {
+                    Some(api_key) => api_key_session(&store, &api_key, scope).await.map(|_| ()),
+                    None => Ok(()),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Like `auth()`, but additionally requires `role` or a higher one.
+/// Rejects with `Forbidden` (403) when the token is valid but the role is not.
+pub fn require_role(
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    role: Role,
+) -> impl warp::Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(store, keyring).and_then(move |session: Session| {
+        if session.has_role(role) {
+            std::future::ready(Ok(session))
+        } else {
+            std::future::ready(Err(warp::reject::custom(handle_errors::Error::Forbidden)))
+        }
+    })
+}
+
+/// Like `auth()`, but additionally requires a verified e-mail address.
+pub fn auth_verified(
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+) -> impl warp::Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth(store.clone(), keyring).and_then(move |session: Session| {
+        let store = store.clone();
+        async move { require_verified(&store, session).await }
+    })
+}
+
+/// Like `auth_scoped()`, but additionally requires a verified e-mail address.
+pub fn auth_verified_scoped(
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    scope: Scope,
+) -> impl warp::Filter<Extract = (Session,), Error = warp::Rejection> + Clone {
+    auth_scoped(store.clone(), keyring, scope).and_then(move |session: Session| {
+        let store = store.clone();
+        async move { require_verified(&store, session).await }
     })
 }
+
+async fn require_verified(store: &crate::store::DynStore, session: Session) -> Result<Session, warp::Rejection> {
+    match store.get_account_by_id(&session.account_id).await {
+        Ok(account) if account.email_verified => Ok(session),
+        Ok(_) => Err(warp::reject::custom(handle_errors::Error::EmailNotVerified)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+
+#[cfg(test)]
+mod authentication_tests {
+    use super::*;
+    use base64::Engine;
+    use crate::{keyring, store, types};
+    use crate::store::AccountRepository;
+    use crate::routes::test_support::{login, register_and_login, test_routes, test_routes_with, TEST_PASSWORD};
+
+    const POLICY: PasswordPolicy = PasswordPolicy { memory_kib: 1024, iterations: 1, parallelism: 1 };
+
+    #[test]
+    fn hashes_with_argon2id() {
+        // act
+        let hash = hash_password(&POLICY, b"secret");
+
+        // assert
+        assert!(hash.starts_with("$argon2id$v=19$m=1024,t=1,p=1$"));
+        assert!(verify_password(&hash, b"secret").unwrap());
+        assert!(!verify_password(&hash, b"wrong").unwrap());
+        assert!(!POLICY.needs_rehash(&hash));
+    }
+
+    #[test]
+    fn rehash_on_weaker_or_other_parameters() {
+        // arrange
+        let argon2i = argon2::hash_encoded(b"secret", b"saltsaltsalt", &Config::default()).unwrap();
+        let weaker = hash_password(&POLICY, b"secret");
+        let stronger = PasswordPolicy { memory_kib: 2048, ..POLICY };
+
+        // assert
+        assert!(POLICY.needs_rehash(&argon2i));
+        assert!(stronger.needs_rehash(&weaker));
+        assert!(PasswordPolicy { iterations: 2, ..POLICY }.needs_rehash(&weaker));
+        assert!(PasswordPolicy { parallelism: 2, ..POLICY }.needs_rehash(&weaker));
+        assert!(POLICY.needs_rehash("not a hash"));
+        assert!(!POLICY.needs_rehash(&weaker));
+        assert!(!POLICY.needs_rehash(&hash_password(&stronger, b"secret")));
+        assert!(!POLICY.needs_rehash(&hash_password(&PasswordPolicy { iterations: 2, ..POLICY }, b"secret")));
+    }
+
+    #[test]
+    fn dummy_hash_follows_the_policy() {
+        // arrange
+        let stronger = PasswordPolicy { memory_kib: 2048, ..POLICY };
+
+        // act
+        let dummy = dummy_hash(&POLICY);
+        let stronger_dummy = dummy_hash(&stronger);
+
+        // assert
+        assert_eq!(dummy, dummy_hash(&POLICY));
+        assert!(stronger_dummy.contains("m=2048,"));
+        assert!(stronger.needs_rehash(&dummy));
+        assert!(!stronger.needs_rehash(&stronger_dummy));
+    }
+
+    #[tokio::test]
+    async fn registration_with_in_memory_store() {
+        // arrange
+        let (routes, _) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let account = serde_json::json!({
+            "email": "test@example.com",
+            "password": TEST_PASSWORD,
+        });
+
+        // act
+        let first = warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&account)
+            .reply(&routes)
+            .await;
+        let second = warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&account)
+            .reply(&routes)
+            .await;
+
+        // assert
+        assert_eq!(first.status(), 200);
+        assert_eq!(second.status(), 409);
+        let error: serde_json::Value = serde_json::from_slice(second.body()).unwrap();
+        assert_eq!(error["code"], "account_exists");
+    }
+
+    #[tokio::test]
+    async fn registration_validation_with_in_memory_store() {
+        // arrange
+        let (routes, _) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let register = |email: &str, password: &str| warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&serde_json::json!({ "email": email, "password": password }));
+
+        // act
+        let invalid = register("not-an-address", "password").reply(&routes).await;
+        let first = register(" Test@Example.com ", TEST_PASSWORD).reply(&routes).await;
+        let different_case = register("test@EXAMPLE.com", TEST_PASSWORD).reply(&routes).await;
+        let login = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&serde_json::json!({ "email": "TEST@example.com", "password": TEST_PASSWORD }))
+            .reply(&routes)
+            .await;
+
+        // assert
+        assert_eq!(invalid.status(), 422);
+        let error: serde_json::Value = serde_json::from_slice(invalid.body()).unwrap();
+        assert_eq!(error["code"], "validation_failed");
+        let fields: Vec<(&str, &str)> = error["details"]["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|f| (f["field"].as_str().unwrap(), f["code"].as_str().unwrap()))
+            .collect();
+        assert!(fields.contains(&("email", "invalid")));
+        assert!(fields.contains(&("password", "too_short")));
+        assert_eq!(first.status(), 200);
+        assert_eq!(different_case.status(), 409);
+        assert_eq!(login.status(), 200);
+    }
+
+    #[tokio::test]
+    async fn sessions_with_in_memory_store() {
+        // arrange
+        let (routes, _) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let account = serde_json::json!({ "email": "test@example.com", "password": TEST_PASSWORD });
+        warp::test::request().method("POST").path("/registration").json(&account).reply(&routes).await;
+        let account = &account;
+        let login = |routes| async move {
+            let reply = warp::test::request().method("POST").path("/login").json(account).reply(routes).await;
+            serde_json::from_slice::<types::account::TokenPair>(reply.body()).unwrap()
+        };
+        let phone = login(&routes).await;
+        let laptop = login(&routes).await;
+        let refresh = |token: &str| warp::test::request()
+            .method("POST")
+            .path("/token/refresh")
+            .json(&serde_json::json!({ "refresh_token": token }));
+        let logout = |path: &str, token: &str| warp::test::request()
+            .method("POST")
+            .path(path)
+            .header("Authorization", format!("Bearer {}", token));
+
+        // act
+        let refreshed = refresh(&phone.refresh_token).reply(&routes).await;
+        let reused = refresh(&phone.refresh_token).reply(&routes).await;
+        let tokens: types::account::TokenPair = serde_json::from_slice(refreshed.body()).unwrap();
+        let logged_out = logout("/logout", &tokens.access_token).reply(&routes).await;
+        let revoked_access = logout("/logout", &phone.access_token).reply(&routes).await;
+        let revoked_refresh = refresh(&tokens.refresh_token).reply(&routes).await;
+        let tablet = login(&routes).await;
+        let everywhere = logout("/logout/all", &tablet.access_token).reply(&routes).await;
+        let laptop_after = refresh(&laptop.refresh_token).reply(&routes).await;
+
+        // assert
+        assert_eq!(phone.token_type, "Bearer");
+        assert_eq!(refreshed.status(), 200);
+        assert_ne!(tokens.refresh_token, phone.refresh_token);
+        assert_eq!(reused.status(), 401);
+        assert_eq!(logged_out.status(), 200);
+        assert_eq!(revoked_access.status(), 401);
+        assert_eq!(revoked_refresh.status(), 401);
+        assert_eq!(everywhere.status(), 200);
+        let revoked: serde_json::Value = serde_json::from_slice(everywhere.body()).unwrap();
+        assert_eq!(revoked["revoked_sessions"], 2);
+        assert_eq!(laptop_after.status(), 401);
+    }
+
+    #[tokio::test]
+    async fn login_throttling_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes_with(
+            std::sync::Arc::new(store::InMemoryStore::new()),
+            &["--login-max-failures", "3", "--login-ip-max-failures", "4", "--login-backoff-ms", "0"],
+        ).await;
+        register_and_login(&routes, &mail_dir, "test@example.com").await;
+        let attempt = |email: &str, password: &str, ip: [u8; 4]| warp::test::request()
+            .method("POST")
+            .path("/login")
+            .remote_addr((ip, 4000).into())
+            .json(&serde_json::json!({ "email": email, "password": password }));
+
+        // act
+        let unknown = attempt("nobody@example.com", TEST_PASSWORD, [10, 0, 0, 1]).reply(&routes).await;
+        let mut failures = Vec::new();
+        for _ in 0..3 {
+            failures.push(attempt("test@example.com", "wrong", [10, 0, 0, 2]).reply(&routes).await.status());
+        }
+        let locked = attempt("test@example.com", TEST_PASSWORD, [10, 0, 0, 3]).reply(&routes).await;
+        let ip_locked = attempt("nobody@example.com", TEST_PASSWORD, [10, 0, 0, 2]).reply(&routes).await;
+        let other_ip = attempt("nobody@example.com", TEST_PASSWORD, [10, 0, 0, 4]).reply(&routes).await;
+
+        // assert
+        assert_eq!(unknown.status(), 401);
+        let error: serde_json::Value = serde_json::from_slice(unknown.body()).unwrap();
+        assert_eq!(error["code"], "wrong_credentials");
+        assert_eq!(failures, vec![401, 401, 401]);
+        assert_eq!(locked.status(), 429);
+        assert_eq!(locked.headers()["retry-after"], "900");
+        assert_eq!(ip_locked.status(), 401);
+        assert_eq!(other_ip.status(), 401);
+        let ip_locked_now = attempt("nobody@example.com", TEST_PASSWORD, [10, 0, 0, 2]).reply(&routes).await;
+        assert_eq!(ip_locked_now.status(), 429);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn password_confirmations_are_throttled_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes_with(
+            std::sync::Arc::new(store::InMemoryStore::new()),
+            &["--login-max-failures", "3", "--login-backoff-ms", "0"],
+        ).await;
+        let token = register_and_login(&routes, &mail_dir, "test@example.com").await;
+        let confirm = |method: &str, path: &str, body: serde_json::Value| warp::test::request()
+            .method(method)
+            .path(path)
+            .header("Authorization", &token)
+            .json(&body);
+
+        // act
+        let wrong_change = confirm("PUT", "/account/password", serde_json::json!({ "old_password": "wrong", "new_password": "Changed Macintosh 42" }))
+            .reply(&routes)
+            .await;
+        let wrong_disable = confirm("DELETE", "/account/2fa", serde_json::json!({ "password": "wrong" })).reply(&routes).await;
+        let wrong_delete = confirm("DELETE", "/account", serde_json::json!({ "password": "wrong" })).reply(&routes).await;
+        let locked_delete = confirm("DELETE", "/account", serde_json::json!({ "password": TEST_PASSWORD })).reply(&routes).await;
+        let locked_login = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&serde_json::json!({ "email": "test@example.com", "password": TEST_PASSWORD }))
+            .reply(&routes)
+            .await;
+
+        // assert
+        assert_eq!(wrong_change.status(), 401);
+        assert_eq!(wrong_disable.status(), 401);
+        assert_eq!(wrong_delete.status(), 401);
+        assert_eq!(locked_delete.status(), 429);
+        assert_eq!(locked_login.status(), 429);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rehash_on_login_with_in_memory_store() {
+        // arrange
+        let store = std::sync::Arc::new(store::InMemoryStore::new());
+        let (weak_routes, mail_dir) = test_routes(store.clone()).await;
+        register_and_login(&weak_routes, &mail_dir, "test@example.com").await;
+        let (routes, _) = test_routes_with(store.clone(), &["--argon2-memory-kib", "2048"]).await;
+
+        // act
+        login(&routes, "test@example.com").await;
+
+        // assert
+        let account = store.get_account("test@example.com".to_string()).await.unwrap();
+        assert!(account.password.starts_with("$argon2id$v=19$m=2048,t=1,p=1$"));
+        assert!(!login(&routes, "test@example.com").await.is_empty());
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn public_access_tokens_with_in_memory_store() {
+        // arrange
+        let store: store::DynStore = std::sync::Arc::new(store::InMemoryStore::new());
+        let (routes, mail_dir) = test_routes_with(store.clone(), &["--access-token-format", "public"]).await;
+        let (local_routes, local_mail_dir) = test_routes_with(store, &[]).await;
+        let get_account = |token: &str| warp::test::request()
+            .method("GET")
+            .path("/account")
+            .header("Authorization", token);
+
+        // act
+        let access_token = register_and_login(&routes, &mail_dir, "public@example.com").await;
+        let published = warp::test::request()
+            .method("GET")
+            .path("/.well-known/paseto-keys")
+            .reply(&routes)
+            .await;
+        let published: keyring::PublicKeys = serde_json::from_slice(published.body()).unwrap();
+        let public_key = base64::engine::general_purpose::URL_SAFE_NO_PAD.decode(&published.keys[0].public_key).unwrap();
+        let verified = paseto::tokens::validate_public_token(
+            &access_token,
+            Some(r#"{"kid":"test"}"#),
+            &paseto::tokens::PasetoPublicKey::ED25519PublicKey(&public_key),
+            &paseto::tokens::TimeBackend::Chrono,
+        );
+        let own_account = get_account(&access_token).reply(&routes).await;
+        let on_local_routes = get_account(&access_token).reply(&local_routes).await;
+        let local_token = login(&local_routes, "public@example.com").await;
+        let local_on_public_routes = get_account(&local_token).reply(&routes).await;
+
+        // assert
+        assert!(access_token.starts_with("v2.public."));
+        assert_eq!(published.keys[0].kid, "test");
+        assert!(verified.unwrap()["account_id"].is_number());
+        assert_eq!(own_account.status(), 200);
+        assert_eq!(on_local_routes.status(), 200);
+        assert!(local_token.starts_with("v2.local."));
+        assert_eq!(local_on_public_routes.status(), 200);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+        std::fs::remove_dir_all(local_mail_dir).ok();
+    }
+}
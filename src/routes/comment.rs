@@ -0,0 +1,187 @@
+use crate::types::account::Session;
+use crate::types::comment::{Comment, CommentTarget, NewComment};
+
+/// Rejects with `QuestionNotFound` or `AnswerNotFound` unless the target exists.
+async fn check_target(store: &crate::store::DynStore, target: &CommentTarget) -> Result<(), warp::Rejection> {
+    match target {
+        CommentTarget::Question(id) => match store.get_question_owner(id.0).await? {
+            Some(_) => Ok(()),
+            None => Err(warp::reject::custom(handle_errors::Error::QuestionNotFound)),
+        },
+        CommentTarget::Answer(id) => match store.get_answer_owner(id.0).await? {
+            Some(_) => Ok(()),
+            None => Err(warp::reject::custom(handle_errors::Error::AnswerNotFound)),
+        },
+    }
+}
+
+/// The comment, as long as it is on `target` and written by the logged in
+/// account. Unlike questions and answers, moderators can't change comments.
+async fn own_comment(
+    store: &crate::store::DynStore,
+    target: &CommentTarget,
+    id: i32,
+    session: &Session,
+) -> Result<Comment, warp::Rejection> {
+    let comment = match store.get_comment(id).await? {
+        Some(comment) if &comment.target == target => comment,
+        _ => return Err(warp::reject::custom(handle_errors::Error::CommentNotFound)),
+    };
+    if comment.author.id != session.account_id {
+        return Err(warp::reject::custom(handle_errors::Error::Forbidden));
+    }
+    Ok(comment)
+}
+
+pub async fn get_comments(
+    target: CommentTarget,
+    params: std::collections::HashMap<String, String>,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut limit = None;
+    let mut offset = 0;
+    if !params.is_empty() {
+        let pagination = crate::types::pagination::get_pagination(params);
+        limit = pagination.get_limit();
+        offset = pagination.get_offset();
+    }
+    check_target(&store, &target).await?;
+
+    match store.get_comments(&target, limit, offset).await {
+        Ok(comments) => Ok(warp::reply::json(&comments)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn add_comment(
+    target: CommentTarget,
+    session: Session,
+    store: crate::store::DynStore,
+    profanity: crate::profanity::DynProfanityFilter,
+    new_comment: NewComment,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    check_target(&store, &target).await?;
+    let content = match crate::profanity::check_profanity(profanity.as_ref(), new_comment.content).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    match store.add_comment(&target, content, &session.account_id).await {
+        Ok(comment) => Ok(warp::reply::with_status(warp::reply::json(&comment), warp::hyper::StatusCode::CREATED)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn update_comment(
+    target: CommentTarget,
+    id: i32,
+    session: Session,
+    store: crate::store::DynStore,
+    profanity: crate::profanity::DynProfanityFilter,
+    comment: NewComment,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    own_comment(&store, &target, id, &session).await?;
+    let content = match crate::profanity::check_profanity(profanity.as_ref(), comment.content).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    match store.update_comment(id, content, &session.account_id).await {
+        Ok(comment) => Ok(warp::reply::json(&comment)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+pub async fn delete_comment(
+    target: CommentTarget,
+    id: i32,
+    session: Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    own_comment(&store, &target, id, &session).await?;
+
+    match store.delete_comment(id, &session.account_id).await {
+        Ok(_) => Ok(warp::reply::with_status("Comment deleted", warp::hyper::StatusCode::OK)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+
+#[cfg(test)]
+mod comment_tests {
+    use crate::{store, types};
+    use crate::routes::test_support::{register_and_login, test_routes};
+
+    #[tokio::test]
+    async fn comments_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let owner = register_and_login(&routes, &mail_dir, "owner@example.com").await;
+        let commenter = register_and_login(&routes, &mail_dir, "commenter@example.com").await;
+        warp::test::request()
+            .method("POST")
+            .path("/questions")
+            .header("Authorization", &owner)
+            .json(&serde_json::json!({ "title": "Question", "content": "Content" }))
+            .reply(&routes)
+            .await;
+        warp::test::request()
+            .method("POST")
+            .path("/answers")
+            .header("Authorization", &commenter)
+            .body("content=Answer&question_id=1")
+            .reply(&routes)
+            .await;
+        let comment = |method: &str, token: &str, path: &str, content: &str| warp::test::request()
+            .method(method)
+            .path(path)
+            .header("Authorization", token)
+            .json(&serde_json::json!({ "content": content }));
+        let get = |path: &str| warp::test::request().method("GET").path(path);
+
+        // act
+        let on_question = comment("POST", &commenter, "/questions/1/comments", "Nice question").reply(&routes).await;
+        let on_answer = comment("POST", &owner, "/answers/1/comments", "Thanks").reply(&routes).await;
+        let unknown_question = comment("POST", &owner, "/questions/9/comments", "Hello").reply(&routes).await;
+        let anonymous = warp::test::request()
+            .method("POST")
+            .path("/questions/1/comments")
+            .json(&serde_json::json!({ "content": "Hello" }))
+            .reply(&routes)
+            .await;
+        let question_comments = get("/questions/1/comments").reply(&routes).await;
+        let answer_comments = get("/answers/1/comments").reply(&routes).await;
+        let edit_by_other = comment("PUT", &owner, "/questions/1/comments/1", "Hijacked").reply(&routes).await;
+        let wrong_target = comment("PUT", &commenter, "/answers/1/comments/1", "Edited").reply(&routes).await;
+        let edited = comment("PUT", &commenter, "/questions/1/comments/1", "Edited").reply(&routes).await;
+        let delete_by_other = comment("DELETE", &commenter, "/answers/1/comments/2", "").reply(&routes).await;
+        let deleted = comment("DELETE", &owner, "/answers/1/comments/2", "").reply(&routes).await;
+        let deleted_again = comment("DELETE", &owner, "/answers/1/comments/2", "").reply(&routes).await;
+
+        // assert
+        assert_eq!(on_question.status(), 201);
+        let on_question: serde_json::Value = serde_json::from_slice(on_question.body()).unwrap();
+        assert_eq!(on_question["question_id"], 1);
+        assert_eq!(on_question["author"]["display_name"], "user2");
+        assert_eq!(on_answer.status(), 201);
+        let on_answer: serde_json::Value = serde_json::from_slice(on_answer.body()).unwrap();
+        assert_eq!(on_answer["answer_id"], 1);
+        assert_eq!(unknown_question.status(), 404);
+        assert_eq!(anonymous.status(), 401);
+        let question_comments: Vec<types::comment::Comment> = serde_json::from_slice(question_comments.body()).unwrap();
+        assert_eq!(question_comments.iter().map(|c| c.content.as_str()).collect::<Vec<_>>(), vec!["Nice question"]);
+        let answer_comments: Vec<types::comment::Comment> = serde_json::from_slice(answer_comments.body()).unwrap();
+        assert_eq!(answer_comments.iter().map(|c| c.content.as_str()).collect::<Vec<_>>(), vec!["Thanks"]);
+        assert_eq!(edit_by_other.status(), 403);
+        assert_eq!(wrong_target.status(), 404);
+        let error: serde_json::Value = serde_json::from_slice(wrong_target.body()).unwrap();
+        assert_eq!(error["code"], "comment_not_found");
+        assert_eq!(edited.status(), 200);
+        let edited: types::comment::Comment = serde_json::from_slice(edited.body()).unwrap();
+        assert_eq!(edited.content, "Edited");
+        assert_eq!(delete_by_other.status(), 403);
+        assert_eq!(deleted.status(), 200);
+        assert_eq!(deleted_again.status(), 404);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+}
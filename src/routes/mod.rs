@@ -1,3 +1,15 @@
+pub mod account;
+pub mod api_key;
 pub mod answer;
+pub mod comment;
 pub mod question;
 pub mod authentication;
+pub mod oidc;
+pub mod password;
+pub mod tag;
+pub mod two_factor;
+pub mod verification;
+pub mod vote;
+
+#[cfg(test)]
+mod test_support;
@@ -0,0 +1,358 @@
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::types::account::{Account, OidcCallbackQuery, Role};
+
+const PURPOSE: &str = "oidc_login";
+/// Cookie tying the callback to the browser that started the login.
+const COOKIE: &str = "oidc_login";
+/// How long the user has to sign in at the provider.
+const LOGIN_TTL_MINUTES: i64 = 10;
+
+#[derive(Deserialize)]
+struct LoginClaims {
+    state: String,
+    code_verifier: String,
+}
+
+/// Redirects to the provider. The state and PKCE verifier travel in an
+/// encrypted, short-lived cookie until the provider redirects back.
+pub async fn login(
+    provider: crate::oidc::DynOidcProvider,
+    public_url: String,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let state = crate::oidc::generate_code_verifier();
+    let code_verifier = crate::oidc::generate_code_verifier();
+    let location = provider
+        .authorization_url(&state, &crate::oidc::code_challenge(&code_verifier))
+        .await
+        .map_err(warp::reject::custom)?;
+    let token = super::authentication::issue_purpose_token(
+        &keyring,
+        PURPOSE,
+        &[
+            ("state", serde_json::json!(state)),
+            ("code_verifier", serde_json::json!(code_verifier)),
+        ],
+        chrono::Duration::minutes(LOGIN_TTL_MINUTES),
+    );
+
+    Ok(warp::http::Response::builder()
+        .status(warp::http::StatusCode::FOUND)
+        .header(warp::http::header::LOCATION, location)
+        .header(warp::http::header::SET_COOKIE, cookie(&public_url, &token, LOGIN_TTL_MINUTES * 60))
+        .body(String::new())
+        .expect("valid redirect"))
+}
+
+/// Signs in the account linked to the provider identity. Unknown
+/// identities are linked to the account with the same, provider verified
+/// address, or get a new account without a usable password. Accounts
+/// with two factors still have to take the second login step.
+pub async fn callback(
+    query: OidcCallbackQuery,
+    login_cookie: Option<String>,
+    provider: crate::oidc::DynOidcProvider,
+    public_url: String,
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    policy: super::authentication::PasswordPolicy,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Some(error) = query.error {
+        tracing::event!(tracing::Level::INFO, "identity provider denied the login: {}", error);
+        return Err(warp::reject::custom(handle_errors::Error::Unauthorized));
+    }
+    let invalid = |name: &str| warp::reject::custom(handle_errors::Error::InvalidParameter(name.to_string()));
+    let claims = login_cookie
+        .and_then(|token| super::authentication::verify_purpose_token::<LoginClaims>(&keyring, &token, PURPOSE).ok())
+        .ok_or_else(|| invalid("state"))?;
+    if query.state.as_deref() != Some(claims.state.as_str()) {
+        return Err(invalid("state"));
+    }
+    let code = query.code.ok_or_else(|| invalid("code"))?;
+
+    let user_info = provider
+        .user_info(&code, &claims.code_verifier)
+        .await
+        .map_err(warp::reject::custom)?;
+    let account = match store.get_account_by_identity(&provider.issuer, &user_info.sub).await {
+        Ok(Some(account)) => account,
+        Ok(None) => link_account(&store, &provider.issuer, &user_info, &policy).await?,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let reply = super::two_factor::session_or_challenge(&store, &keyring, account).await?;
+    Ok(warp::reply::with_header(
+        reply,
+        warp::http::header::SET_COOKIE,
+        cookie(&public_url, "", 0),
+    ))
+}
+
+async fn link_account(
+    store: &crate::store::DynStore,
+    issuer: &str,
+    user_info: &crate::oidc::UserInfo,
+    policy: &super::authentication::PasswordPolicy,
+) -> Result<Account, warp::Rejection> {
+    let email = user_info.email.as_deref().map(crate::validation::normalize_email).unwrap_or_default();
+    if !crate::validation::check_email("email", &email).is_empty() {
+        return Err(warp::reject::custom(handle_errors::Error::IdentityProviderError(
+            "no valid e-mail address in userinfo".to_string()
+        )));
+    }
+
+    let account = match store.get_account(email.clone()).await {
+        // only the owner of the address may take over its account
+        Ok(_) if !user_info.email_verified => {
+            return Err(warp::reject::custom(handle_errors::Error::AccountAlreadyExists));
+        },
+        Ok(account) if !account.email_verified => {
+            claim_unverified_account(store, &account, policy).await?;
+            account
+        },
+        Ok(account) => account,
+        Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)) => {
+            let password = rand::thread_rng().gen::<[u8; 32]>();
+            let account = Account {
+                id: None,
+                email: email.clone(),
+                password: super::authentication::hash_password(policy, &password),
+                role: Role::User,
+                email_verified: false,
+                profile: Default::default(),
+            };
+            store.add_account(account).await.map_err(warp::reject::custom)?;
+            store.get_account(email.clone()).await.map_err(warp::reject::custom)?
+        },
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let account_id = account.id.clone().expect("id not found");
+    if user_info.email_verified && !account.email_verified {
+        store.verify_email(&account_id, &email).await.map_err(warp::reject::custom)?;
+    }
+    store.link_identity(&account_id, issuer, &user_info.sub).await.map_err(warp::reject::custom)?;
+
+    store.get_account_by_id(&account_id).await.map_err(warp::reject::custom)
+}
+
+/// Whoever registered an address without verifying it may not own it, so
+/// their password, second factor and sessions go before the owner signs in.
+async fn claim_unverified_account(
+    store: &crate::store::DynStore,
+    account: &Account,
+    policy: &super::authentication::PasswordPolicy,
+) -> Result<(), warp::Rejection> {
+    let account_id = account.id.clone().expect("id not found");
+    let password = rand::thread_rng().gen::<[u8; 32]>();
+    store
+        .update_password(&account_id, &super::authentication::hash_password(policy, &password))
+        .await
+        .map_err(warp::reject::custom)?;
+    store.disable_two_factor(&account_id).await.map_err(warp::reject::custom)?;
+    store
+        .revoke_sessions(&account_id, chrono::Utc::now().naive_utc())
+        .await
+        .map_err(warp::reject::custom)?;
+    tracing::event!(target: "audit", tracing::Level::INFO, account_id = account_id.0, "unverified account claimed through OpenID Connect");
+    Ok(())
+}
+
+fn cookie(public_url: &str, value: &str, max_age: i64) -> String {
+    let secure = if public_url.starts_with("https://") { "; Secure" } else { "" };
+    format!("{}={}; Path=/oidc; Max-Age={}; HttpOnly; SameSite=Lax{}", COOKIE, value, max_age, secure)
+}
+
+
+#[cfg(test)]
+mod oidc_tests {
+    use warp::Filter;
+    use crate::{oidc, store};
+    use crate::store::AccountRepository;
+    use crate::routes::test_support::{login, test_routes_with, TEST_PASSWORD};
+
+    /// OpenID Connect provider on a local port that signs everyone in as
+    /// `user_info`, checking the PKCE verifier like a real provider.
+    fn mock_identity_provider(user_info: serde_json::Value) -> String {
+        use std::collections::HashMap;
+
+        let issuer = std::sync::Arc::new(std::sync::OnceLock::<String>::new());
+        let challenges = std::sync::Arc::new(std::sync::Mutex::new(HashMap::<String, String>::new()));
+
+        let discovery = {
+            let issuer = issuer.clone();
+            warp::path!(".well-known" / "openid-configuration").map(move || {
+                let issuer = issuer.get().unwrap();
+                warp::reply::json(&serde_json::json!({
+                    "issuer": issuer,
+                    "authorization_endpoint": format!("{}/authorize", issuer),
+                    "token_endpoint": format!("{}/token", issuer),
+                    "userinfo_endpoint": format!("{}/userinfo", issuer),
+                }))
+            })
+        };
+        let authorize = {
+            let challenges = challenges.clone();
+            warp::path!("authorize").and(warp::query()).map(move |query: HashMap<String, String>| {
+                assert_eq!(query["code_challenge_method"], "S256");
+                let code = uuid::Uuid::new_v4().to_string();
+                challenges.lock().unwrap().insert(code.clone(), query["code_challenge"].clone());
+                let callback = serde_urlencoded::to_string([("code", &code), ("state", &query["state"])]).unwrap();
+                warp::http::Response::builder()
+                    .status(302)
+                    .header("Location", format!("{}?{}", query["redirect_uri"], callback))
+                    .body(String::new())
+                    .unwrap()
+            })
+        };
+        let token = warp::path!("token").and(warp::body::form()).map(move |form: HashMap<String, String>| {
+            let challenge = challenges.lock().unwrap().remove(&form["code"]);
+            if challenge != Some(oidc::code_challenge(&form["code_verifier"])) {
+                return warp::reply::with_status(warp::reply::json(&"invalid_grant"), warp::http::StatusCode::BAD_REQUEST);
+            }
+            let tokens = serde_json::json!({ "access_token": "mock-access-token", "token_type": "Bearer" });
+            warp::reply::with_status(warp::reply::json(&tokens), warp::http::StatusCode::OK)
+        });
+        let userinfo = warp::path!("userinfo")
+            .and(warp::header::exact("Authorization", "Bearer mock-access-token"))
+            .map(move || warp::reply::json(&user_info));
+
+        let (addr, server) = warp::serve(discovery.or(authorize).or(token).or(userinfo))
+            .bind_ephemeral(([127, 0, 0, 1], 0));
+        issuer.set(format!("http://{}", addr)).unwrap();
+        tokio::spawn(server);
+        issuer.get().unwrap().clone()
+    }
+
+    /// Runs the whole sign-in through the provider, tampering with the state
+    /// the provider sends back if `state` is given.
+    async fn oidc_sign_in(
+        routes: &(impl Filter<Extract = (impl warp::Reply,)> + Clone + 'static),
+        state: Option<&str>,
+    ) -> warp::http::Response<warp::hyper::body::Bytes> {
+        let login = warp::test::request().path("/oidc/login").reply(routes).await;
+        assert_eq!(login.status(), 302);
+        let cookie = login.headers()["Set-Cookie"].to_str().unwrap().split(';').next().unwrap().to_string();
+        let authorize = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap()
+            .get(login.headers()["Location"].to_str().unwrap())
+            .send()
+            .await
+            .unwrap();
+        let callback = authorize.headers()["Location"].to_str().unwrap();
+        let (path, query) = callback.split_once("/oidc/callback").unwrap();
+        assert_eq!(path, "http://localhost:8080");
+        let mut query = serde_urlencoded::from_str::<Vec<(String, String)>>(&query[1..]).unwrap();
+        if let Some(state) = state {
+            query.retain(|(name, _)| name != "state");
+            query.push(("state".to_string(), state.to_string()));
+        }
+
+        warp::test::request()
+            .path(&format!("/oidc/callback?{}", serde_urlencoded::to_string(&query).unwrap()))
+            .header("Cookie", cookie)
+            .reply(routes)
+            .await
+    }
+
+    #[tokio::test]
+    async fn oidc_sign_in_with_in_memory_store() {
+        // arrange
+        let store = std::sync::Arc::new(store::InMemoryStore::new());
+        let user_info = serde_json::json!({ "sub": "mock-1", "email": "Oidc@Example.com", "email_verified": true });
+        let issuer = mock_identity_provider(user_info);
+        let args = ["--oidc-issuer", &issuer, "--oidc-client-id", "web_questions"];
+        let (routes, mail_dir) = test_routes_with(store.clone(), &args).await;
+        let (disabled, _) = test_routes_with(store.clone(), &[]).await;
+        let unverified_issuer = mock_identity_provider(
+            serde_json::json!({ "sub": "mock-2", "email": "oidc@example.com", "email_verified": false })
+        );
+        let unverified_args = ["--oidc-issuer", &unverified_issuer, "--oidc-client-id", "web_questions"];
+        let (unverified, _) = test_routes_with(store.clone(), &unverified_args).await;
+
+        // act
+        let first = oidc_sign_in(&routes, None).await;
+        let second = oidc_sign_in(&routes, None).await;
+        let tampered = oidc_sign_in(&routes, Some("forged")).await;
+        let without_cookie = warp::test::request()
+            .path("/oidc/callback?code=abc&state=def")
+            .reply(&routes)
+            .await;
+        let denied = warp::test::request()
+            .path("/oidc/callback?error=access_denied")
+            .reply(&routes)
+            .await;
+        let not_configured = warp::test::request().path("/oidc/login").reply(&disabled).await;
+        let takeover = oidc_sign_in(&unverified, None).await;
+        let first_tokens: serde_json::Value = serde_json::from_slice(first.body()).unwrap();
+        let account = warp::test::request()
+            .path("/account")
+            .header("Authorization", first_tokens["access_token"].as_str().unwrap())
+            .reply(&routes)
+            .await;
+        let account: serde_json::Value = serde_json::from_slice(account.body()).unwrap();
+
+        // assert
+        assert_eq!(first.status(), 200);
+        assert_eq!(second.status(), 200);
+        assert!(first.headers()["Set-Cookie"].to_str().unwrap().contains("Max-Age=0"));
+        assert_eq!(account["email"], "oidc@example.com");
+        assert_eq!(account["email_verified"], true);
+        assert_eq!(store.get_accounts().await.unwrap().len(), 1);
+        assert_eq!(tampered.status(), 400);
+        assert_eq!(without_cookie.status(), 400);
+        assert_eq!(denied.status(), 401);
+        assert_eq!(not_configured.status(), 404);
+        assert_eq!(takeover.status(), 409);
+        std::fs::remove_dir_all(mail_dir).ok();
+    }
+
+    #[tokio::test]
+    async fn oidc_sign_in_claims_unverified_account_with_in_memory_store() {
+        // arrange
+        let store = std::sync::Arc::new(store::InMemoryStore::new());
+        let user_info = serde_json::json!({ "sub": "mock-1", "email": "victim@example.com", "email_verified": true });
+        let issuer = mock_identity_provider(user_info);
+        let args = ["--oidc-issuer", &issuer, "--oidc-client-id", "web_questions"];
+        let (routes, mail_dir) = test_routes_with(store.clone(), &args).await;
+        warp::test::request()
+            .method("POST")
+            .path("/registration")
+            .json(&serde_json::json!({ "email": "victim@example.com", "password": TEST_PASSWORD }))
+            .reply(&routes)
+            .await;
+        let squatter = login(&routes, "victim@example.com").await;
+
+        // act
+        let sign_in = oidc_sign_in(&routes, None).await;
+        let squatter_session = warp::test::request()
+            .path("/account")
+            .header("Authorization", &squatter)
+            .reply(&routes)
+            .await;
+        let squatter_login = warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&serde_json::json!({ "email": "victim@example.com", "password": TEST_PASSWORD }))
+            .reply(&routes)
+            .await;
+        let tokens: serde_json::Value = serde_json::from_slice(sign_in.body()).unwrap();
+        let account = warp::test::request()
+            .path("/account")
+            .header("Authorization", tokens["access_token"].as_str().unwrap())
+            .reply(&routes)
+            .await;
+        let account: serde_json::Value = serde_json::from_slice(account.body()).unwrap();
+
+        // assert
+        assert_eq!(sign_in.status(), 200);
+        assert_eq!(squatter_session.status(), 401);
+        assert_eq!(squatter_login.status(), 401);
+        assert_eq!(account["email_verified"], true);
+        assert_eq!(store.get_accounts().await.unwrap().len(), 1);
+        std::fs::remove_dir_all(mail_dir).ok();
+    }
+}
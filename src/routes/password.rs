@@ -0,0 +1,227 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::mail::MailLimit;
+use crate::types::account::{AccountId, PasswordChange, PasswordForgot, PasswordReset, Session};
+
+use super::authentication::{hash_password, PasswordPolicy};
+
+const PURPOSE: &str = "reset_password";
+/// How long a reset link can be used.
+const RESET_TTL_MINUTES: i64 = 60;
+/// Reset mails an address may get, and an IP address may ask for, per hour.
+const RESET_MAILS_PER_ADDRESS: u32 = 3;
+const RESET_MAILS_PER_IP: u32 = 10;
+
+#[derive(Deserialize)]
+struct ResetClaims {
+    account_id: AccountId,
+    password_fingerprint: String,
+}
+
+pub fn reset_mail_limit() -> MailLimit {
+    MailLimit::new(RESET_MAILS_PER_ADDRESS, RESET_MAILS_PER_IP, std::time::Duration::from_secs(60 * 60))
+}
+
+/// Mails a reset link if the address belongs to an account and neither it
+/// nor the caller is over the mail limit. Answers the same either way, so
+/// it can't be used to find out who has an account.
+pub async fn forgot_password(
+    remote: Option<std::net::SocketAddr>,
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    mailer: crate::mail::DynMailSender,
+    limit: std::sync::Arc<MailLimit>,
+    public_url: String,
+    request: PasswordForgot,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let email = crate::validation::normalize_email(&request.email);
+    if !limit.allow(&email, remote.map(|addr| addr.ip()), std::time::Instant::now()) {
+        tracing::event!(tracing::Level::WARN, "password reset mail to {} held back by the mail limit", email);
+        return Ok(forgot_reply());
+    }
+
+    // looked up and mailed in the background, so neither the time taken nor
+    // a failing mailer tells whether the address has an account
+    tokio::spawn(async move {
+        let Ok(account) = store.get_account(email).await else {
+            return;
+        };
+        let token = super::authentication::issue_purpose_token(
+            &keyring,
+            PURPOSE,
+            &[
+                ("account_id", serde_json::json!(account.id)),
+                ("password_fingerprint", serde_json::json!(password_fingerprint(&account.password))),
+            ],
+            chrono::Duration::minutes(RESET_TTL_MINUTES),
+        );
+        let link = format!("{}/password/reset?token={}", public_url, token);
+        if let Err(e) = mailer.send(crate::mail::password_reset_email(&account.email, &link)).await {
+            tracing::event!(tracing::Level::ERROR, "password reset mail to {} failed: {:?}", account.email, e);
+        }
+    });
+
+    Ok(forgot_reply())
+}
+
+fn forgot_reply() -> impl warp::Reply {
+    warp::reply::with_status(
+        "If the address belongs to an account, a reset link was sent",
+        warp::http::StatusCode::OK,
+    )
+}
+
+/// Sets a new password and logs the account out everywhere. The token
+/// carries a fingerprint of the old password, so it works only once.
+pub async fn reset_password(
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    policy: PasswordPolicy,
+    rules: crate::validation::PasswordRules,
+    request: PasswordReset,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let invalid_token = || warp::reject::custom(handle_errors::Error::InvalidParameter("token".to_string()));
+    let claims = super::authentication::verify_purpose_token::<ResetClaims>(&keyring, &request.token, PURPOSE)
+        .map_err(|_| invalid_token())?;
+    let account = store.get_account_by_id(&claims.account_id).await.map_err(|_| invalid_token())?;
+    if password_fingerprint(&account.password) != claims.password_fingerprint {
+        return Err(invalid_token());
+    }
+    crate::validation::validate(rules.check("password", &request.password, &account.email))
+        .map_err(warp::reject::custom)?;
+
+    if let Err(e) = store.update_password(&claims.account_id, &hash_password(&policy, request.password.as_bytes())).await {
+        return Err(warp::reject::custom(e));
+    }
+    if let Err(e) = store.revoke_sessions(&claims.account_id, chrono::Utc::now().naive_utc()).await {
+        return Err(warp::reject::custom(e));
+    }
+
+    Ok(warp::reply::with_status("Password reset", warp::http::StatusCode::OK))
+}
+
+pub async fn change_password(
+    session: Session,
+    remote: Option<std::net::SocketAddr>,
+    store: crate::store::DynStore,
+    throttle: std::sync::Arc<crate::throttle::LoginThrottle>,
+    policy: PasswordPolicy,
+    rules: crate::validation::PasswordRules,
+    request: PasswordChange,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account = match store.get_account_by_id(&session.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    super::authentication::confirm_password(&throttle, remote, &account, &request.old_password)?;
+    crate::validation::validate(rules.check("new_password", &request.new_password, &account.email))
+        .map_err(warp::reject::custom)?;
+
+    match store.update_password(&session.account_id, &hash_password(&policy, request.new_password.as_bytes())).await {
+        Ok(_) => Ok(warp::reply::with_status("Password changed", warp::http::StatusCode::OK)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Short digest of the stored hash; changes whenever the password does.
+fn password_fingerprint(password_hash: &str) -> String {
+    hex::encode(&Sha256::digest(password_hash.as_bytes())[..8])
+}
+
+
+#[cfg(test)]
+mod password_tests {
+    use crate::store;
+    use crate::routes::test_support::{mailed_token, mails_to, register_and_login, test_routes, test_routes_with, wait_for_mail, TEST_PASSWORD};
+
+    #[tokio::test]
+    async fn password_reset_and_change_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes_with(
+            std::sync::Arc::new(store::InMemoryStore::new()),
+            &["--login-backoff-ms", "0"],
+        ).await;
+        let access_token = register_and_login(&routes, &mail_dir, "test@example.com").await;
+        let login_with = |password: &str| warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&serde_json::json!({ "email": "test@example.com", "password": password }));
+        let change = |old: &str, new: &str| warp::test::request()
+            .method("PUT")
+            .path("/account/password")
+            .header("Authorization", &access_token)
+            .json(&serde_json::json!({ "old_password": old, "new_password": new }));
+
+        // act
+        let wrong_old = change("wrong", "Changed Macintosh 42").reply(&routes).await;
+        let changed = change(TEST_PASSWORD, "Changed Macintosh 42").reply(&routes).await;
+        let unknown = warp::test::request()
+            .method("POST")
+            .path("/password/forgot")
+            .json(&serde_json::json!({ "email": "nobody@example.com" }))
+            .reply(&routes)
+            .await;
+        let forgot = warp::test::request()
+            .method("POST")
+            .path("/password/forgot")
+            .json(&serde_json::json!({ "email": "test@example.com" }))
+            .reply(&routes)
+            .await;
+        let reset = |token: &str| warp::test::request()
+            .method("POST")
+            .path("/password/reset")
+            .json(&serde_json::json!({ "token": token, "password": "Reset Macintosh 42" }));
+        wait_for_mail(&mail_dir, "test@example.com", 2).await;
+        let token = mailed_token(&mail_dir, "test@example.com");
+        let with_access_token = reset(&access_token).reply(&routes).await;
+        let was_reset = reset(&token).reply(&routes).await;
+        let reused = reset(&token).reply(&routes).await;
+        let revoked = change("Reset Macintosh 42", "Again Macintosh 42").reply(&routes).await;
+
+        // assert
+        assert_eq!(wrong_old.status(), 401);
+        assert_eq!(changed.status(), 200);
+        assert_eq!(unknown.status(), 200);
+        assert_eq!(forgot.status(), 200);
+        assert_eq!(unknown.body(), forgot.body());
+        assert_eq!(with_access_token.status(), 400);
+        assert_eq!(was_reset.status(), 200);
+        assert_eq!(reused.status(), 400);
+        assert_eq!(revoked.status(), 401);
+        assert_eq!(login_with("Changed Macintosh 42").reply(&routes).await.status(), 401);
+        assert_eq!(login_with("Reset Macintosh 42").reply(&routes).await.status(), 200);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reset_mails_are_limited_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        register_and_login(&routes, &mail_dir, "test@example.com").await;
+        let forgot = |email: &str, ip: [u8; 4]| warp::test::request()
+            .method("POST")
+            .path("/password/forgot")
+            .remote_addr(std::net::SocketAddr::from((ip, 4000)))
+            .json(&serde_json::json!({ "email": email }));
+
+        // act
+        let mut statuses = Vec::new();
+        for i in 1..=4 {
+            statuses.push(forgot("Test@example.com", [10, 0, 0, i]).reply(&routes).await.status());
+        }
+        for i in 1..=10 {
+            forgot(&format!("nobody{}@example.com", i), [10, 0, 1, 1]).reply(&routes).await;
+        }
+        let over_ip = forgot("test@example.com", [10, 0, 1, 1]).reply(&routes).await;
+        wait_for_mail(&mail_dir, "test@example.com", 4).await;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        // assert
+        assert!(statuses.iter().all(|status| *status == 200));
+        assert_eq!(over_ip.status(), 200);
+        // the verification mail and three reset mails
+        assert_eq!(mails_to(&mail_dir, "test@example.com").len(), 4);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+}
@@ -1,26 +1,95 @@
+use warp::Reply;
+
+/// Parameters a search takes; its results are ranked, so the filters,
+/// sorting and cursor of the listing don't apply.
+const SEARCH_PARAMETERS: [&str; 3] = ["q", "limit", "offset"];
+
 pub async fn get_questions(
-    params: std::collections::HashMap<String, String>,
-    store: crate::store::Store,
+    path: warp::path::FullPath,
+    query: Vec<(String, String)>,
+    store: crate::store::DynStore,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut limit = None;
-    let mut offset = 0;
-    if !params.is_empty() {
-        let pagination = crate::types::pagination::get_pagination(params);
-        limit = pagination.get_limit();
-        offset = pagination.get_offset();
+    let params: std::collections::HashMap<String, String> = query.iter().cloned().collect();
+    let pagination = crate::types::pagination::Pagination::new(&params);
+    let limit = pagination.get_limit();
+    let offset = pagination.get_offset();
+
+    if let Some(search) = params.get("q").filter(|q| !q.trim().is_empty()) {
+        if let Some((name, _)) = query.iter().find(|(name, _)| !SEARCH_PARAMETERS.contains(&name.as_str())) {
+            return Err(warp::reject::custom(handle_errors::Error::InvalidParameter(name.clone())));
+        }
+        let res = match store.search_questions(search, limit, offset).await {
+            Ok(res) => res,
+            Err(e) => return Err(warp::reject::custom(e)),
+        };
+
+        return Ok(warp::reply::json(&res).into_response());
+    }
+
+    if pagination.is_keyset() {
+        return get_questions_page(path, query, pagination, store).await;
     }
 
-    let res: Vec<crate::types::question::Question> = match store.get_questions(limit, offset).await {
+    let filter = crate::types::question::QuestionFilter::new(&query)?;
+    let sort = crate::types::question::QuestionSort::new(&query)?;
+    let res: Vec<crate::types::question::Question> = match store.get_questions(&filter, &sort, limit, offset).await {
         Ok(res) => res,
         Err(e) => return Err(warp::reject::custom(e)),
     };
 
-    Ok(warp::reply::json(&res))
+    Ok(warp::reply::json(&res).into_response())
+}
+
+/// Keyset mode of `get_questions`: a page envelope plus `Link` headers.
+async fn get_questions_page(
+    path: warp::path::FullPath,
+    query: Vec<(String, String)>,
+    pagination: crate::types::pagination::Pagination,
+    store: crate::store::DynStore,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    use crate::types::pagination::{CursorPage, Direction};
+    use crate::types::question::{SortField, SortOrder};
+
+    let filter = crate::types::question::QuestionFilter::new(&query)?;
+    let sort = crate::types::question::QuestionSort::new(&query)?;
+    if sort.field != SortField::Id && sort.field != SortField::CreatedOn {
+        return Err(warp::reject::custom(handle_errors::Error::InvalidParameter("sort".to_string())));
+    }
+    let cursor = pagination.get_cursor()?;
+    let limit = pagination.get_limit();
+
+    let backwards = cursor.as_ref().is_some_and(|c| c.direction == Direction::Prev);
+    let scan = match (sort.order, backwards) {
+        (SortOrder::Asc, false) | (SortOrder::Desc, true) => SortOrder::Asc,
+        _ => SortOrder::Desc,
+    };
+
+    let rows = match store.get_questions_by_key(&filter, scan, cursor.as_ref(), limit.map(|l| l.saturating_add(1))).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let total = match store.count_questions(&filter).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    let page = CursorPage::new(rows, limit, cursor.as_ref(), total, |q: &crate::types::question::Question| {
+        q.created_on.map(|created_on| (created_on, q.id.0))
+    });
+
+    let mut response = warp::reply::json(&page).into_response();
+    if let Some(links) = page.link_header(path.as_str(), &query) {
+        if let Ok(links) = warp::http::HeaderValue::from_str(&links) {
+            response.headers_mut().insert(warp::http::header::LINK, links);
+        }
+    }
+
+    Ok(response)
 }
 
 pub async fn get_question(
     id: i32,
-    store: crate::store::Store,
+    store: crate::store::DynStore,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let res = match store.get_question(id).await {
         Ok(res) => res,
@@ -32,12 +101,13 @@ pub async fn get_question(
 
 pub async fn add_question(
     session: crate::types::account::Session,
-    store: crate::store::Store,
+    store: crate::store::DynStore,
+    profanity: crate::profanity::DynProfanityFilter,
     new_question: crate::types::question::NewQuestion,
 ) -> Result<impl warp::Reply, warp::Rejection> {
      let (title, content) = tokio::join!(
-         crate::profanity::check_profanity(new_question.title),
-         crate::profanity::check_profanity(new_question.content),
+         crate::profanity::check_profanity(profanity.as_ref(), new_question.title),
+         crate::profanity::check_profanity(profanity.as_ref(), new_question.content),
      );
      let (title, content) = (
          match title {
@@ -67,15 +137,20 @@ pub async fn add_question(
 pub async fn update_question(
     id: i32,
     session: crate::types::account::Session,
-    store: crate::store::Store,
+    store: crate::store::DynStore,
+    profanity: crate::profanity::DynProfanityFilter,
     question: crate::types::question::Question,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    if store.is_question_owner(id, &session.account_id).await? {
-        let title = match crate::profanity::check_profanity(question.title).await {
+    let owner = match store.get_question_owner(id).await? {
+        Some(owner) => owner,
+        None => return Err(warp::reject::custom(handle_errors::Error::QuestionNotFound)),
+    };
+    if session.may_modify(&owner) {
+        let title = match crate::profanity::check_profanity(profanity.as_ref(), question.title).await {
             Ok(res) => res,
             Err(e) => return Err(warp::reject::custom(e)),
         };
-        let content = match crate::profanity::check_profanity(question.content).await {
+        let content = match crate::profanity::check_profanity(profanity.as_ref(), question.content).await {
             Ok(res) => res,
             Err(e) => return Err(warp::reject::custom(e)),
         };
@@ -86,30 +161,239 @@ pub async fn update_question(
                 title,
                 content,
                 tags: question.tags,
+                created_on: question.created_on,
+                score: 0,
+                accepted_answer_id: None,
+                author: None,
             },
-            &session.account_id,
+            &owner,
         ).await {
             return Err(warp::reject::custom(e))
         };
 
         Ok(warp::reply::with_status("Question updated", warp::hyper::StatusCode::OK))
     } else {
-        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+        Err(warp::reject::custom(handle_errors::Error::Forbidden))
     }
 }
 
 pub async fn delete_question(
     id: i32,
     session: crate::types::account::Session,
-    store: crate::store::Store,
+    store: crate::store::DynStore,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    if store.is_question_owner(id, &session.account_id).await? {
-        if let Err(e) = store.delete_question(id, &session.account_id).await {
+    let owner = match store.get_question_owner(id).await? {
+        Some(owner) => owner,
+        None => return Err(warp::reject::custom(handle_errors::Error::QuestionNotFound)),
+    };
+    if session.may_modify(&owner) {
+        if let Err(e) = store.delete_question(id, &owner).await {
             return Err(warp::reject::custom(e))
         };
 
         Ok(warp::reply::with_status("Question deleted", warp::hyper::StatusCode::OK))
     } else {
-        Err(warp::reject::custom(handle_errors::Error::Unauthorized))
+        Err(warp::reject::custom(handle_errors::Error::Forbidden))
+    }
+}
+
+/// Marks the answer as the one that solved the question, replacing an
+/// earlier choice. Only the asker may do this.
+pub async fn accept_answer(
+    id: i32,
+    answer_id: i32,
+    session: crate::types::account::Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if store.get_question_owner(id).await?.is_none() {
+        return Err(warp::reject::custom(handle_errors::Error::QuestionNotFound));
+    }
+    if !store.is_question_owner(id, &session.account_id).await? {
+        return Err(warp::reject::custom(handle_errors::Error::Forbidden));
+    }
+
+    match store.accept_answer(id, answer_id).await {
+        Ok(true) => Ok(warp::reply::with_status("Answer accepted", warp::hyper::StatusCode::OK)),
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::AnswerNotFound)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Takes the acceptance of the answer back. Only the asker may do this.
+pub async fn unaccept_answer(
+    id: i32,
+    answer_id: i32,
+    session: crate::types::account::Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if store.get_question_owner(id).await?.is_none() {
+        return Err(warp::reject::custom(handle_errors::Error::QuestionNotFound));
+    }
+    if !store.is_question_owner(id, &session.account_id).await? {
+        return Err(warp::reject::custom(handle_errors::Error::Forbidden));
+    }
+
+    match store.unaccept_answer(id, answer_id).await {
+        Ok(true) => Ok(warp::reply::with_status("Answer no longer accepted", warp::hyper::StatusCode::OK)),
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::AnswerNotFound)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+
+#[cfg(test)]
+mod question_tests {
+    use crate::{store, types};
+    use crate::routes::test_support::{register_and_login, test_routes};
+
+    #[tokio::test]
+    async fn questions_from_in_memory_store() {
+        // arrange
+        let (routes, _) = test_routes(std::sync::Arc::new(store::InMemoryStore::seeded())).await;
+
+        // act
+        let list = warp::test::request()
+            .method("GET")
+            .path("/questions?limit=10&offset=0")
+            .reply(&routes)
+            .await;
+        let single = warp::test::request()
+            .method("GET")
+            .path("/questions/1")
+            .reply(&routes)
+            .await;
+        let answers = warp::test::request()
+            .method("GET")
+            .path("/questions/2/answers")
+            .reply(&routes)
+            .await;
+        let search = warp::test::request()
+            .method("GET")
+            .path("/questions?q=question&limit=10")
+            .reply(&routes)
+            .await;
+        let search_with_cursor = warp::test::request()
+            .method("GET")
+            .path("/questions?q=question&cursor=")
+            .reply(&routes)
+            .await;
+
+        // assert
+        assert_eq!(list.status(), 200);
+        let questions: Vec<types::question::Question> = serde_json::from_slice(list.body()).unwrap();
+        assert_eq!(questions.len(), 1);
+        assert_eq!(single.status(), 200);
+        let question: types::question::Question = serde_json::from_slice(single.body()).unwrap();
+        assert_eq!(question.title, "Question #1");
+        assert_eq!(answers.status(), 404);
+        assert_eq!(search.status(), 200);
+        let results: Vec<types::search::QuestionSearchResult> = serde_json::from_slice(search.body()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(search_with_cursor.status(), 400);
+    }
+
+    #[tokio::test]
+    async fn keyset_pages_from_in_memory_store() {
+        // arrange
+        let store = store::InMemoryStore::new();
+        for title in ["1", "2", "3"] {
+            store::QuestionRepository::add_question(&store, types::question::NewQuestion {
+                title: title.to_string(),
+                content: "content".to_string(),
+                tags: None,
+            }, &types::account::AccountId(1)).await.unwrap();
+        }
+        let (routes, _) = test_routes(std::sync::Arc::new(store)).await;
+        let get = |path: String| warp::test::request().method("GET").path(&path).reply(&routes);
+
+        // act
+        let first = get("/questions?cursor=&limit=2".to_string()).await;
+        let first_page: types::pagination::CursorPage<types::question::Question> =
+            serde_json::from_slice(first.body()).unwrap();
+        let second = get(format!("/questions?limit=2&cursor={}", first_page.next_cursor.clone().unwrap())).await;
+        let second_page: types::pagination::CursorPage<types::question::Question> =
+            serde_json::from_slice(second.body()).unwrap();
+        let invalid = get("/questions?cursor=garbage".to_string()).await;
+        let huge = get("/questions?limit=2147483647&sort=created_on&cursor=".to_string()).await;
+
+        // assert
+        assert_eq!(first.status(), 200);
+        assert_eq!(first_page.total, 3);
+        assert_eq!(first_page.items.iter().map(|q| q.title.as_str()).collect::<Vec<_>>(), vec!["1", "2"]);
+        assert_eq!(
+            first.headers()["link"],
+            format!("</questions?limit=2&cursor={}>; rel=\"next\"", first_page.next_cursor.unwrap()).as_str()
+        );
+        assert_eq!(second_page.items.iter().map(|q| q.title.as_str()).collect::<Vec<_>>(), vec!["3"]);
+        assert!(second_page.next_cursor.is_none());
+        assert!(second.headers()["link"].to_str().unwrap().ends_with("rel=\"prev\""));
+        assert_eq!(invalid.status(), 400);
+        assert_eq!(huge.status(), 200);
+        let huge_page: types::pagination::CursorPage<types::question::Question> =
+            serde_json::from_slice(huge.body()).unwrap();
+        assert_eq!(huge_page.items.len(), 3);
+        assert!(huge_page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn accepted_answers_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let owner = register_and_login(&routes, &mail_dir, "owner@example.com").await;
+        let other = register_and_login(&routes, &mail_dir, "other@example.com").await;
+        for title in ["Solved", "Open"] {
+            warp::test::request()
+                .method("POST")
+                .path("/questions")
+                .header("Authorization", &owner)
+                .json(&serde_json::json!({ "title": title, "content": "Content" }))
+                .reply(&routes)
+                .await;
+        }
+        for content in ["First", "Second"] {
+            warp::test::request()
+                .method("POST")
+                .path("/answers")
+                .header("Authorization", &other)
+                .body(format!("content={}&question_id=1", content))
+                .reply(&routes)
+                .await;
+        }
+        let accept = |method: &str, token: &str, path: &str| warp::test::request()
+            .method(method)
+            .path(path)
+            .header("Authorization", token);
+        let get = |path: &str| warp::test::request().method("GET").path(path);
+
+        // act
+        let by_answerer = accept("POST", &other, "/questions/1/accept/2").reply(&routes).await;
+        let not_an_answer = accept("POST", &owner, "/questions/2/accept/2").reply(&routes).await;
+        let unknown_question = accept("POST", &owner, "/questions/9/accept/2").reply(&routes).await;
+        let accepted = accept("POST", &owner, "/questions/1/accept/2").reply(&routes).await;
+        let question = get("/questions/1").reply(&routes).await;
+        let answers = get("/questions/1/answers").reply(&routes).await;
+        let answered = get("/questions?answered=true").reply(&routes).await;
+        let unanswered = get("/questions?answered=false").reply(&routes).await;
+        let invalid_filter = get("/questions?answered=maybe").reply(&routes).await;
+        let unaccepted = accept("DELETE", &owner, "/questions/1/accept/2").reply(&routes).await;
+        let unaccepted_again = accept("DELETE", &owner, "/questions/1/accept/2").reply(&routes).await;
+
+        // assert
+        assert_eq!(by_answerer.status(), 403);
+        assert_eq!(not_an_answer.status(), 404);
+        assert_eq!(unknown_question.status(), 404);
+        assert_eq!(accepted.status(), 200);
+        let question: serde_json::Value = serde_json::from_slice(question.body()).unwrap();
+        assert_eq!(question["accepted_answer_id"], 2);
+        let answers: Vec<types::answer::Answer> = serde_json::from_slice(answers.body()).unwrap();
+        assert_eq!(answers.iter().map(|a| a.content.as_str()).collect::<Vec<_>>(), vec!["Second", "First"]);
+        let answered: Vec<serde_json::Value> = serde_json::from_slice(answered.body()).unwrap();
+        assert_eq!(answered.iter().map(|q| q["id"].as_i64().unwrap()).collect::<Vec<_>>(), vec![1]);
+        let unanswered: Vec<serde_json::Value> = serde_json::from_slice(unanswered.body()).unwrap();
+        assert_eq!(unanswered.iter().map(|q| q["id"].as_i64().unwrap()).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(invalid_filter.status(), 400);
+        assert_eq!(unaccepted.status(), 200);
+        assert_eq!(unaccepted_again.status(), 404);
+        std::fs::remove_dir_all(mail_dir).unwrap();
     }
 }
@@ -0,0 +1,84 @@
+pub async fn get_tags(
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let res: Vec<crate::types::tag::Tag> = match store.get_tags().await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
+
+pub async fn get_tag_questions(
+    name: String,
+    params: std::collections::HashMap<String, String>,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut limit = None;
+    let mut offset = 0;
+    if !params.is_empty() {
+        let pagination = crate::types::pagination::get_pagination(params);
+        limit = pagination.get_limit();
+        offset = pagination.get_offset();
+    }
+
+    let name = match percent_encoding::percent_decode_str(&name).decode_utf8() {
+        Ok(name) => name.into_owned(),
+        Err(_) => return Err(warp::reject::custom(handle_errors::Error::MissingParameters)),
+    };
+    let filter = crate::types::question::QuestionFilter::with_tag(name);
+
+    let sort = crate::types::question::QuestionSort::default();
+
+    let res: Vec<crate::types::question::Question> = match store.get_questions(&filter, &sort, limit, offset).await {
+        Ok(res) => res,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    Ok(warp::reply::json(&res))
+}
+
+
+#[cfg(test)]
+mod tag_tests {
+    use crate::{store, types};
+    use crate::routes::test_support::test_routes;
+
+    #[tokio::test]
+    async fn tags_from_in_memory_store() {
+        // arrange
+        let (routes, _) = test_routes(std::sync::Arc::new(store::InMemoryStore::seeded())).await;
+
+        // act
+        let tags = warp::test::request()
+            .method("GET")
+            .path("/tags")
+            .reply(&routes)
+            .await;
+        let by_tag = warp::test::request()
+            .method("GET")
+            .path("/tags/general/questions")
+            .reply(&routes)
+            .await;
+        let by_query = warp::test::request()
+            .method("GET")
+            .path("/questions?tag=rust&tag=general&tag_match=any")
+            .reply(&routes)
+            .await;
+        let by_query_all = warp::test::request()
+            .method("GET")
+            .path("/questions?tag=rust&tag=general&tag_match=all")
+            .reply(&routes)
+            .await;
+
+        // assert
+        let tags: Vec<types::tag::Tag> = serde_json::from_slice(tags.body()).unwrap();
+        assert_eq!(tags, vec![types::tag::Tag { name: "general".to_string(), question_count: 1 }]);
+        let by_tag: Vec<types::question::Question> = serde_json::from_slice(by_tag.body()).unwrap();
+        assert_eq!(by_tag.len(), 1);
+        let by_query: Vec<types::question::Question> = serde_json::from_slice(by_query.body()).unwrap();
+        assert_eq!(by_query.len(), 1);
+        let by_query_all: Vec<types::question::Question> = serde_json::from_slice(by_query_all.body()).unwrap();
+        assert!(by_query_all.is_empty());
+    }
+}
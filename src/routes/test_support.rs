@@ -0,0 +1,121 @@
+//! Helpers shared by the route tests, building the routes around an
+//! in-memory store.
+
+use clap::Parser;
+use warp::Filter;
+
+use crate::{build_routes, config, keyring, mail, profanity, store, types};
+
+const TEST_PASETO_KEY: &[u8] = b"RANDOM WORDS WINTER MACINTOSH PC";
+const TEST_PASETO_SIGNING_SEED: &[u8] = b"SIGNING SEED FOR THE ROUTE TESTS";
+pub(crate) const TEST_PASSWORD: &str = "Winter Macintosh 42";
+
+/// Routes without profanity filter, writing mails to a fresh directory.
+pub(crate) async fn test_routes(
+    store: store::DynStore,
+) -> (impl Filter<Extract = (impl warp::Reply,)> + Clone + 'static, std::path::PathBuf) {
+    test_routes_with(store, &[]).await
+}
+
+/// Like `test_routes`, with additional command line options.
+pub(crate) async fn test_routes_with(
+    store: store::DynStore,
+    args: &[&str],
+) -> (impl Filter<Extract = (impl warp::Reply,)> + Clone + 'static, std::path::PathBuf) {
+    let mail_dir = std::env::temp_dir().join(format!("web_questions_mail_{}", uuid::Uuid::new_v4()));
+    // cheap hashing keeps the tests fast, unless a test asks otherwise
+    let defaults = [["--argon2-memory-kib", "1024"], ["--argon2-iterations", "1"]]
+        .into_iter()
+        .filter(|[name, _]| !args.contains(name))
+        .flatten();
+    let config = config::Config::parse_from(
+        ["web_questions", "--mail-dir", mail_dir.to_str().unwrap()]
+            .into_iter()
+            .chain(defaults)
+            .chain(args.iter().copied())
+    );
+    let keyring = keyring::Keyring::new(
+        vec![(keyring::DEFAULT_KEY_ID.to_string(), TEST_PASETO_KEY.to_vec())],
+        vec![("test".to_string(), TEST_PASETO_SIGNING_SEED.to_vec())],
+        config.access_token_format,
+    ).unwrap();
+    let routes = build_routes(
+        &config,
+        store,
+        std::sync::Arc::new(profanity::NoopFilter),
+        std::sync::Arc::new(mail::FileMailSender::new(&mail_dir)),
+        std::sync::Arc::new(keyring),
+    ).await;
+
+    (routes, mail_dir)
+}
+
+/// Mails sent to `email`, oldest first.
+/// Mails sent to `email` so far, oldest first.
+pub(crate) fn mails_to(mail_dir: &std::path::Path, email: &str) -> Vec<String> {
+    let Ok(files) = std::fs::read_dir(mail_dir) else {
+        return Vec::new();
+    };
+    let mut mails = files
+        .map(|f| f.unwrap().path())
+        .collect::<Vec<_>>();
+    mails.sort();
+    mails
+        .into_iter()
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .filter(|mail| mail.starts_with(&format!("To: {}\n", email)))
+        .collect()
+}
+
+/// Waits for mails sent in the background until `email` got `count` of them.
+pub(crate) async fn wait_for_mail(mail_dir: &std::path::Path, email: &str, count: usize) {
+    for _ in 0..100 {
+        if mails_to(mail_dir, email).len() >= count {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("{} got fewer than {} mails", email, count);
+}
+
+/// Token of the newest link mailed to `email`.
+pub(crate) fn mailed_token(mail_dir: &std::path::Path, email: &str) -> String {
+    let mail = mails_to(mail_dir, email).pop().expect("no mail sent");
+    let token = mail.split("token=").nth(1).expect("no link in mail");
+    token.split_whitespace().next().unwrap().to_string()
+}
+
+/// Registers `email`, verifies it through the mailed link and logs in.
+pub(crate) async fn register_and_login(
+    routes: &(impl Filter<Extract = (impl warp::Reply,)> + Clone + 'static),
+    mail_dir: &std::path::Path,
+    email: &str,
+) -> String {
+    let account = serde_json::json!({ "email": email, "password": TEST_PASSWORD });
+    warp::test::request()
+        .method("POST")
+        .path("/registration")
+        .json(&account)
+        .reply(routes)
+        .await;
+    warp::test::request()
+        .method("GET")
+        .path(&format!("/verify?token={}", mailed_token(mail_dir, email)))
+        .reply(routes)
+        .await;
+    login(routes, email).await
+}
+
+pub(crate) async fn login(
+    routes: &(impl Filter<Extract = (impl warp::Reply,)> + Clone + 'static),
+    email: &str,
+) -> String {
+    let login = warp::test::request()
+        .method("POST")
+        .path("/login")
+        .json(&serde_json::json!({ "email": email, "password": TEST_PASSWORD }))
+        .reply(routes)
+        .await;
+    let tokens: types::account::TokenPair = serde_json::from_slice(login.body()).unwrap();
+    tokens.access_token
+}
@@ -0,0 +1,339 @@
+use rand::Rng;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::types::account::{Account, AccountId, Session};
+use crate::types::two_factor::{
+    RecoveryCodes, TwoFactor, TwoFactorChallenge, TwoFactorCode, TwoFactorDisable, TwoFactorLogin, TwoFactorSetup,
+};
+
+const PURPOSE: &str = "two_factor";
+/// How long the second login step can be taken after the password was verified.
+const CHALLENGE_TTL_MINUTES: i64 = 5;
+/// Name authenticator apps list the secret under.
+const ISSUER: &str = "Web Questions";
+const RECOVERY_CODE_COUNT: usize = 10;
+
+#[derive(Deserialize)]
+struct ChallengeClaims {
+    account_id: AccountId,
+}
+
+/// Creates a new pending TOTP secret for the caller, replacing an earlier
+/// pending one. Logins only ask for codes once the secret is confirmed.
+pub async fn setup(
+    session: Session,
+    store: crate::store::DynStore,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account = match store.get_account_by_id(&session.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let secret = crate::totp::generate_secret();
+    match store.set_two_factor_secret(&session.account_id, &secret).await {
+        Ok(true) => Ok(warp::reply::json(&TwoFactorSetup {
+            otpauth_uri: crate::totp::otpauth_uri(ISSUER, &account.email, &secret),
+            secret,
+        })),
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::TwoFactorEnabled)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Enables the pending secret once a code of it checks out, replying with
+/// fresh recovery codes. They are shown only this once.
+pub async fn confirm(
+    session: Session,
+    store: crate::store::DynStore,
+    request: TwoFactorCode,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let two_factor = match store.get_two_factor(&session.account_id).await {
+        Ok(Some(two_factor)) if two_factor.enabled => {
+            return Err(warp::reject::custom(handle_errors::Error::TwoFactorEnabled));
+        },
+        Ok(Some(two_factor)) => two_factor,
+        Ok(None) => return Err(warp::reject::custom(handle_errors::Error::TwoFactorNotEnabled)),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    let step = crate::totp::verify(&two_factor.secret, &request.code, chrono::Utc::now().timestamp())
+        .ok_or_else(|| warp::reject::custom(handle_errors::Error::InvalidParameter("code".to_string())))?;
+
+    let recovery_codes = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect::<Vec<_>>();
+    let hashes = recovery_codes.iter().map(|code| hash_recovery_code(code)).collect::<Vec<_>>();
+    match store.enable_two_factor(&session.account_id, step, &hashes).await {
+        Ok(true) => {
+            tracing::event!(target: "audit", tracing::Level::INFO, account_id = session.account_id.0, "two-factor authentication enabled");
+            Ok(warp::reply::json(&RecoveryCodes { recovery_codes }))
+        },
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::TwoFactorEnabled)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Turns two-factor authentication off again, after checking the password
+/// or a code.
+pub async fn disable(
+    session: Session,
+    remote: Option<std::net::SocketAddr>,
+    store: crate::store::DynStore,
+    throttle: std::sync::Arc<crate::throttle::LoginThrottle>,
+    request: TwoFactorDisable,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account = match store.get_account_by_id(&session.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    confirm_password_or_code(&store, &throttle, remote, &account, request.password.as_deref(), request.code.as_deref()).await?;
+
+    match store.disable_two_factor(&session.account_id).await {
+        Ok(true) => {
+            tracing::event!(target: "audit", tracing::Level::INFO, account_id = session.account_id.0, "two-factor authentication disabled");
+            Ok(warp::reply::with_status("Two-factor authentication disabled", warp::http::StatusCode::OK))
+        },
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::TwoFactorNotEnabled)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Reply to a successful first login step: a session, or a challenge for
+/// the second step if the account has two factors enabled.
+pub(crate) async fn session_or_challenge(
+    store: &crate::store::DynStore,
+    keyring: &crate::keyring::Keyring,
+    account: Account,
+) -> Result<warp::reply::Json, warp::Rejection> {
+    let account_id = account.id.expect("id not found");
+    let enabled = match store.get_two_factor(&account_id).await {
+        Ok(two_factor) => two_factor.is_some_and(|t| t.enabled),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    if !enabled {
+        let tokens = super::authentication::start_session(store, keyring, account_id, account.role).await?;
+        return Ok(warp::reply::json(&tokens));
+    }
+
+    let two_factor_token = super::authentication::issue_purpose_token(
+        keyring,
+        PURPOSE,
+        &[("account_id", serde_json::json!(account_id))],
+        chrono::Duration::minutes(CHALLENGE_TTL_MINUTES),
+    );
+    Ok(warp::reply::json(&TwoFactorChallenge {
+        two_factor_required: true,
+        two_factor_token,
+        expires_in: CHALLENGE_TTL_MINUTES * 60,
+    }))
+}
+
+/// Second login step, exchanging the challenge token and a TOTP or
+/// recovery code for a session. Every code works once; failures count
+/// towards the same throttle as wrong passwords.
+pub async fn login(
+    remote: Option<std::net::SocketAddr>,
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    throttle: std::sync::Arc<crate::throttle::LoginThrottle>,
+    request: TwoFactorLogin,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let claims = super::authentication::verify_purpose_token::<ChallengeClaims>(&keyring, &request.two_factor_token, PURPOSE)
+        .map_err(warp::reject::custom)?;
+    let account = match store.get_account_by_id(&claims.account_id).await {
+        Ok(account) => account,
+        Err(_) => return Err(warp::reject::custom(handle_errors::Error::TokenError)),
+    };
+    let ip = remote.map(|addr| addr.ip());
+    super::authentication::check_throttle(&throttle, &account.email, ip)?;
+    let two_factor = match store.get_two_factor(&claims.account_id).await {
+        Ok(Some(two_factor)) if two_factor.enabled => two_factor,
+        Ok(_) => return Err(warp::reject::custom(handle_errors::Error::TokenError)),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    match use_code(&store, &claims.account_id, &two_factor, &request.code).await {
+        Ok(true) => {
+            throttle.record_success(&account.email);
+            let tokens = super::authentication::start_session(&store, &keyring, claims.account_id, account.role).await?;
+            Ok(warp::reply::json(&tokens))
+        },
+        Ok(false) => {
+            throttle.record_failure(&account.email, ip, std::time::Instant::now());
+            Err(warp::reject::custom(handle_errors::Error::WrongPassword))
+        },
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Confirms a sensitive request with the password or, instead, a TOTP or
+/// recovery code. Accounts created through OpenID Connect have no password
+/// their owner knows; without two factors they set one through
+/// `POST /password/forgot` first. Wrong codes count towards the login
+/// throttle like wrong passwords.
+pub(crate) async fn confirm_password_or_code(
+    store: &crate::store::DynStore,
+    throttle: &crate::throttle::LoginThrottle,
+    remote: Option<std::net::SocketAddr>,
+    account: &Account,
+    password: Option<&str>,
+    code: Option<&str>,
+) -> Result<(), warp::Rejection> {
+    let code = match (password, code) {
+        (Some(password), _) => return super::authentication::confirm_password(throttle, remote, account, password),
+        (None, Some(code)) => code,
+        (None, None) => return Err(warp::reject::custom(handle_errors::Error::MissingParameters)),
+    };
+    let ip = remote.map(|addr| addr.ip());
+    super::authentication::check_throttle(throttle, &account.email, ip)?;
+    let account_id = account.id.clone().expect("id not found");
+    let two_factor = match store.get_two_factor(&account_id).await {
+        Ok(Some(two_factor)) if two_factor.enabled => two_factor,
+        Ok(_) => return Err(warp::reject::custom(handle_errors::Error::TwoFactorNotEnabled)),
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+
+    match use_code(store, &account_id, &two_factor, code).await {
+        Ok(true) => {
+            throttle.record_success(&account.email);
+            Ok(())
+        },
+        Ok(false) => {
+            throttle.record_failure(&account.email, ip, std::time::Instant::now());
+            Err(warp::reject::custom(handle_errors::Error::WrongPassword))
+        },
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Uses up a TOTP or recovery code. `false` if it is wrong or was used
+/// before.
+async fn use_code(
+    store: &crate::store::DynStore,
+    account_id: &AccountId,
+    two_factor: &TwoFactor,
+    code: &str,
+) -> Result<bool, handle_errors::Error> {
+    let now = chrono::Utc::now();
+    match crate::totp::verify(&two_factor.secret, code, now.timestamp()) {
+        Some(step) => store.use_totp_step(account_id, step).await,
+        None => store.use_recovery_code(account_id, &hash_recovery_code(code), now.naive_utc()).await,
+    }
+}
+
+/// Ten characters like `k3xv9-q2mfa`, about 50 bits.
+fn generate_recovery_code() -> String {
+    let bytes = rand::thread_rng().gen::<[u8; 7]>();
+    let code = base32::encode(base32::Alphabet::RFC4648 { padding: false }, &bytes).to_lowercase();
+    format!("{}-{}", &code[..5], &code[5..10])
+}
+
+/// Recovery codes are random, so a fast unsalted hash is enough. Case,
+/// dashes and spaces do not matter when typing them.
+fn hash_recovery_code(code: &str) -> String {
+    let normalized = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<String>();
+    hex::encode(Sha256::digest(normalized.as_bytes()))
+}
+
+
+#[cfg(test)]
+mod two_factor_tests {
+    use super::*;
+    use crate::{store, totp};
+    use crate::routes::test_support::{register_and_login, test_routes_with, TEST_PASSWORD};
+
+    #[test]
+    fn recovery_codes() {
+        // act
+        let code = generate_recovery_code();
+
+        // assert
+        assert_eq!(code.len(), 11);
+        assert_eq!(&code[5..6], "-");
+        assert_eq!(hash_recovery_code(&code), hash_recovery_code(&code.to_uppercase().replace('-', " ")));
+        assert_ne!(hash_recovery_code(&code), hash_recovery_code(&generate_recovery_code()));
+    }
+
+    #[tokio::test]
+    async fn two_factor_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes_with(
+            std::sync::Arc::new(store::InMemoryStore::new()),
+            &["--login-backoff-ms", "0"],
+        ).await;
+        let access_token = register_and_login(&routes, &mail_dir, "2fa@example.com").await;
+        let post = |path: &str, body: serde_json::Value| warp::test::request()
+            .method("POST")
+            .path(path)
+            .header("Authorization", &access_token)
+            .json(&body);
+        let setup = post("/account/2fa/setup", serde_json::json!({})).reply(&routes).await;
+        let setup: serde_json::Value = serde_json::from_slice(setup.body()).unwrap();
+        let secret = setup["secret"].as_str().unwrap().to_string();
+        let step = totp::step(chrono::Utc::now().timestamp());
+        let code = |step: i64| totp::code(&secret, step).unwrap();
+        let password_step = || warp::test::request()
+            .method("POST")
+            .path("/login")
+            .json(&serde_json::json!({ "email": "2fa@example.com", "password": TEST_PASSWORD }))
+            .reply(&routes);
+        let second_step = |token: &serde_json::Value, code: &str| warp::test::request()
+            .method("POST")
+            .path("/login/2fa")
+            .json(&serde_json::json!({ "two_factor_token": token, "code": code }))
+            .reply(&routes);
+
+        // act
+        let wrong_confirmation = post("/account/2fa/confirm", serde_json::json!({ "code": code(step - 10) })).reply(&routes).await;
+        let confirmed = post("/account/2fa/confirm", serde_json::json!({ "code": code(step) })).reply(&routes).await;
+        let setup_again = post("/account/2fa/setup", serde_json::json!({})).reply(&routes).await;
+        let challenge = password_step().await;
+        let challenge: serde_json::Value = serde_json::from_slice(challenge.body()).unwrap();
+        let token = &challenge["two_factor_token"];
+        let replayed_confirmation_code = second_step(token, &code(step)).await;
+        let with_totp = second_step(token, &code(step + 1)).await;
+        let replayed = second_step(token, &code(step + 1)).await;
+        let recovery_codes: serde_json::Value = serde_json::from_slice(confirmed.body()).unwrap();
+        let recovery_code = recovery_codes["recovery_codes"][0].as_str().unwrap().to_uppercase();
+        let with_recovery_code = second_step(token, &recovery_code).await;
+        let recovery_code_again = second_step(token, &recovery_code).await;
+        let forged = second_step(&serde_json::json!("v2.local.forged"), &code(step + 1)).await;
+        let disable = |body: serde_json::Value| warp::test::request()
+            .method("DELETE")
+            .path("/account/2fa")
+            .header("Authorization", &access_token)
+            .json(&body)
+            .reply(&routes);
+        let unconfirmed = disable(serde_json::json!({})).await;
+        let wrong_code = disable(serde_json::json!({ "code": "000000" })).await;
+        let disabled = disable(serde_json::json!({ "code": recovery_codes["recovery_codes"][1] })).await;
+        let disabled_again = disable(serde_json::json!({ "password": TEST_PASSWORD })).await;
+        let confirmed_without_setup = post("/account/2fa/confirm", serde_json::json!({ "code": code(step + 2) })).reply(&routes).await;
+        let after_disabling = password_step().await;
+        let after_disabling: serde_json::Value = serde_json::from_slice(after_disabling.body()).unwrap();
+
+        // assert
+        assert!(setup["otpauth_uri"].as_str().unwrap().starts_with("otpauth://totp/Web%20Questions:2fa%40example%2Ecom?secret="));
+        assert_eq!(wrong_confirmation.status(), 400);
+        assert_eq!(confirmed.status(), 200);
+        assert_eq!(recovery_codes["recovery_codes"].as_array().unwrap().len(), 10);
+        assert_eq!(setup_again.status(), 409);
+        assert_eq!(challenge["two_factor_required"], true);
+        assert!(challenge.get("access_token").is_none());
+        assert_eq!(replayed_confirmation_code.status(), 401);
+        assert_eq!(with_totp.status(), 200);
+        assert_eq!(replayed.status(), 401);
+        assert_eq!(with_recovery_code.status(), 200);
+        assert_eq!(recovery_code_again.status(), 401);
+        assert_eq!(forged.status(), 401);
+        assert_eq!(unconfirmed.status(), 400);
+        assert_eq!(wrong_code.status(), 401);
+        assert_eq!(disabled.status(), 200);
+        assert_eq!(disabled_again.status(), 409);
+        assert!(std::str::from_utf8(disabled_again.body()).unwrap().contains("two_factor_not_enabled"));
+        assert_eq!(confirmed_without_setup.status(), 409);
+        assert!(after_disabling["access_token"].is_string());
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+}
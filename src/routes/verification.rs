@@ -0,0 +1,124 @@
+use serde::Deserialize;
+
+use crate::types::account::{Account, AccountId, Session, VerificationQuery};
+
+const PURPOSE: &str = "verify_email";
+/// How long a verification link can be used.
+const VERIFICATION_TTL_DAYS: i64 = 2;
+
+#[derive(Deserialize)]
+struct VerificationClaims {
+    account_id: AccountId,
+    email: String,
+}
+
+/// Mails a link to `GET /verify` to the address of `account`.
+pub async fn send_verification(
+    mailer: &crate::mail::DynMailSender,
+    keyring: &crate::keyring::Keyring,
+    public_url: &str,
+    account: &Account,
+) -> Result<(), warp::Rejection> {
+    let token = super::authentication::issue_purpose_token(
+        keyring,
+        PURPOSE,
+        &[
+            ("account_id", serde_json::json!(account.id)),
+            ("email", serde_json::json!(account.email)),
+        ],
+        chrono::Duration::days(VERIFICATION_TTL_DAYS),
+    );
+    let link = format!("{}/verify?token={}", public_url, token);
+
+    match mailer.send(crate::mail::verification_email(&account.email, &link)).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Marks the address in the token as verified. The token carries the
+/// address, so it stops working once used or after the address changed.
+pub async fn verify(
+    query: VerificationQuery,
+    store: crate::store::DynStore,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let claims = super::authentication::verify_purpose_token::<VerificationClaims>(&keyring, &query.token, PURPOSE)
+        .map_err(|_| warp::reject::custom(handle_errors::Error::InvalidParameter("token".to_string())))?;
+
+    match store.verify_email(&claims.account_id, &claims.email).await {
+        Ok(true) => Ok(warp::reply::with_status("E-mail address verified", warp::http::StatusCode::OK)),
+        Ok(false) => Err(warp::reject::custom(handle_errors::Error::InvalidParameter("token".to_string()))),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Sends a new verification link to the logged in account.
+pub async fn resend(
+    session: Session,
+    store: crate::store::DynStore,
+    mailer: crate::mail::DynMailSender,
+    keyring: std::sync::Arc<crate::keyring::Keyring>,
+    public_url: String,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let account = match store.get_account_by_id(&session.account_id).await {
+        Ok(account) => account,
+        Err(e) => return Err(warp::reject::custom(e)),
+    };
+    if account.email_verified {
+        return Ok(warp::reply::with_status("E-mail address already verified", warp::http::StatusCode::OK));
+    }
+    send_verification(&mailer, &keyring, &public_url, &account).await?;
+
+    Ok(warp::reply::with_status("Verification e-mail sent", warp::http::StatusCode::OK))
+}
+
+
+#[cfg(test)]
+mod verification_tests {
+    use crate::store;
+    use crate::routes::test_support::{login, mailed_token, test_routes, TEST_PASSWORD};
+
+    #[tokio::test]
+    async fn email_verification_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let account = serde_json::json!({ "email": "test@example.com", "password": TEST_PASSWORD });
+        warp::test::request().method("POST").path("/registration").json(&account).reply(&routes).await;
+        let first_token = mailed_token(&mail_dir, "test@example.com");
+        let access_token = login(&routes, "test@example.com").await;
+        let add_question = || warp::test::request()
+            .method("POST")
+            .path("/questions")
+            .header("Authorization", &access_token)
+            .json(&serde_json::json!({ "title": "Title", "content": "Content" }));
+        let verify = |token: &str| warp::test::request()
+            .method("GET")
+            .path(&format!("/verify?token={}", token));
+
+        // act
+        let unverified = add_question().reply(&routes).await;
+        let resent = warp::test::request()
+            .method("POST")
+            .path("/verify/resend")
+            .header("Authorization", &access_token)
+            .reply(&routes)
+            .await;
+        let with_access_token = verify(&access_token).reply(&routes).await;
+        let verified = verify(&mailed_token(&mail_dir, "test@example.com")).reply(&routes).await;
+        let reused = verify(&first_token).reply(&routes).await;
+        let added = add_question().reply(&routes).await;
+
+        // assert
+        assert_eq!(unverified.status(), 403);
+        let error: serde_json::Value = serde_json::from_slice(unverified.body()).unwrap();
+        assert_eq!(error["code"], "email_not_verified");
+        assert_eq!(resent.status(), 200);
+        assert_eq!(std::fs::read_dir(&mail_dir).unwrap().count(), 2);
+        assert_eq!(with_access_token.status(), 400);
+        assert_eq!(verified.status(), 200);
+        assert_eq!(reused.status(), 400);
+        assert_eq!(added.status(), 201);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+}
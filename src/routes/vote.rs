@@ -0,0 +1,115 @@
+use crate::types::account::Session;
+use crate::types::vote::{NewVote, VoteResult};
+
+/// Up or down vote on a question, or takes the vote back. One vote per
+/// account, a new one replaces the earlier.
+pub async fn vote_question(
+    id: i32,
+    session: Session,
+    store: crate::store::DynStore,
+    vote: NewVote,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_question_owner(id).await? {
+        Some(owner) if owner == session.account_id => {
+            return Err(warp::reject::custom(handle_errors::Error::OwnContentVote));
+        },
+        Some(_) => (),
+        None => return Err(warp::reject::custom(handle_errors::Error::QuestionNotFound)),
+    }
+
+    match store.vote_question(id, &session.account_id, vote.direction.value()).await {
+        Ok(Some(score)) => Ok(warp::reply::json(&VoteResult { direction: vote.direction, score })),
+        Ok(None) => Err(warp::reject::custom(handle_errors::Error::QuestionNotFound)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+/// Like `vote_question`, for an answer.
+pub async fn vote_answer(
+    id: i32,
+    session: Session,
+    store: crate::store::DynStore,
+    vote: NewVote,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_answer_owner(id).await? {
+        Some(owner) if owner == session.account_id => {
+            return Err(warp::reject::custom(handle_errors::Error::OwnContentVote));
+        },
+        Some(_) => (),
+        None => return Err(warp::reject::custom(handle_errors::Error::AnswerNotFound)),
+    }
+
+    match store.vote_answer(id, &session.account_id, vote.direction.value()).await {
+        Ok(Some(score)) => Ok(warp::reply::json(&VoteResult { direction: vote.direction, score })),
+        Ok(None) => Err(warp::reject::custom(handle_errors::Error::AnswerNotFound)),
+        Err(e) => Err(warp::reject::custom(e)),
+    }
+}
+
+
+#[cfg(test)]
+mod vote_tests {
+    use crate::{store, types};
+    use crate::routes::test_support::{register_and_login, test_routes};
+
+    #[tokio::test]
+    async fn votes_with_in_memory_store() {
+        // arrange
+        let (routes, mail_dir) = test_routes(std::sync::Arc::new(store::InMemoryStore::new())).await;
+        let owner = register_and_login(&routes, &mail_dir, "owner@example.com").await;
+        let voter = register_and_login(&routes, &mail_dir, "voter@example.com").await;
+        for title in ["First", "Second"] {
+            warp::test::request()
+                .method("POST")
+                .path("/questions")
+                .header("Authorization", &owner)
+                .json(&serde_json::json!({ "title": title, "content": "Content" }))
+                .reply(&routes)
+                .await;
+        }
+        for content in ["Old", "New"] {
+            warp::test::request()
+                .method("POST")
+                .path("/answers")
+                .header("Authorization", &owner)
+                .body(format!("content={}&question_id=1", content))
+                .reply(&routes)
+                .await;
+        }
+        let vote = |token: &str, path: &str, direction: &str| warp::test::request()
+            .method("POST")
+            .path(path)
+            .header("Authorization", token)
+            .json(&serde_json::json!({ "direction": direction }));
+        let get = |path: &str| warp::test::request().method("GET").path(path);
+
+        // act
+        let own = vote(&owner, "/questions/2/vote", "up").reply(&routes).await;
+        let up = vote(&voter, "/questions/2/vote", "up").reply(&routes).await;
+        let down = vote(&voter, "/questions/1/vote", "down").reply(&routes).await;
+        let answer_up = vote(&voter, "/answers/2/vote", "up").reply(&routes).await;
+        let unknown = vote(&voter, "/answers/9/vote", "up").reply(&routes).await;
+        let invalid = vote(&voter, "/answers/1/vote", "sideways").reply(&routes).await;
+        let by_score = get("/questions?sort=score&order=desc").reply(&routes).await;
+        let answers_by_score = get("/questions/1/answers?sort=score").reply(&routes).await;
+        let cleared = vote(&voter, "/questions/2/vote", "clear").reply(&routes).await;
+
+        // assert
+        assert_eq!(own.status(), 403);
+        let error: serde_json::Value = serde_json::from_slice(own.body()).unwrap();
+        assert_eq!(error["code"], "own_content_vote");
+        let up: types::vote::VoteResult = serde_json::from_slice(up.body()).unwrap();
+        assert_eq!(up, types::vote::VoteResult { direction: types::vote::VoteDirection::Up, score: 1 });
+        assert_eq!(down.status(), 200);
+        assert_eq!(answer_up.status(), 200);
+        assert_eq!(unknown.status(), 404);
+        assert_eq!(invalid.status(), 400);
+        let by_score: Vec<serde_json::Value> = serde_json::from_slice(by_score.body()).unwrap();
+        assert_eq!(by_score.iter().map(|q| (q["id"].as_i64().unwrap(), q["score"].as_i64().unwrap())).collect::<Vec<_>>(), vec![(2, 1), (1, -1)]);
+        let answers_by_score: Vec<types::answer::Answer> = serde_json::from_slice(answers_by_score.body()).unwrap();
+        assert_eq!(answers_by_score.iter().map(|a| a.content.as_str()).collect::<Vec<_>>(), vec!["New", "Old"]);
+        let cleared: types::vote::VoteResult = serde_json::from_slice(cleared.body()).unwrap();
+        assert_eq!(cleared.score, 0);
+        std::fs::remove_dir_all(mail_dir).unwrap();
+    }
+}
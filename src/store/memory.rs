@@ -0,0 +1,1515 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{NaiveDateTime, Timelike};
+use serde::{Deserialize, Deserializer};
+use tokio::sync::RwLock;
+
+use super::{AccountRepository, AnswerRepository, ApiKeyRepository, CommentRepository, QuestionRepository, SessionRepository, TagRepository, TwoFactorRepository, VoteRepository};
+
+use crate::types::account::{deleted_account_email, public_name, Account, AccountId, Author, Profile, PublicProfile, Role, StoredSession, DELETED_ACCOUNT_NAME};
+use crate::types::question::{Question, QuestionId, NewQuestion, QuestionFilter, QuestionSort, SortField, SortOrder};
+use crate::types::answer::{Answer, AnswerId, AnswerSort, NewAnswer};
+use crate::types::comment::{Comment, CommentId, CommentTarget};
+use crate::types::api_key::{ApiKey, ApiKeyId, NewApiKey};
+use crate::types::pagination::Cursor;
+use crate::types::search::{escape_html, QuestionSearchResult, HIGHLIGHT_START, HIGHLIGHT_STOP};
+use crate::types::tag::Tag;
+use crate::types::two_factor::TwoFactor;
+
+/// Owner recorded for questions loaded from a seed file.
+const SEED_ACCOUNT_ID: AccountId = AccountId(0);
+
+/// Storage backend keeping everything in process memory, for tests and local
+/// runs without Postgres. Mirrors the behaviour of the Postgres `Store`.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    tables: Arc<RwLock<Tables>>,
+}
+
+#[derive(Debug, Default)]
+struct Tables {
+    questions: BTreeMap<i32, Owned<Question>>,
+    answers: BTreeMap<i32, Owned<Answer>>,
+    comments: BTreeMap<i32, Owned<Comment>>,
+    accounts: BTreeMap<i32, Account>,
+    sessions: BTreeMap<String, SessionRow>,
+    /// API keys by id, each with the hash of the key.
+    api_keys: BTreeMap<i32, (ApiKey, String)>,
+    /// Accounts by OpenID Connect issuer and subject.
+    identities: BTreeMap<(String, String), AccountId>,
+    two_factor: BTreeMap<i32, TwoFactor>,
+    /// Recovery code hashes by account id, each with the time it was used.
+    recovery_codes: BTreeMap<i32, Vec<(String, Option<NaiveDateTime>)>>,
+    /// Votes by question id and account id, 1 or -1.
+    question_votes: BTreeMap<(i32, i32), i16>,
+    /// Votes by answer id and account id, 1 or -1.
+    answer_votes: BTreeMap<(i32, i32), i16>,
+    question_seq: i32,
+    answer_seq: i32,
+    comment_seq: i32,
+    account_seq: i32,
+    api_key_seq: i32,
+}
+
+impl Tables {
+    fn author(&self, account_id: &AccountId) -> Author {
+        let display_name = self.accounts
+            .get(&account_id.0)
+            .and_then(|a| a.profile.display_name.clone());
+        Author::new(account_id.clone(), display_name)
+    }
+
+    /// Stored question as the store hands it out, with its author.
+    fn question(&self, stored: &Owned<Question>) -> Question {
+        Question { author: Some(self.author(&stored.account_id)), ..stored.item.clone() }
+    }
+
+    /// Stored answer as the store hands it out, with its author.
+    fn answer(&self, stored: &Owned<Answer>) -> Answer {
+        Answer { author: Some(self.author(&stored.account_id)), ..stored.item.clone() }
+    }
+
+    /// Stored comment as the store hands it out, with its author.
+    fn comment(&self, stored: &Owned<Comment>) -> Comment {
+        Comment { author: self.author(&stored.account_id), ..stored.item.clone() }
+    }
+
+    /// Drops the votes and comments on questions and answers that are gone
+    /// and forgets accepted answers that are gone, like the foreign keys do
+    /// in Postgres.
+    fn drop_orphans(&mut self) {
+        let Tables { questions, answers, comments, question_votes, answer_votes, .. } = self;
+        question_votes.retain(|(question_id, _), _| questions.contains_key(question_id));
+        answer_votes.retain(|(answer_id, _), _| answers.contains_key(answer_id));
+        comments.retain(|_, c| match &c.item.target {
+            CommentTarget::Question(id) => questions.contains_key(&id.0),
+            CommentTarget::Answer(id) => answers.contains_key(&id.0),
+        });
+        for question in questions.values_mut() {
+            if question.item.accepted_answer_id.as_ref().is_some_and(|id| !answers.contains_key(&id.0)) {
+                question.item.accepted_answer_id = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Owned<T> {
+    item: T,
+    account_id: AccountId,
+}
+
+#[derive(Debug)]
+struct SessionRow {
+    account_id: AccountId,
+    refresh_token_hash: String,
+    expires_on: NaiveDateTime,
+    revoked_on: Option<NaiveDateTime>,
+}
+
+impl SessionRow {
+    fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.revoked_on.is_none() && self.expires_on > now
+    }
+}
+
+#[derive(Deserialize)]
+struct SeedQuestion {
+    #[serde(deserialize_with = "deserialize_id")]
+    id: i32,
+    title: String,
+    content: String,
+    tags: Option<Vec<String>>,
+}
+
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i32, D::Error> {
+    String::deserialize(deserializer)?
+        .parse::<i32>()
+        .map_err(serde::de::Error::custom)
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        InMemoryStore::default()
+    }
+
+    /// Store pre-filled with the questions shipped in `questions.json`.
+    pub fn seeded() -> Self {
+        InMemoryStore::from_json(include_str!("../../questions.json"))
+            .expect("questions.json is not a valid seed file")
+    }
+
+    /// Store pre-filled from a JSON object of questions keyed by their id.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let seed: HashMap<String, SeedQuestion> = serde_json::from_str(json)?;
+
+        let mut tables = Tables::default();
+        for question in seed.into_values() {
+            tables.question_seq = tables.question_seq.max(question.id);
+            tables.questions.insert(question.id, Owned {
+                item: Question {
+                    id: QuestionId(question.id),
+                    title: question.title,
+                    content: question.content,
+                    tags: question.tags,
+                    created_on: Some(now()),
+                    score: 0,
+                    accepted_answer_id: None,
+                    author: None,
+                },
+                account_id: SEED_ACCOUNT_ID,
+            });
+        }
+
+        Ok(InMemoryStore {
+            tables: Arc::new(RwLock::new(tables)),
+        })
+    }
+}
+
+fn page<T>(
+    items: impl Iterator<Item = T>,
+    limit: Option<i32>,
+    offset: i32,
+) -> Vec<T> {
+    items
+        .skip(offset.max(0) as usize)
+        .take(limit.map_or(usize::MAX, |limit| limit.max(0) as usize))
+        .collect()
+}
+
+/// Splits `text` into lowercase words, the unit search terms are matched on.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+fn term_hits(text: &str, terms: &[String]) -> usize {
+    words(text).filter(|word| terms.contains(word)).count()
+}
+
+fn matches_all(text: &str, terms: &[String]) -> bool {
+    let words = words(text).collect::<Vec<_>>();
+    terms.iter().all(|term| words.contains(term))
+}
+
+/// HTML-escaped `text` with the words among `terms` marked.
+fn highlight(text: &str, terms: &[String]) -> String {
+    let mut highlighted = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(char::is_alphanumeric) {
+        let (before, word_and_rest) = rest.split_at(start);
+        let end = word_and_rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(word_and_rest.len());
+        let (word, after) = word_and_rest.split_at(end);
+
+        highlighted.push_str(&escape_html(before));
+        if terms.contains(&word.to_lowercase()) {
+            highlighted.push_str(HIGHLIGHT_START);
+            highlighted.push_str(word);
+            highlighted.push_str(HIGHLIGHT_STOP);
+        } else {
+            highlighted.push_str(word);
+        }
+        rest = after;
+    }
+    highlighted.push_str(&escape_html(rest));
+    highlighted
+}
+
+/// Current time at the microsecond precision Postgres stores.
+fn now() -> chrono::NaiveDateTime {
+    let now = chrono::Utc::now().naive_utc();
+    now.with_nanosecond(now.nanosecond() / 1_000 * 1_000).unwrap_or(now)
+}
+
+fn row_not_found() -> handle_errors::Error {
+    handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)
+}
+
+#[async_trait]
+impl QuestionRepository for InMemoryStore {
+    async fn get_questions(&self, filter: &QuestionFilter, sort: &QuestionSort, limit: Option<i32>, offset: i32) -> Result<Vec<Question>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        let answer_count = |question: &Question| tables.answers
+            .values()
+            .filter(|a| a.item.question_id == question.id)
+            .count();
+        let mut questions = tables.questions
+            .values()
+            .filter(|q| filter.matches(&q.item))
+            .map(|q| tables.question(q))
+            .collect::<Vec<_>>();
+        questions.sort_by(|a, b| {
+            let ordering = match sort.field {
+                SortField::Id => std::cmp::Ordering::Equal,
+                SortField::CreatedOn => a.created_on.cmp(&b.created_on),
+                SortField::Title => a.title.cmp(&b.title),
+                SortField::AnswerCount => answer_count(a).cmp(&answer_count(b)),
+                SortField::Score => a.score.cmp(&b.score),
+            }.then(a.id.0.cmp(&b.id.0));
+            match sort.order {
+                SortOrder::Asc => ordering,
+                SortOrder::Desc => ordering.reverse(),
+            }
+        });
+        Ok(page(questions.into_iter(), limit, offset))
+    }
+    async fn get_questions_by_key(&self, filter: &QuestionFilter, scan: SortOrder, after: Option<&Cursor>, limit: Option<i32>) -> Result<Vec<Question>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        let key = |q: &Question| (q.created_on, q.id.0);
+        let after = after.map(|cursor| (Some(cursor.created_on), cursor.id));
+        let mut questions = tables.questions
+            .values()
+            .filter(|q| filter.matches(&q.item))
+            .map(|q| tables.question(q))
+            .filter(|q| match (scan, after) {
+                (_, None) => true,
+                (SortOrder::Asc, Some(after)) => key(q) > after,
+                (SortOrder::Desc, Some(after)) => key(q) < after,
+            })
+            .collect::<Vec<_>>();
+        questions.sort_by_key(|q| key(q));
+        if scan == SortOrder::Desc {
+            questions.reverse();
+        }
+        Ok(page(questions.into_iter(), limit, 0))
+    }
+    async fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.questions
+            .values()
+            .filter(|q| filter.matches(&q.item))
+            .count() as i64)
+    }
+    async fn get_question(&self, id: i32) -> Result<Option<Question>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.questions.get(&id).map(|q| tables.question(q)))
+    }
+    async fn search_questions(&self, query: &str, limit: Option<i32>, offset: i32) -> Result<Vec<QuestionSearchResult>, handle_errors::Error> {
+        // Plain case-insensitive word matching, weighted like the Postgres
+        // ranking (title A, content B, answers half of D) but without stemming.
+        let terms = words(query).collect::<Vec<_>>();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let tables = self.tables.read().await;
+        let mut results = tables.questions
+            .values()
+            .filter_map(|stored| {
+                let question = &stored.item;
+                let best_answer = tables.answers
+                    .values()
+                    .map(|a| &a.item)
+                    .filter(|a| a.question_id == question.id && matches_all(&a.content, &terms))
+                    .max_by_key(|a| term_hits(&a.content, &terms));
+                let question_text = format!("{} {}", question.title, question.content);
+                if !matches_all(&question_text, &terms) && best_answer.is_none() {
+                    return None;
+                }
+
+                let rank = term_hits(&question.title, &terms) as f32
+                    + term_hits(&question.content, &terms) as f32 * 0.4
+                    + best_answer.map_or(0.0, |a| term_hits(&a.content, &terms) as f32 * 0.05);
+                Some(QuestionSearchResult {
+                    question: tables.question(stored),
+                    rank,
+                    title_snippet: highlight(&question.title, &terms),
+                    content_snippet: highlight(&question.content, &terms),
+                    answer_snippet: best_answer.map(|a| highlight(&a.content, &terms)),
+                })
+            })
+            .collect::<Vec<_>>();
+        results.sort_by(|a, b| b.rank.total_cmp(&a.rank).then(a.question.id.0.cmp(&b.question.id.0)));
+
+        Ok(page(results.into_iter(), limit, offset))
+    }
+    async fn add_question(&self, new_question: NewQuestion, account_id: &AccountId) -> Result<Question, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        tables.question_seq += 1;
+        let question = Question {
+            id: QuestionId(tables.question_seq),
+            title: new_question.title,
+            content: new_question.content,
+            tags: new_question.tags,
+            created_on: Some(now()),
+            score: 0,
+            accepted_answer_id: None,
+            author: None,
+        };
+        let stored = Owned {
+            item: question,
+            account_id: account_id.clone(),
+        };
+        let question = tables.question(&stored);
+        tables.questions.insert(question.id.0, stored);
+        Ok(question)
+    }
+    async fn update_question(&self, id: i32, question: Question, account_id: &AccountId) -> Result<Question, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.questions.get_mut(&id) {
+            Some(stored) if &stored.account_id == account_id => {
+                stored.item.title = question.title;
+                stored.item.content = question.content;
+                stored.item.tags = question.tags;
+            },
+            _ => return Err(row_not_found()),
+        }
+        Ok(tables.question(&tables.questions[&id]))
+    }
+    async fn delete_question(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        if tables.questions.get(&id).is_some_and(|q| &q.account_id == account_id) {
+            tables.questions.remove(&id);
+            tables.answers.retain(|_, a| a.item.question_id.0 != id);
+            tables.drop_orphans();
+        }
+        Ok(true)
+    }
+    async fn is_question_owner(&self, question_id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.questions.get(&question_id).is_some_and(|q| &q.account_id == account_id))
+    }
+    async fn get_question_owner(&self, question_id: i32) -> Result<Option<AccountId>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.questions.get(&question_id).map(|q| q.account_id.clone()))
+    }
+    async fn get_questions_by_account(&self, account_id: &AccountId) -> Result<Vec<Question>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.questions
+            .values()
+            .filter(|q| &q.account_id == account_id)
+            .map(|q| tables.question(q))
+            .collect())
+    }
+    async fn accept_answer(&self, question_id: i32, answer_id: i32) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let Tables { questions, answers, .. } = &mut *tables;
+        match (questions.get_mut(&question_id), answers.get(&answer_id)) {
+            (Some(question), Some(answer)) if answer.item.question_id.0 == question_id => {
+                question.item.accepted_answer_id = Some(AnswerId(answer_id));
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+    async fn unaccept_answer(&self, question_id: i32, answer_id: i32) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.questions.get_mut(&question_id) {
+            Some(question) if question.item.accepted_answer_id == Some(AnswerId(answer_id)) => {
+                question.item.accepted_answer_id = None;
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl AnswerRepository for InMemoryStore {
+    async fn add_answer(&self, new_answer: NewAnswer, account_id: &AccountId) -> Result<Answer, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        if !tables.questions.contains_key(&new_answer.question_id.0) {
+            return Err(handle_errors::Error::QuestionNotFound);
+        }
+        tables.answer_seq += 1;
+        let answer = Answer {
+            id: AnswerId(tables.answer_seq),
+            content: new_answer.content,
+            question_id: new_answer.question_id,
+            score: 0,
+            author: None,
+        };
+        let stored = Owned {
+            item: answer,
+            account_id: account_id.clone(),
+        };
+        let answer = tables.answer(&stored);
+        tables.answers.insert(answer.id.0, stored);
+        Ok(answer)
+    }
+    async fn get_answers(&self, question_id: i32, sort: AnswerSort, limit: Option<i32>, offset: i32) -> Result<Vec<Answer>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        let mut answers = tables.answers
+            .values()
+            .filter(|a| a.item.question_id.0 == question_id)
+            .map(|a| tables.answer(a))
+            .collect::<Vec<_>>();
+        if sort == AnswerSort::Score {
+            // stable, so ties stay oldest first
+            answers.sort_by_key(|a| std::cmp::Reverse(a.score));
+        }
+        let accepted = tables.questions.get(&question_id).and_then(|q| q.item.accepted_answer_id.clone());
+        answers.sort_by_key(|a| Some(&a.id) != accepted.as_ref());
+        Ok(page(answers.into_iter(), limit, offset))
+    }
+    async fn get_answer(&self, id: i32) -> Result<Option<Answer>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.answers.get(&id).map(|a| tables.answer(a)))
+    }
+    async fn update_answer(&self, id: i32, answer: Answer, account_id: &AccountId) -> Result<Answer, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.answers.get_mut(&id) {
+            Some(stored) if &stored.account_id == account_id => {
+                stored.item.content = answer.content;
+            },
+            _ => return Err(row_not_found()),
+        }
+        Ok(tables.answer(&tables.answers[&id]))
+    }
+    async fn delete_answer(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        if tables.answers.get(&id).is_some_and(|a| &a.account_id == account_id) {
+            tables.answers.remove(&id);
+            tables.drop_orphans();
+        }
+        Ok(true)
+    }
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.answers.get(&answer_id).is_some_and(|a| &a.account_id == account_id))
+    }
+    async fn get_answer_owner(&self, answer_id: i32) -> Result<Option<AccountId>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.answers.get(&answer_id).map(|a| a.account_id.clone()))
+    }
+    async fn get_answers_by_account(&self, account_id: &AccountId) -> Result<Vec<Answer>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.answers
+            .values()
+            .filter(|a| &a.account_id == account_id)
+            .map(|a| tables.answer(a))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl AccountRepository for InMemoryStore {
+    async fn add_account(&self, new_account: Account) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        if tables.accounts.values().any(|a| a.email.eq_ignore_ascii_case(&new_account.email)) {
+            return Err(handle_errors::Error::AccountAlreadyExists);
+        }
+        tables.account_seq += 1;
+        let id = tables.account_seq;
+        tables.accounts.insert(id, Account {
+            id: Some(AccountId(id)),
+            email: new_account.email,
+            password: new_account.password,
+            role: Role::User,
+            email_verified: false,
+            profile: Profile::default(),
+        });
+        Ok(true)
+    }
+    async fn get_account(&self, email: String) -> Result<Account, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        tables.accounts
+            .values()
+            .find(|a| a.email.eq_ignore_ascii_case(&email))
+            .cloned()
+            .ok_or_else(row_not_found)
+    }
+    async fn get_account_by_id(&self, account_id: &AccountId) -> Result<Account, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        tables.accounts.get(&account_id.0).cloned().ok_or_else(row_not_found)
+    }
+    async fn get_accounts(&self) -> Result<Vec<Account>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.accounts.values().cloned().collect())
+    }
+    async fn update_password(&self, account_id: &AccountId, password: &str) -> Result<(), handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let account = tables.accounts.get_mut(&account_id.0).ok_or_else(row_not_found)?;
+        account.password = password.to_string();
+        Ok(())
+    }
+    async fn verify_email(&self, account_id: &AccountId, email: &str) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.accounts.get_mut(&account_id.0) {
+            Some(account) if account.email == email && !account.email_verified => {
+                account.email_verified = true;
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+    async fn set_account_role(&self, account_id: &AccountId, role: Role) -> Result<Account, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let account = tables.accounts.get_mut(&account_id.0).ok_or_else(row_not_found)?;
+        account.role = role;
+        Ok(account.clone())
+    }
+    async fn update_profile(&self, account_id: &AccountId, profile: &Profile) -> Result<Account, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let account = tables.accounts.get_mut(&account_id.0).ok_or_else(row_not_found)?;
+        account.profile = profile.clone();
+        Ok(account.clone())
+    }
+    async fn get_public_profile(&self, account_id: &AccountId) -> Result<PublicProfile, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        let account = tables.accounts.get(&account_id.0).ok_or_else(row_not_found)?;
+        Ok(PublicProfile {
+            id: account_id.clone(),
+            display_name: public_name(account_id, account.profile.display_name.clone()),
+            bio: account.profile.bio.clone(),
+            avatar_url: account.profile.avatar_url.clone(),
+            question_count: tables.questions.values().filter(|q| &q.account_id == account_id).count() as i64,
+            answer_count: tables.answers.values().filter(|a| &a.account_id == account_id).count() as i64,
+        })
+    }
+    async fn anonymize_account(&self, account_id: &AccountId, password: &str) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let Some(account) = tables.accounts.get_mut(&account_id.0) else {
+            return Ok(false);
+        };
+        *account = Account {
+            id: Some(account_id.clone()),
+            email: deleted_account_email(account_id),
+            password: password.to_string(),
+            role: Role::User,
+            email_verified: false,
+            profile: Profile {
+                display_name: Some(DELETED_ACCOUNT_NAME.to_string()),
+                ..Profile::default()
+            },
+        };
+        tables.sessions.retain(|_, s| &s.account_id != account_id);
+        tables.api_keys.retain(|_, (k, _)| &k.account_id != account_id);
+        tables.identities.retain(|_, id| id != account_id);
+        tables.two_factor.remove(&account_id.0);
+        tables.recovery_codes.remove(&account_id.0);
+        Ok(true)
+    }
+    async fn delete_account(&self, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        if tables.accounts.remove(&account_id.0).is_none() {
+            return Ok(false);
+        }
+        let Tables { questions, answers, question_votes, answer_votes, .. } = &mut *tables;
+        question_votes.retain(|&(question_id, voter), value| {
+            if voter != account_id.0 {
+                return true;
+            }
+            if let Some(question) = questions.get_mut(&question_id) {
+                question.item.score -= i32::from(*value);
+            }
+            false
+        });
+        answer_votes.retain(|&(answer_id, voter), value| {
+            if voter != account_id.0 {
+                return true;
+            }
+            if let Some(answer) = answers.get_mut(&answer_id) {
+                answer.item.score -= i32::from(*value);
+            }
+            false
+        });
+        questions.retain(|_, q| &q.account_id != account_id);
+        answers.retain(|_, a| &a.account_id != account_id && questions.contains_key(&a.item.question_id.0));
+        tables.comments.retain(|_, c| &c.account_id != account_id);
+        tables.drop_orphans();
+        tables.sessions.retain(|_, s| &s.account_id != account_id);
+        tables.api_keys.retain(|_, (k, _)| &k.account_id != account_id);
+        tables.identities.retain(|_, id| id != account_id);
+        tables.two_factor.remove(&account_id.0);
+        tables.recovery_codes.remove(&account_id.0);
+        Ok(true)
+    }
+    async fn get_account_by_identity(&self, issuer: &str, subject: &str) -> Result<Option<Account>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.identities
+            .get(&(issuer.to_string(), subject.to_string()))
+            .and_then(|id| tables.accounts.get(&id.0))
+            .cloned())
+    }
+    async fn link_identity(&self, account_id: &AccountId, issuer: &str, subject: &str) -> Result<(), handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let key = (issuer.to_string(), subject.to_string());
+        if tables.identities.contains_key(&key) {
+            return Err(handle_errors::Error::AccountAlreadyExists);
+        }
+        tables.identities.insert(key, account_id.clone());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionRepository for InMemoryStore {
+    async fn add_session(&self, session: &StoredSession, refresh_token_hash: &str, expires_on: NaiveDateTime) -> Result<(), handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        tables.sessions.insert(session.id.clone(), SessionRow {
+            account_id: session.account_id.clone(),
+            refresh_token_hash: refresh_token_hash.to_string(),
+            expires_on,
+            revoked_on: None,
+        });
+        Ok(())
+    }
+    async fn rotate_refresh_token(&self, refresh_token_hash: &str, new_refresh_token_hash: &str, now: NaiveDateTime) -> Result<Option<StoredSession>, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let session = tables.sessions
+            .iter_mut()
+            .find(|(_, s)| s.refresh_token_hash == refresh_token_hash && s.is_active(now));
+        Ok(session.map(|(id, session)| {
+            session.refresh_token_hash = new_refresh_token_hash.to_string();
+            StoredSession { id: id.clone(), account_id: session.account_id.clone() }
+        }))
+    }
+    async fn is_session_active(&self, id: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.sessions.get(id).is_some_and(|s| s.is_active(now)))
+    }
+    async fn revoke_session(&self, id: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.sessions.get_mut(id) {
+            Some(session) if session.revoked_on.is_none() => {
+                session.revoked_on = Some(now);
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+    async fn revoke_sessions(&self, account_id: &AccountId, now: NaiveDateTime) -> Result<u64, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let mut revoked = 0;
+        for session in tables.sessions.values_mut() {
+            if &session.account_id == account_id && session.is_active(now) {
+                session.revoked_on = Some(now);
+                revoked += 1;
+            }
+        }
+        Ok(revoked)
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for InMemoryStore {
+    async fn add_api_key(&self, account_id: &AccountId, new_api_key: &NewApiKey, prefix: &str, key_hash: &str) -> Result<ApiKey, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        tables.api_key_seq += 1;
+        let api_key = ApiKey {
+            id: ApiKeyId(tables.api_key_seq),
+            account_id: account_id.clone(),
+            name: new_api_key.name.clone(),
+            prefix: prefix.to_string(),
+            scopes: new_api_key.scopes.clone(),
+            created_on: now(),
+            last_used_on: None,
+            revoked_on: None,
+        };
+        tables.api_keys.insert(api_key.id.0, (api_key.clone(), key_hash.to_string()));
+        Ok(api_key)
+    }
+    async fn get_api_keys(&self, account_id: &AccountId) -> Result<Vec<ApiKey>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.api_keys
+            .values()
+            .filter(|(k, _)| &k.account_id == account_id)
+            .map(|(k, _)| k.clone())
+            .collect())
+    }
+    async fn revoke_api_key(&self, id: &ApiKeyId, account_id: &AccountId, now: NaiveDateTime) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.api_keys.get_mut(&id.0) {
+            Some((api_key, _)) if &api_key.account_id == account_id && api_key.revoked_on.is_none() => {
+                api_key.revoked_on = Some(now);
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+    async fn use_api_key(&self, key_hash: &str, now: NaiveDateTime) -> Result<Option<ApiKey>, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        Ok(tables.api_keys
+            .values_mut()
+            .find(|(k, hash)| hash == key_hash && k.revoked_on.is_none())
+            .map(|(api_key, _)| {
+                api_key.last_used_on = Some(now);
+                api_key.clone()
+            }))
+    }
+}
+
+#[async_trait]
+impl TwoFactorRepository for InMemoryStore {
+    async fn get_two_factor(&self, account_id: &AccountId) -> Result<Option<TwoFactor>, handle_errors::Error> {
+        Ok(self.tables.read().await.two_factor.get(&account_id.0).cloned())
+    }
+    async fn set_two_factor_secret(&self, account_id: &AccountId, secret: &str) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        if tables.two_factor.get(&account_id.0).is_some_and(|t| t.enabled) {
+            return Ok(false);
+        }
+        tables.two_factor.insert(account_id.0, TwoFactor {
+            secret: secret.to_string(),
+            enabled: false,
+            last_used_step: None,
+        });
+        Ok(true)
+    }
+    async fn enable_two_factor(&self, account_id: &AccountId, step: i64, recovery_code_hashes: &[String]) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.two_factor.get_mut(&account_id.0) {
+            Some(two_factor) if !two_factor.enabled => {
+                two_factor.enabled = true;
+                two_factor.last_used_step = Some(step);
+            },
+            _ => return Ok(false),
+        }
+        let codes = recovery_code_hashes.iter().map(|hash| (hash.clone(), None)).collect();
+        tables.recovery_codes.insert(account_id.0, codes);
+        Ok(true)
+    }
+    async fn disable_two_factor(&self, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        tables.recovery_codes.remove(&account_id.0);
+        Ok(tables.two_factor.remove(&account_id.0).is_some())
+    }
+    async fn use_totp_step(&self, account_id: &AccountId, step: i64) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.two_factor.get_mut(&account_id.0) {
+            Some(two_factor) if two_factor.enabled && two_factor.last_used_step.is_none_or(|last| last < step) => {
+                two_factor.last_used_step = Some(step);
+                Ok(true)
+            },
+            _ => Ok(false),
+        }
+    }
+    async fn use_recovery_code(&self, account_id: &AccountId, code_hash: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let unused = tables.recovery_codes
+            .get_mut(&account_id.0)
+            .and_then(|codes| codes.iter_mut().find(|(hash, used_on)| hash == code_hash && used_on.is_none()));
+        match unused {
+            Some((_, used_on)) => {
+                *used_on = Some(now);
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+}
+
+#[async_trait]
+impl VoteRepository for InMemoryStore {
+    async fn vote_question(&self, question_id: i32, account_id: &AccountId, value: i16) -> Result<Option<i32>, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let Tables { questions, question_votes, .. } = &mut *tables;
+        let Some(question) = questions.get_mut(&question_id) else {
+            return Ok(None);
+        };
+        question.item.score += i32::from(replace_vote(question_votes, (question_id, account_id.0), value));
+        Ok(Some(question.item.score))
+    }
+    async fn vote_answer(&self, answer_id: i32, account_id: &AccountId, value: i16) -> Result<Option<i32>, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        let Tables { answers, answer_votes, .. } = &mut *tables;
+        let Some(answer) = answers.get_mut(&answer_id) else {
+            return Ok(None);
+        };
+        answer.item.score += i32::from(replace_vote(answer_votes, (answer_id, account_id.0), value));
+        Ok(Some(answer.item.score))
+    }
+}
+
+#[async_trait]
+impl CommentRepository for InMemoryStore {
+    async fn add_comment(&self, target: &CommentTarget, content: String, account_id: &AccountId) -> Result<Comment, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match target {
+            CommentTarget::Question(id) if !tables.questions.contains_key(&id.0) => return Err(handle_errors::Error::QuestionNotFound),
+            CommentTarget::Answer(id) if !tables.answers.contains_key(&id.0) => return Err(handle_errors::Error::AnswerNotFound),
+            _ => (),
+        }
+        tables.comment_seq += 1;
+        let stored = Owned {
+            item: Comment {
+                id: CommentId(tables.comment_seq),
+                content,
+                target: target.clone(),
+                created_on: now(),
+                author: tables.author(account_id),
+            },
+            account_id: account_id.clone(),
+        };
+        let comment = tables.comment(&stored);
+        tables.comments.insert(comment.id.0, stored);
+        Ok(comment)
+    }
+    async fn get_comments(&self, target: &CommentTarget, limit: Option<i32>, offset: i32) -> Result<Vec<Comment>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        let comments = tables.comments
+            .values()
+            .filter(|c| &c.item.target == target)
+            .map(|c| tables.comment(c));
+        Ok(page(comments, limit, offset))
+    }
+    async fn get_comment(&self, id: i32) -> Result<Option<Comment>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.comments.get(&id).map(|c| tables.comment(c)))
+    }
+    async fn update_comment(&self, id: i32, content: String, account_id: &AccountId) -> Result<Comment, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        match tables.comments.get_mut(&id) {
+            Some(stored) if &stored.account_id == account_id => {
+                stored.item.content = content;
+            },
+            _ => return Err(row_not_found()),
+        }
+        Ok(tables.comment(&tables.comments[&id]))
+    }
+    async fn delete_comment(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        let mut tables = self.tables.write().await;
+        if tables.comments.get(&id).is_some_and(|c| &c.account_id == account_id) {
+            tables.comments.remove(&id);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+    async fn get_comments_by_account(&self, account_id: &AccountId) -> Result<Vec<Comment>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        Ok(tables.comments
+            .values()
+            .filter(|c| &c.account_id == account_id)
+            .map(|c| tables.comment(c))
+            .collect())
+    }
+}
+
+/// Stores `value` as the vote under `key`, 0 removing it, and returns how
+/// much the score changes.
+fn replace_vote(votes: &mut BTreeMap<(i32, i32), i16>, key: (i32, i32), value: i16) -> i16 {
+    let previous = match value {
+        0 => votes.remove(&key),
+        _ => votes.insert(key, value),
+    };
+    value - previous.unwrap_or(0)
+}
+
+#[async_trait]
+impl TagRepository for InMemoryStore {
+    async fn get_tags(&self) -> Result<Vec<Tag>, handle_errors::Error> {
+        let tables = self.tables.read().await;
+        let mut counts = BTreeMap::<&str, i64>::new();
+        for question in tables.questions.values() {
+            let mut tags = question.item.tags.iter().flatten().map(String::as_str).collect::<Vec<_>>();
+            tags.sort_unstable();
+            tags.dedup();
+            for tag in tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+
+        let mut tags = counts
+            .into_iter()
+            .map(|(name, question_count)| Tag { name: name.to_string(), question_count })
+            .collect::<Vec<_>>();
+        tags.sort_by(|a, b| b.question_count.cmp(&a.question_count).then_with(|| a.name.cmp(&b.name)));
+        Ok(tags)
+    }
+}
+
+
+#[cfg(test)]
+mod memory_tests {
+    use super::*;
+    use crate::types::api_key::Scope;
+
+    fn new_question(title: &str) -> NewQuestion {
+        NewQuestion {
+            title: title.to_string(),
+            content: "How do I test this?".to_string(),
+            tags: Some(vec!["rust".to_string()]),
+        }
+    }
+
+    #[tokio::test]
+    async fn seeded_from_questions_json() {
+        // act
+        let store = InMemoryStore::seeded();
+
+        // assert
+        let questions = store.get_questions(&QuestionFilter::default(), &QuestionSort::default(), None, 0).await.unwrap();
+        assert_eq!(questions.len(), 1);
+        assert_eq!(questions[0].id, QuestionId(1));
+        assert_eq!(questions[0].title, "Question #1");
+
+        let added = store.add_question(new_question("Next"), &AccountId(1)).await.unwrap();
+        assert_eq!(added.id, QuestionId(2));
+    }
+
+    #[tokio::test]
+    async fn invalid_seed() {
+        // act
+        let result = InMemoryStore::from_json(r#"{"1": {"id": "one", "title": "t", "content": "c"}}"#);
+
+        // assert
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn accounts() {
+        // arrange
+        let store = InMemoryStore::new();
+        let account = Account {
+            id: None,
+            email: "test@example.com".to_string(),
+            password: "hashed".to_string(),
+            role: Role::Admin,
+            email_verified: true,
+            profile: Profile::default(),
+        };
+
+        // act
+        let added = store.add_account(account.clone()).await.unwrap();
+        let duplicate = store.add_account(account).await;
+        let fetched = store.get_account("test@example.com".to_string()).await.unwrap();
+        let missing = store.get_account("nobody@example.com".to_string()).await;
+        let promoted = store.set_account_role(&AccountId(1), Role::Moderator).await.unwrap();
+        let unknown = store.set_account_role(&AccountId(2), Role::Moderator).await;
+
+        // assert
+        assert!(added);
+        assert!(matches!(duplicate, Err(handle_errors::Error::AccountAlreadyExists)));
+        assert_eq!(fetched.id, Some(AccountId(1)));
+        assert_eq!(fetched.role, Role::User);
+        assert!(!fetched.email_verified);
+        assert!(missing.is_err());
+        assert!(!store.verify_email(&AccountId(1), "other@example.com").await.unwrap());
+        assert!(store.verify_email(&AccountId(1), "test@example.com").await.unwrap());
+        assert!(!store.verify_email(&AccountId(1), "test@example.com").await.unwrap());
+        assert!(store.get_account_by_id(&AccountId(1)).await.unwrap().email_verified);
+        store.update_password(&AccountId(1), "rehashed").await.unwrap();
+        assert_eq!(store.get_account_by_id(&AccountId(1)).await.unwrap().password, "rehashed");
+        assert!(store.update_password(&AccountId(2), "rehashed").await.is_err());
+        assert_eq!(promoted.role, Role::Moderator);
+        assert_eq!(store.get_accounts().await.unwrap()[0].role, Role::Moderator);
+        assert!(unknown.is_err());
+    }
+
+    #[tokio::test]
+    async fn sorting() {
+        // arrange
+        let store = InMemoryStore::new();
+        let owner = AccountId(1);
+        let b = store.add_question(new_question("B"), &owner).await.unwrap();
+        let c = store.add_question(new_question("C"), &owner).await.unwrap();
+        let a = store.add_question(new_question("A"), &owner).await.unwrap();
+        store.add_answer(NewAnswer { content: "1".to_string(), question_id: c.id.clone() }, &owner).await.unwrap();
+        store.add_answer(NewAnswer { content: "2".to_string(), question_id: c.id.clone() }, &owner).await.unwrap();
+        store.add_answer(NewAnswer { content: "3".to_string(), question_id: a.id.clone() }, &owner).await.unwrap();
+        let ids = |questions: Vec<Question>| questions.into_iter().map(|q| q.id).collect::<Vec<_>>();
+        let sort = |field, order| QuestionSort { field, order };
+        let no_filter = QuestionFilter::default();
+
+        // act
+        let by_title = store.get_questions(&no_filter, &sort(SortField::Title, SortOrder::Desc), None, 0).await.unwrap();
+        let by_answers = store.get_questions(&no_filter, &sort(SortField::AnswerCount, SortOrder::Desc), None, 0).await.unwrap();
+        let none_in_range = store.get_questions(
+            &QuestionFilter { end: a.created_on.map(|d| d - chrono::Duration::days(1)), ..QuestionFilter::default() },
+            &QuestionSort::default(),
+            None,
+            0,
+        ).await.unwrap();
+
+        // assert
+        assert_eq!(ids(by_title), vec![c.id.clone(), b.id.clone(), a.id.clone()]);
+        assert_eq!(ids(by_answers), vec![c.id, a.id, b.id]);
+        assert!(none_in_range.is_empty());
+    }
+
+    #[tokio::test]
+    async fn keyset_pagination() {
+        // arrange
+        let store = InMemoryStore::new();
+        let mut ids = Vec::new();
+        for title in ["1", "2", "3"] {
+            ids.push(store.add_question(new_question(title), &AccountId(1)).await.unwrap());
+        }
+        let no_filter = QuestionFilter::default();
+        let after = |q: &Question| Cursor {
+            created_on: q.created_on.unwrap(),
+            id: q.id.0,
+            direction: crate::types::pagination::Direction::Next,
+        };
+
+        // act
+        let newest = store.get_questions_by_key(&no_filter, SortOrder::Desc, None, Some(1)).await.unwrap();
+        let older = store.get_questions_by_key(&no_filter, SortOrder::Desc, Some(&after(&newest[0])), None).await.unwrap();
+        let total = store.count_questions(&no_filter).await.unwrap();
+
+        // assert
+        assert_eq!(newest[0].id, ids[2].id);
+        assert_eq!(older.iter().map(|q| q.id.clone()).collect::<Vec<_>>(), vec![ids[1].id.clone(), ids[0].id.clone()]);
+        assert_eq!(total, 3);
+    }
+
+    #[tokio::test]
+    async fn tags() {
+        // arrange
+        let store = InMemoryStore::new();
+        let tagged = |title: &str, tags: &[&str]| NewQuestion {
+            title: title.to_string(),
+            content: "content".to_string(),
+            tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+        };
+        store.add_question(tagged("Rust", &["rust", "rust"]), &AccountId(1)).await.unwrap();
+        store.add_question(tagged("Both", &["warp", "rust"]), &AccountId(1)).await.unwrap();
+        store.add_question(NewQuestion { tags: None, ..tagged("Untagged", &[]) }, &AccountId(1)).await.unwrap();
+
+        // act
+        let tags = store.get_tags().await.unwrap();
+        let warp = store.get_questions(&QuestionFilter::with_tag("warp".to_string()), &QuestionSort::default(), None, 0).await.unwrap();
+
+        // assert
+        assert_eq!(tags, vec![
+            Tag { name: "rust".to_string(), question_count: 2 },
+            Tag { name: "warp".to_string(), question_count: 1 },
+        ]);
+        assert_eq!(warp.len(), 1);
+        assert_eq!(warp[0].title, "Both");
+    }
+
+    #[test]
+    fn highlights_whole_words() {
+        // arrange
+        let terms = vec!["borrow".to_string()];
+
+        // act
+        let highlighted = highlight("Borrow, borrowing and (borrow)", &terms);
+
+        // assert
+        assert_eq!(highlighted, "<mark>Borrow</mark>, borrowing and (<mark>borrow</mark>)");
+    }
+
+    #[tokio::test]
+    async fn search() {
+        // arrange
+        let store = InMemoryStore::new();
+        let owner = AccountId(1);
+        let borrow = store.add_question(NewQuestion {
+            title: "Borrow checker errors".to_string(),
+            content: "Why does the compiler reject my references?".to_string(),
+            tags: None,
+        }, &owner).await.unwrap();
+        let lifetimes = store.add_question(NewQuestion {
+            title: "Lifetimes in structs".to_string(),
+            content: "When do I need explicit lifetimes?".to_string(),
+            tags: None,
+        }, &owner).await.unwrap();
+        store.add_answer(NewAnswer {
+            content: "Lifetimes tie references to the borrow scope.".to_string(),
+            question_id: borrow.id.clone(),
+        }, &owner).await.unwrap();
+        store.add_question(NewQuestion {
+            title: "Unsafe <b>markup</b>".to_string(),
+            content: "<img src=x onerror=alert(1)> is unsafe".to_string(),
+            tags: None,
+        }, &owner).await.unwrap();
+
+        // act
+        let by_title = store.search_questions("BORROW", None, 0).await.unwrap();
+        let markup = store.search_questions("unsafe", None, 0).await.unwrap();
+        let by_answer = store.search_questions("lifetimes", None, 0).await.unwrap();
+        let none = store.search_questions("javascript", None, 0).await.unwrap();
+
+        // assert
+        assert_eq!(by_title.len(), 1);
+        assert_eq!(by_title[0].title_snippet, "<mark>Borrow</mark> checker errors");
+        assert_eq!(by_title[0].answer_snippet.as_deref(), Some("Lifetimes tie references to the <mark>borrow</mark> scope."));
+        assert_eq!(by_answer.len(), 2);
+        assert_eq!(by_answer[0].question.id, lifetimes.id);
+        assert_eq!(by_answer[1].question.id, borrow.id);
+        assert!(none.is_empty());
+        assert_eq!(markup[0].title_snippet, "<mark>Unsafe</mark> &lt;b&gt;markup&lt;/b&gt;");
+        assert_eq!(markup[0].content_snippet, "&lt;img src=x onerror=alert(1)&gt; is <mark>unsafe</mark>");
+    }
+
+    #[tokio::test]
+    async fn questions_and_answers() {
+        // arrange
+        let store = InMemoryStore::new();
+        let owner = AccountId(1);
+        let other = AccountId(2);
+        let question = store.add_question(new_question("Question"), &owner).await.unwrap();
+        store.add_question(new_question("Another"), &owner).await.unwrap();
+
+        // act
+        let answer = store.add_answer(
+            NewAnswer { content: "Answer".to_string(), question_id: question.id.clone() },
+            &other,
+        ).await.unwrap();
+        let orphan = store.add_answer(
+            NewAnswer { content: "Orphan".to_string(), question_id: QuestionId(-1) },
+            &other,
+        ).await;
+
+        // assert
+        assert!(matches!(orphan, Err(handle_errors::Error::QuestionNotFound)));
+        assert_eq!(store.get_questions(&QuestionFilter::default(), &QuestionSort::default(), Some(1), 1).await.unwrap()[0].title, "Another");
+        assert_eq!(store.get_answers(question.id.0, AnswerSort::Id, None, 0).await.unwrap().len(), 1);
+        assert!(store.is_question_owner(question.id.0, &owner).await.unwrap());
+        assert!(!store.is_answer_owner(answer.id.0, &owner).await.unwrap());
+        assert_eq!(store.get_question_owner(question.id.0).await.unwrap(), Some(owner.clone()));
+        assert_eq!(store.get_answer_owner(answer.id.0).await.unwrap(), Some(other.clone()));
+        assert_eq!(store.get_answer_owner(-1).await.unwrap(), None);
+        assert!(store.update_question(question.id.0, question.clone(), &other).await.is_err());
+        assert!(store.update_answer(answer.id.0, answer.clone(), &owner).await.is_err());
+
+        store.delete_question(question.id.0, &other).await.unwrap();
+        assert!(store.get_question(question.id.0).await.unwrap().is_some());
+
+        store.delete_question(question.id.0, &owner).await.unwrap();
+        assert!(store.get_question(question.id.0).await.unwrap().is_none());
+        assert!(store.get_answer(answer.id.0).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn sessions() {
+        // arrange
+        let store = InMemoryStore::new();
+        let now = now();
+        let session = |id: &str, account_id| StoredSession { id: id.to_string(), account_id: AccountId(account_id) };
+        store.add_session(&session("a", 1), "hash-a", now + chrono::Duration::days(1)).await.unwrap();
+        store.add_session(&session("b", 1), "hash-b", now + chrono::Duration::days(1)).await.unwrap();
+        store.add_session(&session("c", 2), "hash-c", now + chrono::Duration::days(1)).await.unwrap();
+        store.add_session(&session("old", 1), "hash-old", now - chrono::Duration::days(1)).await.unwrap();
+
+        // act
+        let rotated = store.rotate_refresh_token("hash-a", "hash-a2", now).await.unwrap();
+        let reused = store.rotate_refresh_token("hash-a", "hash-a3", now).await.unwrap();
+        let expired = store.rotate_refresh_token("hash-old", "hash-old2", now).await.unwrap();
+        let revoked = store.revoke_session("a", now).await.unwrap();
+        let revoked_again = store.revoke_session("a", now).await.unwrap();
+        let revoked_all = store.revoke_sessions(&AccountId(1), now).await.unwrap();
+
+        // assert
+        assert_eq!(rotated, Some(session("a", 1)));
+        assert_eq!(reused, None);
+        assert_eq!(expired, None);
+        assert!(revoked);
+        assert!(!revoked_again);
+        assert_eq!(revoked_all, 1);
+        assert!(!store.is_session_active("a", now).await.unwrap());
+        assert!(!store.is_session_active("b", now).await.unwrap());
+        assert!(store.is_session_active("c", now).await.unwrap());
+        assert!(!store.is_session_active("old", now).await.unwrap());
+        assert!(!store.is_session_active("missing", now).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn profiles_and_authors() {
+        // arrange
+        let store = InMemoryStore::new();
+        store.add_account(Account {
+            id: None,
+            email: "jane@example.com".to_string(),
+            password: "hashed".to_string(),
+            role: Role::User,
+            email_verified: true,
+            profile: Profile::default(),
+        }).await.unwrap();
+        let jane = AccountId(1);
+        let question = store.add_question(new_question("Profiles"), &jane).await.unwrap();
+        store.add_answer(NewAnswer { content: "Answer".to_string(), question_id: question.id.clone() }, &jane).await.unwrap();
+        let profile = Profile {
+            display_name: Some("Jane".to_string()),
+            bio: Some("Rustacean".to_string()),
+            avatar_url: None,
+        };
+
+        // act
+        let updated = store.update_profile(&jane, &profile).await.unwrap();
+        let public = store.get_public_profile(&jane).await.unwrap();
+        let missing = store.get_public_profile(&AccountId(2)).await;
+        let fetched = store.get_question(question.id.0).await.unwrap().unwrap();
+        let answers = store.get_answers(question.id.0, AnswerSort::Id, None, 0).await.unwrap();
+
+        // assert
+        assert_eq!(question.author, Some(Author::new(jane.clone(), None)));
+        assert_eq!(updated.profile, profile);
+        assert_eq!(public.display_name, "Jane");
+        assert_eq!(public.bio.as_deref(), Some("Rustacean"));
+        assert_eq!((public.question_count, public.answer_count), (1, 1));
+        assert!(matches!(missing, Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound))));
+        assert_eq!(fetched.author, Some(Author::new(jane.clone(), Some("Jane".to_string()))));
+        assert_eq!(answers[0].author.as_ref().unwrap().display_name, "Jane");
+    }
+
+    #[tokio::test]
+    async fn account_deletion() {
+        // arrange
+        let store = InMemoryStore::new();
+        for email in ["anonymized@example.com", "deleted@example.com", "other@example.com"] {
+            store.add_account(Account {
+                id: None,
+                email: email.to_string(),
+                password: "hashed".to_string(),
+                role: Role::Moderator,
+                email_verified: true,
+                profile: Profile::default(),
+            }).await.unwrap();
+        }
+        let (anonymized, deleted, other) = (AccountId(1), AccountId(2), AccountId(3));
+        let kept = store.add_question(new_question("Kept"), &anonymized).await.unwrap();
+        let removed = store.add_question(new_question("Removed"), &deleted).await.unwrap();
+        let answer = |question: &Question| NewAnswer { content: "Answer".to_string(), question_id: question.id.clone() };
+        store.add_answer(answer(&kept), &deleted).await.unwrap();
+        store.add_answer(answer(&removed), &other).await.unwrap();
+        store.add_answer(answer(&kept), &other).await.unwrap();
+        let session = StoredSession { id: "a".to_string(), account_id: anonymized.clone() };
+        store.add_session(&session, "hash-a", now() + chrono::Duration::days(1)).await.unwrap();
+
+        // act
+        let was_anonymized = store.anonymize_account(&anonymized, "unusable").await.unwrap();
+        let was_deleted = store.delete_account(&deleted).await.unwrap();
+        let deleted_again = store.delete_account(&deleted).await.unwrap();
+
+        // assert
+        assert!(was_anonymized);
+        assert!(was_deleted);
+        assert!(!deleted_again);
+        let account = store.get_account_by_id(&anonymized).await.unwrap();
+        assert_eq!(account.email, "deleted-1@invalid");
+        assert_eq!(account.password, "unusable");
+        assert_eq!(account.role, Role::User);
+        assert!(store.get_account("anonymized@example.com".to_string()).await.is_err());
+        assert!(!store.is_session_active("a", now()).await.unwrap());
+        let question = store.get_question(kept.id.0).await.unwrap().unwrap();
+        assert_eq!(question.author.unwrap().display_name, DELETED_ACCOUNT_NAME);
+        assert!(store.get_question(removed.id.0).await.unwrap().is_none());
+        assert!(store.get_account_by_id(&deleted).await.is_err());
+        assert_eq!(store.get_answers(kept.id.0, AnswerSort::Id, None, 0).await.unwrap().len(), 1);
+        assert_eq!(store.get_answers_by_account(&other).await.unwrap().len(), 1);
+        assert_eq!(store.get_questions_by_account(&anonymized).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn api_keys() {
+        // arrange
+        let store = InMemoryStore::new();
+        let (owner, other) = (AccountId(1), AccountId(2));
+        let new_api_key = NewApiKey { name: "bot".to_string(), scopes: vec![Scope::QuestionsWrite] };
+        let api_key = store.add_api_key(&owner, &new_api_key, "qa_abc", "hash-a").await.unwrap();
+        let now = now();
+
+        // act
+        let used = store.use_api_key("hash-a", now).await.unwrap();
+        let unknown = store.use_api_key("hash-b", now).await.unwrap();
+        let revoked_by_other = store.revoke_api_key(&api_key.id, &other, now).await.unwrap();
+        let revoked = store.revoke_api_key(&api_key.id, &owner, now).await.unwrap();
+        let used_after_revocation = store.use_api_key("hash-a", now).await.unwrap();
+        let listed = store.get_api_keys(&owner).await.unwrap();
+
+        // assert
+        assert_eq!(used.unwrap().last_used_on, Some(now));
+        assert_eq!(unknown, None);
+        assert!(!revoked_by_other);
+        assert!(revoked);
+        assert_eq!(used_after_revocation, None);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].scopes, vec![Scope::QuestionsWrite]);
+        assert_eq!(listed[0].revoked_on, Some(now));
+        assert!(store.get_api_keys(&other).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn identities() {
+        // arrange
+        let store = InMemoryStore::new();
+        for email in ["linked@example.com", "other@example.com"] {
+            store.add_account(Account {
+                id: None,
+                email: email.to_string(),
+                password: "hashed".to_string(),
+                role: Role::User,
+                email_verified: true,
+                profile: Profile::default(),
+            }).await.unwrap();
+        }
+        let (linked, other) = (AccountId(1), AccountId(2));
+
+        // act
+        let before = store.get_account_by_identity("https://idp.example.com", "sub-1").await.unwrap();
+        store.link_identity(&linked, "https://idp.example.com", "sub-1").await.unwrap();
+        let found = store.get_account_by_identity("https://idp.example.com", "sub-1").await.unwrap();
+        let other_issuer = store.get_account_by_identity("https://other.example.com", "sub-1").await.unwrap();
+        let duplicate = store.link_identity(&other, "https://idp.example.com", "sub-1").await;
+        store.delete_account(&linked).await.unwrap();
+        let after_deletion = store.get_account_by_identity("https://idp.example.com", "sub-1").await.unwrap();
+
+        // assert
+        assert!(before.is_none());
+        assert_eq!(found.unwrap().email, "linked@example.com");
+        assert!(other_issuer.is_none());
+        assert!(matches!(duplicate, Err(handle_errors::Error::AccountAlreadyExists)));
+        assert!(after_deletion.is_none());
+    }
+
+    #[tokio::test]
+    async fn two_factor() {
+        // arrange
+        let store = InMemoryStore::new();
+        let account = AccountId(1);
+        let hashes = vec!["hash-a".to_string(), "hash-b".to_string()];
+
+        // act
+        let pending = store.set_two_factor_secret(&account, "SECRET").await.unwrap();
+        let unused_before_enabling = store.use_totp_step(&account, 10).await.unwrap();
+        let enabled = store.enable_two_factor(&account, 10, &hashes).await.unwrap();
+        let replaced_while_enabled = store.set_two_factor_secret(&account, "OTHER").await.unwrap();
+        let same_step = store.use_totp_step(&account, 10).await.unwrap();
+        let next_step = store.use_totp_step(&account, 11).await.unwrap();
+        let recovery_code = store.use_recovery_code(&account, "hash-a", now()).await.unwrap();
+        let recovery_code_again = store.use_recovery_code(&account, "hash-a", now()).await.unwrap();
+        let two_factor = store.get_two_factor(&account).await.unwrap();
+        let disabled = store.disable_two_factor(&account).await.unwrap();
+
+        // assert
+        assert!(pending);
+        assert!(!unused_before_enabling);
+        assert!(enabled);
+        assert!(!replaced_while_enabled);
+        assert!(!same_step);
+        assert!(next_step);
+        assert!(recovery_code);
+        assert!(!recovery_code_again);
+        assert_eq!(two_factor, Some(TwoFactor { secret: "SECRET".to_string(), enabled: true, last_used_step: Some(11) }));
+        assert!(disabled);
+        assert_eq!(store.get_two_factor(&account).await.unwrap(), None);
+        assert!(!store.use_recovery_code(&account, "hash-b", now()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn votes() {
+        // arrange
+        let store = InMemoryStore::new();
+        for email in ["owner@example.com", "voter@example.com", "other@example.com"] {
+            store.add_account(Account {
+                id: None,
+                email: email.to_string(),
+                password: "hashed".to_string(),
+                role: Role::User,
+                email_verified: true,
+                profile: Profile::default(),
+            }).await.unwrap();
+        }
+        let (owner, voter, other) = (AccountId(1), AccountId(2), AccountId(3));
+        let first = store.add_question(new_question("First"), &owner).await.unwrap();
+        let second = store.add_question(new_question("Second"), &owner).await.unwrap();
+        let old = store.add_answer(NewAnswer { content: "Old".to_string(), question_id: first.id.clone() }, &owner).await.unwrap();
+        let new = store.add_answer(NewAnswer { content: "New".to_string(), question_id: first.id.clone() }, &owner).await.unwrap();
+
+        // act
+        let up = store.vote_question(second.id.0, &voter, 1).await.unwrap();
+        let up_again = store.vote_question(second.id.0, &voter, 1).await.unwrap();
+        let other_up = store.vote_question(second.id.0, &other, 1).await.unwrap();
+        let switched = store.vote_question(second.id.0, &voter, -1).await.unwrap();
+        let cleared = store.vote_question(second.id.0, &voter, 0).await.unwrap();
+        let missing = store.vote_question(-1, &voter, 1).await.unwrap();
+        store.vote_question(first.id.0, &voter, -1).await.unwrap();
+        store.vote_answer(new.id.0, &voter, 1).await.unwrap();
+        let by_score = store.get_questions(&QuestionFilter::default(), &QuestionSort { field: SortField::Score, order: SortOrder::Desc }, None, 0).await.unwrap();
+        let answers = store.get_answers(first.id.0, AnswerSort::Score, None, 0).await.unwrap();
+        store.delete_account(&other).await.unwrap();
+
+        // assert
+        assert_eq!((up, up_again, other_up, switched, cleared, missing), (Some(1), Some(1), Some(2), Some(0), Some(1), None));
+        assert_eq!(by_score.iter().map(|q| (q.id.clone(), q.score)).collect::<Vec<_>>(), vec![(second.id.clone(), 1), (first.id.clone(), -1)]);
+        assert_eq!(answers.iter().map(|a| (a.id.clone(), a.score)).collect::<Vec<_>>(), vec![(new.id, 1), (old.id, 0)]);
+        assert_eq!(store.get_question(second.id.0).await.unwrap().unwrap().score, 0);
+    }
+
+    #[tokio::test]
+    async fn accepted_answers() {
+        // arrange
+        let store = InMemoryStore::new();
+        let owner = AccountId(1);
+        let solved = store.add_question(new_question("Solved"), &owner).await.unwrap();
+        let open = store.add_question(new_question("Open"), &owner).await.unwrap();
+        let answer = |question: &Question, content: &str| NewAnswer { content: content.to_string(), question_id: question.id.clone() };
+        let first = store.add_answer(answer(&solved, "First"), &owner).await.unwrap();
+        let second = store.add_answer(answer(&solved, "Second"), &owner).await.unwrap();
+        let elsewhere = store.add_answer(answer(&open, "Elsewhere"), &owner).await.unwrap();
+        let answered = QuestionFilter { answered: Some(true), ..QuestionFilter::default() };
+        let unanswered = QuestionFilter { answered: Some(false), ..QuestionFilter::default() };
+
+        // act
+        let wrong_question = store.accept_answer(solved.id.0, elsewhere.id.0).await.unwrap();
+        let accepted = store.accept_answer(solved.id.0, second.id.0).await.unwrap();
+        let answers = store.get_answers(solved.id.0, AnswerSort::Id, None, 0).await.unwrap();
+        let answered_questions = store.get_questions(&answered, &QuestionSort::default(), None, 0).await.unwrap();
+        let unanswered_questions = store.get_questions(&unanswered, &QuestionSort::default(), None, 0).await.unwrap();
+        let unaccept_other = store.unaccept_answer(solved.id.0, first.id.0).await.unwrap();
+        store.delete_answer(second.id.0, &owner).await.unwrap();
+
+        // assert
+        assert!(!wrong_question);
+        assert!(accepted);
+        assert_eq!(answers.iter().map(|a| a.id.clone()).collect::<Vec<_>>(), vec![second.id.clone(), first.id.clone()]);
+        assert_eq!(answered_questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>(), vec![solved.id.clone()]);
+        assert_eq!(unanswered_questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>(), vec![open.id.clone()]);
+        assert!(!unaccept_other);
+        assert_eq!(store.get_question(solved.id.0).await.unwrap().unwrap().accepted_answer_id, None);
+        assert!(store.accept_answer(solved.id.0, first.id.0).await.unwrap());
+        assert!(store.unaccept_answer(solved.id.0, first.id.0).await.unwrap());
+        assert_eq!(store.count_questions(&answered).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn comments() {
+        // arrange
+        let store = InMemoryStore::new();
+        for email in ["owner@example.com", "commenter@example.com"] {
+            store.add_account(Account {
+                id: None,
+                email: email.to_string(),
+                password: "hashed".to_string(),
+                role: Role::User,
+                email_verified: true,
+                profile: Profile::default(),
+            }).await.unwrap();
+        }
+        let (owner, commenter) = (AccountId(1), AccountId(2));
+        let solved = store.add_question(new_question("Solved"), &owner).await.unwrap();
+        let removed = store.add_question(new_question("Removed"), &owner).await.unwrap();
+        let answer = store.add_answer(NewAnswer { content: "Answer".to_string(), question_id: solved.id.clone() }, &owner).await.unwrap();
+        let on_question = CommentTarget::Question(solved.id.clone());
+        let on_answer = CommentTarget::Answer(answer.id.clone());
+        let first = store.add_comment(&on_question, "First".to_string(), &commenter).await.unwrap();
+        let second = store.add_comment(&on_question, "Second".to_string(), &owner).await.unwrap();
+        let answer_comment = store.add_comment(&on_answer, "On the answer".to_string(), &commenter).await.unwrap();
+        let gone = store.add_comment(&CommentTarget::Question(removed.id.clone()), "Gone".to_string(), &owner).await.unwrap();
+
+        // act
+        let listed = store.get_comments(&on_question, None, 0).await.unwrap();
+        let paged = store.get_comments(&on_question, Some(1), 1).await.unwrap();
+        let updated = store.update_comment(first.id.0, "Edited".to_string(), &commenter).await.unwrap();
+        let update_by_other = store.update_comment(first.id.0, "Hijacked".to_string(), &owner).await;
+        let by_commenter = store.get_comments_by_account(&commenter).await.unwrap();
+        let delete_by_other = store.delete_comment(answer_comment.id.0, &owner).await.unwrap();
+        let deleted = store.delete_comment(second.id.0, &owner).await.unwrap();
+        store.delete_question(removed.id.0, &owner).await.unwrap();
+        store.delete_account(&commenter).await.unwrap();
+
+        // assert
+        assert_eq!(first.target, on_question);
+        assert_eq!(first.author.id, commenter);
+        assert_eq!(listed.iter().map(|c| c.id.clone()).collect::<Vec<_>>(), vec![first.id.clone(), second.id.clone()]);
+        assert_eq!(paged.iter().map(|c| c.id.clone()).collect::<Vec<_>>(), vec![second.id.clone()]);
+        assert_eq!(updated.content, "Edited");
+        assert!(update_by_other.is_err());
+        assert_eq!(by_commenter.iter().map(|c| c.id.clone()).collect::<Vec<_>>(), vec![first.id.clone(), answer_comment.id.clone()]);
+        assert!(!delete_by_other);
+        assert!(deleted);
+        assert!(store.get_comment(gone.id.0).await.unwrap().is_none());
+        assert!(store.get_comments(&on_question, None, 0).await.unwrap().is_empty());
+        assert!(store.get_comments(&on_answer, None, 0).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+
+use crate::types::account::{Account, AccountId, Profile, PublicProfile, Role, StoredSession};
+use crate::types::question::{Question, NewQuestion, QuestionFilter, QuestionSort, SortOrder};
+use crate::types::pagination::Cursor;
+use crate::types::answer::{Answer, AnswerSort, NewAnswer};
+use crate::types::comment::{Comment, CommentTarget};
+use crate::types::api_key::{ApiKey, ApiKeyId, NewApiKey};
+use crate::types::search::QuestionSearchResult;
+use crate::types::tag::Tag;
+use crate::types::two_factor::TwoFactor;
+
+mod memory;
+mod postgres;
+
+pub use memory::InMemoryStore;
+pub use postgres::Store;
+
+/// Storage handed to every route handler, independent of the backend behind it.
+pub type DynStore = std::sync::Arc<dyn Repository>;
+
+#[async_trait]
+pub trait QuestionRepository: Send + Sync {
+    async fn get_questions(&self, filter: &QuestionFilter, sort: &QuestionSort, limit: Option<i32>, offset: i32) -> Result<Vec<Question>, handle_errors::Error>;
+    /// Up to `limit` questions strictly past `after` in `(created_on, id)`
+    /// order, scanning in `scan` order. The cursor's direction is ignored.
+    async fn get_questions_by_key(&self, filter: &QuestionFilter, scan: SortOrder, after: Option<&Cursor>, limit: Option<i32>) -> Result<Vec<Question>, handle_errors::Error>;
+    async fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, handle_errors::Error>;
+    async fn get_question(&self, id: i32) -> Result<Option<Question>, handle_errors::Error>;
+    /// Questions whose title, content or answers match `query`, best match first.
+    async fn search_questions(&self, query: &str, limit: Option<i32>, offset: i32) -> Result<Vec<QuestionSearchResult>, handle_errors::Error>;
+    async fn add_question(&self, new_question: NewQuestion, account_id: &AccountId) -> Result<Question, handle_errors::Error>;
+    async fn update_question(&self, id: i32, question: Question, account_id: &AccountId) -> Result<Question, handle_errors::Error>;
+    async fn delete_question(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error>;
+    async fn is_question_owner(&self, question_id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error>;
+    /// Account that asked the question, `None` if there is no such question.
+    async fn get_question_owner(&self, question_id: i32) -> Result<Option<AccountId>, handle_errors::Error>;
+    /// Every question asked by the account, oldest first.
+    async fn get_questions_by_account(&self, account_id: &AccountId) -> Result<Vec<Question>, handle_errors::Error>;
+    /// Marks the answer as the accepted one of the question, replacing an
+    /// earlier one. `false` if the answer does not belong to the question.
+    async fn accept_answer(&self, question_id: i32, answer_id: i32) -> Result<bool, handle_errors::Error>;
+    /// Clears the accepted answer of the question. `false` if `answer_id`
+    /// was not the accepted one.
+    async fn unaccept_answer(&self, question_id: i32, answer_id: i32) -> Result<bool, handle_errors::Error>;
+}
+
+#[async_trait]
+pub trait AnswerRepository: Send + Sync {
+    async fn add_answer(&self, new_answer: NewAnswer, account_id: &AccountId) -> Result<Answer, handle_errors::Error>;
+    /// Answers to the question, the accepted one first and the others in `sort` order.
+    async fn get_answers(&self, question_id: i32, sort: AnswerSort, limit: Option<i32>, offset: i32) -> Result<Vec<Answer>, handle_errors::Error>;
+    async fn get_answer(&self, id: i32) -> Result<Option<Answer>, handle_errors::Error>;
+    async fn update_answer(&self, id: i32, answer: Answer, account_id: &AccountId) -> Result<Answer, handle_errors::Error>;
+    async fn delete_answer(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error>;
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error>;
+    /// Account that wrote the answer, `None` if there is no such answer.
+    async fn get_answer_owner(&self, answer_id: i32) -> Result<Option<AccountId>, handle_errors::Error>;
+    /// Every answer written by the account, oldest first.
+    async fn get_answers_by_account(&self, account_id: &AccountId) -> Result<Vec<Answer>, handle_errors::Error>;
+}
+
+#[async_trait]
+pub trait AccountRepository: Send + Sync {
+    async fn add_account(&self, new_account: Account) -> Result<bool, handle_errors::Error>;
+    async fn get_account(&self, email: String) -> Result<Account, handle_errors::Error>;
+    async fn get_account_by_id(&self, account_id: &AccountId) -> Result<Account, handle_errors::Error>;
+    async fn get_accounts(&self) -> Result<Vec<Account>, handle_errors::Error>;
+    async fn set_account_role(&self, account_id: &AccountId, role: Role) -> Result<Account, handle_errors::Error>;
+    /// Replaces the password hash of the account.
+    async fn update_password(&self, account_id: &AccountId, password: &str) -> Result<(), handle_errors::Error>;
+    /// Marks `email` of the account as verified. `false` if the account does
+    /// not exist, has another address by now or was verified before.
+    async fn verify_email(&self, account_id: &AccountId, email: &str) -> Result<bool, handle_errors::Error>;
+    /// Replaces every profile field of the account.
+    async fn update_profile(&self, account_id: &AccountId, profile: &Profile) -> Result<Account, handle_errors::Error>;
+    /// Profile with the number of questions and answers written by the account.
+    async fn get_public_profile(&self, account_id: &AccountId) -> Result<PublicProfile, handle_errors::Error>;
+    /// Strips the e-mail address, profile, linked identities and second
+    /// factor from the account and ends its sessions. The password gives way
+    /// to `password`, a hash of a password nobody knows. Its questions,
+    /// answers and comments stay, under a placeholder name. `false` if there
+    /// is no such account.
+    async fn anonymize_account(&self, account_id: &AccountId, password: &str) -> Result<bool, handle_errors::Error>;
+    /// Deletes the account with its sessions, votes, comments, questions and
+    /// answers, including answers and comments others left on them. `false`
+    /// if there is no such account.
+    async fn delete_account(&self, account_id: &AccountId) -> Result<bool, handle_errors::Error>;
+    /// Account linked to `subject` at the OpenID Connect provider `issuer`.
+    async fn get_account_by_identity(&self, issuer: &str, subject: &str) -> Result<Option<Account>, handle_errors::Error>;
+    async fn link_identity(&self, account_id: &AccountId, issuer: &str, subject: &str) -> Result<(), handle_errors::Error>;
+}
+
+#[async_trait]
+pub trait TagRepository: Send + Sync {
+    /// Every tag in use with the number of questions carrying it, most used first.
+    async fn get_tags(&self) -> Result<Vec<Tag>, handle_errors::Error>;
+}
+
+/// Login sessions. Only a hash of each refresh token is kept.
+#[async_trait]
+pub trait SessionRepository: Send + Sync {
+    async fn add_session(&self, session: &StoredSession, refresh_token_hash: &str, expires_on: NaiveDateTime) -> Result<(), handle_errors::Error>;
+    /// Swaps the refresh token of the live session holding `refresh_token_hash`
+    /// for `new_refresh_token_hash`, so every refresh token works only once.
+    async fn rotate_refresh_token(&self, refresh_token_hash: &str, new_refresh_token_hash: &str, now: NaiveDateTime) -> Result<Option<StoredSession>, handle_errors::Error>;
+    /// Whether the session exists, has not expired and was not revoked.
+    async fn is_session_active(&self, id: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error>;
+    async fn revoke_session(&self, id: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error>;
+    /// Revokes every live session of the account, returning how many there were.
+    async fn revoke_sessions(&self, account_id: &AccountId, now: NaiveDateTime) -> Result<u64, handle_errors::Error>;
+}
+
+/// API keys of accounts. Only a hash of each key is kept.
+#[async_trait]
+pub trait ApiKeyRepository: Send + Sync {
+    async fn add_api_key(&self, account_id: &AccountId, new_api_key: &NewApiKey, prefix: &str, key_hash: &str) -> Result<ApiKey, handle_errors::Error>;
+    /// Every key of the account, revoked ones included, oldest first.
+    async fn get_api_keys(&self, account_id: &AccountId) -> Result<Vec<ApiKey>, handle_errors::Error>;
+    /// `false` if the account has no such live key.
+    async fn revoke_api_key(&self, id: &ApiKeyId, account_id: &AccountId, now: NaiveDateTime) -> Result<bool, handle_errors::Error>;
+    /// Live key with `key_hash`, recording `now` as its last use.
+    async fn use_api_key(&self, key_hash: &str, now: NaiveDateTime) -> Result<Option<ApiKey>, handle_errors::Error>;
+}
+
+/// TOTP secrets and recovery codes. Only a hash of each recovery code is kept.
+#[async_trait]
+pub trait TwoFactorRepository: Send + Sync {
+    async fn get_two_factor(&self, account_id: &AccountId) -> Result<Option<TwoFactor>, handle_errors::Error>;
+    /// Stores a new pending secret. `false` if two factors are enabled already.
+    async fn set_two_factor_secret(&self, account_id: &AccountId, secret: &str) -> Result<bool, handle_errors::Error>;
+    /// Enables the pending secret, whose code of `step` was just confirmed,
+    /// replacing the recovery codes. `false` if there is no pending secret.
+    async fn enable_two_factor(&self, account_id: &AccountId, step: i64, recovery_code_hashes: &[String]) -> Result<bool, handle_errors::Error>;
+    /// Removes the secret and the recovery codes. `false` if there was no secret.
+    async fn disable_two_factor(&self, account_id: &AccountId) -> Result<bool, handle_errors::Error>;
+    /// Records a code of `step` as used. `false` if a code of this or a later
+    /// step was used before, so every code works only once.
+    async fn use_totp_step(&self, account_id: &AccountId, step: i64) -> Result<bool, handle_errors::Error>;
+    /// Marks the unused recovery code with `code_hash` as used.
+    async fn use_recovery_code(&self, account_id: &AccountId, code_hash: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error>;
+}
+
+/// Votes on questions and answers, at most one per account on each. The
+/// sum of the votes is kept as the `score` of the question or answer.
+#[async_trait]
+pub trait VoteRepository: Send + Sync {
+    /// Replaces the account's vote on the question with `value`, 1, -1 or 0
+    /// to clear it. The new score, `None` if there is no such question.
+    async fn vote_question(&self, question_id: i32, account_id: &AccountId, value: i16) -> Result<Option<i32>, handle_errors::Error>;
+    /// Like `vote_question`, for an answer.
+    async fn vote_answer(&self, answer_id: i32, account_id: &AccountId, value: i16) -> Result<Option<i32>, handle_errors::Error>;
+}
+
+/// Comments on questions and answers, deleted along with what they are on.
+#[async_trait]
+pub trait CommentRepository: Send + Sync {
+    async fn add_comment(&self, target: &CommentTarget, content: String, account_id: &AccountId) -> Result<Comment, handle_errors::Error>;
+    /// Comments on the question or answer, oldest first.
+    async fn get_comments(&self, target: &CommentTarget, limit: Option<i32>, offset: i32) -> Result<Vec<Comment>, handle_errors::Error>;
+    async fn get_comment(&self, id: i32) -> Result<Option<Comment>, handle_errors::Error>;
+    async fn update_comment(&self, id: i32, content: String, account_id: &AccountId) -> Result<Comment, handle_errors::Error>;
+    async fn delete_comment(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error>;
+    /// Every comment written by the account, oldest first.
+    async fn get_comments_by_account(&self, account_id: &AccountId) -> Result<Vec<Comment>, handle_errors::Error>;
+}
+
+/// Everything the routes need from a storage backend.
+pub trait Repository: QuestionRepository + AnswerRepository + AccountRepository + TagRepository + SessionRepository + ApiKeyRepository + TwoFactorRepository + VoteRepository + CommentRepository {}
+
+impl<T> Repository for T where T: QuestionRepository + AnswerRepository + AccountRepository + TagRepository + SessionRepository + ApiKeyRepository + TwoFactorRepository + VoteRepository + CommentRepository {}
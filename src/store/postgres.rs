@@ -0,0 +1,2012 @@
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use sqlx::postgres::{PgPoolOptions, PgPool, PgRow, Postgres};
+use sqlx::{QueryBuilder, Row};
+
+use super::{AccountRepository, AnswerRepository, ApiKeyRepository, CommentRepository, QuestionRepository, SessionRepository, TagRepository, TwoFactorRepository, VoteRepository};
+
+use crate::types::account::{deleted_account_email, public_name, Account, AccountId, Author, Profile, PublicProfile, Role, StoredSession, DELETED_ACCOUNT_NAME};
+use crate::types::question::{Question, QuestionId, NewQuestion, QuestionFilter, QuestionSort, SortField, SortOrder, TagMatch};
+use crate::types::answer::{Answer, AnswerId, AnswerSort, NewAnswer};
+use crate::types::comment::{Comment, CommentId, CommentTarget};
+use crate::types::api_key::{ApiKey, ApiKeyId, NewApiKey, Scope};
+use crate::types::pagination::Cursor;
+use crate::types::search::{QuestionSearchResult, HIGHLIGHT_START, HIGHLIGHT_STOP, HTML_ESCAPES};
+use crate::types::tag::Tag;
+use crate::types::two_factor::TwoFactor;
+
+/// Table of votable rows, the table of their votes and its reference column.
+struct VoteTable {
+    items: &'static str,
+    votes: &'static str,
+    item_id: &'static str,
+}
+const QUESTION_VOTES: VoteTable = VoteTable { items: "questions", votes: "question_votes", item_id: "question_id" };
+const ANSWER_VOTES: VoteTable = VoteTable { items: "answers", votes: "answer_votes", item_id: "answer_id" };
+
+/// Columns `map_to_comment` reads.
+const COMMENT_COLUMNS: &str = "id, content, question_id, answer_id, account_id, created_on, \
+    (SELECT display_name FROM accounts WHERE accounts.id = comments.account_id) AS author_name";
+
+#[derive(Debug, Clone)]
+pub struct Store {
+    pub connection: PgPool,
+}
+impl Store {
+    pub async fn new(db_url: &str) -> Self {
+        let db_pool = match PgPoolOptions::new()
+            .max_connections(5)
+            .connect(db_url).await {
+                Ok(pool) => pool,
+                Err(_) => panic!("store::new Unable to connect to the Db"),
+            };
+
+        match sqlx::migrate!().run(&db_pool).await {
+            Ok(res) => tracing::event!(tracing::Level::INFO, "store::migrated success {:?}", res),
+            Err(e) => tracing::event!(tracing::Level::ERROR, "store::migrated error {:?}", e),
+        }
+
+        Store {
+            connection: db_pool,
+        }
+    }
+
+    async fn anonymize_account_rows(&self, account_id: &AccountId, password: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+        let updated = sqlx::query(
+            "UPDATE accounts SET email = $2, password = $4, role = 'user', email_verified = FALSE, \
+                display_name = $3, bio = NULL, avatar_url = NULL \
+            WHERE id = $1")
+            .bind(account_id.0)
+            .bind(deleted_account_email(account_id))
+            .bind(DELETED_ACCOUNT_NAME)
+            .bind(password)
+            .execute(&mut tx)
+            .await?;
+        for statement in [
+            "DELETE FROM sessions WHERE account_id = $1",
+            "DELETE FROM api_keys WHERE account_id = $1",
+            "DELETE FROM account_identities WHERE account_id = $1",
+            "DELETE FROM recovery_codes WHERE account_id = $1",
+            "DELETE FROM two_factor WHERE account_id = $1",
+        ] {
+            sqlx::query(statement)
+                .bind(account_id.0)
+                .execute(&mut tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    async fn delete_account_rows(&self, account_id: &AccountId) -> Result<bool, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+        // the account's votes no longer count, and votes on its questions and
+        // answers go with them through the foreign keys
+        for table in [QUESTION_VOTES, ANSWER_VOTES] {
+            sqlx::query(&format!(
+                "UPDATE {items} SET score = score - votes.value FROM {votes} votes \
+                WHERE votes.{item_id} = {items}.id AND votes.account_id = $1",
+                items = table.items, votes = table.votes, item_id = table.item_id,
+            ))
+                .bind(account_id.0)
+                .execute(&mut tx)
+                .await?;
+            sqlx::query(&format!("DELETE FROM {} WHERE account_id = $1", table.votes))
+                .bind(account_id.0)
+                .execute(&mut tx)
+                .await?;
+        }
+        // answers to the account's questions, and comments on both, go with
+        // them through the foreign keys
+        for statement in [
+            "DELETE FROM comments WHERE account_id = $1",
+            "DELETE FROM answers WHERE account_id = $1",
+            "DELETE FROM questions WHERE account_id = $1",
+            "DELETE FROM sessions WHERE account_id = $1",
+            "DELETE FROM api_keys WHERE account_id = $1",
+            "DELETE FROM account_identities WHERE account_id = $1",
+            "DELETE FROM recovery_codes WHERE account_id = $1",
+            "DELETE FROM two_factor WHERE account_id = $1",
+        ] {
+            sqlx::query(statement)
+                .bind(account_id.0)
+                .execute(&mut tx)
+                .await?;
+        }
+        let deleted = sqlx::query("DELETE FROM accounts WHERE id = $1")
+            .bind(account_id.0)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    async fn enable_two_factor_rows(&self, account_id: &AccountId, step: i64, recovery_code_hashes: &[String]) -> Result<bool, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+        let enabled = sqlx::query("UPDATE two_factor SET enabled = TRUE, last_used_step = $2 WHERE account_id = $1 AND NOT enabled")
+            .bind(account_id.0)
+            .bind(step)
+            .execute(&mut tx)
+            .await?;
+        if enabled.rows_affected() == 0 {
+            return Ok(false);
+        }
+        sqlx::query("DELETE FROM recovery_codes WHERE account_id = $1")
+            .bind(account_id.0)
+            .execute(&mut tx)
+            .await?;
+        sqlx::query("INSERT INTO recovery_codes (account_id, code_hash) SELECT $1, UNNEST($2::VARCHAR[])")
+            .bind(account_id.0)
+            .bind(recovery_code_hashes)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    async fn disable_two_factor_rows(&self, account_id: &AccountId) -> Result<bool, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+        sqlx::query("DELETE FROM recovery_codes WHERE account_id = $1")
+            .bind(account_id.0)
+            .execute(&mut tx)
+            .await?;
+        let deleted = sqlx::query("DELETE FROM two_factor WHERE account_id = $1")
+            .bind(account_id.0)
+            .execute(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(deleted.rows_affected() > 0)
+    }
+
+    /// Replaces the account's vote and moves the score by the difference.
+    /// The voted row stays locked until the end, so concurrent votes on it
+    /// can't lose updates.
+    async fn vote_rows(&self, table: VoteTable, id: i32, account_id: &AccountId, value: i16) -> Result<Option<i32>, sqlx::Error> {
+        let mut tx = self.connection.begin().await?;
+        let exists = sqlx::query(&format!("SELECT id FROM {} WHERE id = $1 FOR UPDATE", table.items))
+            .bind(id)
+            .fetch_optional(&mut tx)
+            .await?;
+        if exists.is_none() {
+            return Ok(None);
+        }
+        let previous = sqlx::query(&format!("DELETE FROM {} WHERE {} = $1 AND account_id = $2 RETURNING value", table.votes, table.item_id))
+            .bind(id)
+            .bind(account_id.0)
+            .map(|row: PgRow| row.get::<i16, _>("value"))
+            .fetch_optional(&mut tx)
+            .await?
+            .unwrap_or(0);
+        if value != 0 {
+            sqlx::query(&format!("INSERT INTO {} ({}, account_id, value) VALUES ($1, $2, $3)", table.votes, table.item_id))
+                .bind(id)
+                .bind(account_id.0)
+                .bind(value)
+                .execute(&mut tx)
+                .await?;
+        }
+        let score = sqlx::query(&format!("UPDATE {} SET score = score + $2 WHERE id = $1 RETURNING score", table.items))
+            .bind(id)
+            .bind(i32::from(value - previous))
+            .map(|row: PgRow| row.get::<i32, _>("score"))
+            .fetch_one(&mut tx)
+            .await?;
+        tx.commit().await?;
+        Ok(Some(score))
+    }
+}
+
+#[async_trait]
+impl QuestionRepository for Store {
+    async fn get_questions(&self, filter: &QuestionFilter, sort: &QuestionSort, limit: Option<i32>, offset: i32) -> Result<Vec<Question>, handle_errors::Error> {
+        let mut query = QueryBuilder::<Postgres>::new(
+            "SELECT *, (SELECT display_name FROM accounts WHERE accounts.id = questions.account_id) AS author_name FROM questions WHERE true"
+        );
+        push_question_filter(&mut query, filter);
+        let order = match sort.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        query.push(" ORDER BY ");
+        match sort.field {
+            SortField::Id => {},
+            SortField::CreatedOn => { query.push(format!("created_on {}, ", order)); },
+            SortField::Title => { query.push(format!("title {}, ", order)); },
+            SortField::Score => { query.push(format!("score {}, ", order)); },
+            SortField::AnswerCount => {
+                query.push(format!(
+                    "(SELECT COUNT(*) FROM answers WHERE answers.question_id = questions.id) {}, ",
+                    order
+                ));
+            },
+        }
+        query.push(format!("id {}", order));
+        query.push(" LIMIT ");
+        query.push_bind(limit);
+        query.push(" OFFSET ");
+        query.push_bind(offset);
+
+        match query.build()
+            .map(map_to_question)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(questions) => Ok(questions),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_questions {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_questions_by_key(&self, filter: &QuestionFilter, scan: SortOrder, after: Option<&Cursor>, limit: Option<i32>) -> Result<Vec<Question>, handle_errors::Error> {
+        let (comparison, order) = match scan {
+            SortOrder::Asc => (">", "ASC"),
+            SortOrder::Desc => ("<", "DESC"),
+        };
+        let mut query = QueryBuilder::<Postgres>::new(
+            "SELECT *, (SELECT display_name FROM accounts WHERE accounts.id = questions.account_id) AS author_name FROM questions WHERE true"
+        );
+        push_question_filter(&mut query, filter);
+        if let Some(after) = after {
+            query.push(format!(" AND (created_on, id) {} (", comparison));
+            query.push_bind(after.created_on);
+            query.push(", ");
+            query.push_bind(after.id);
+            query.push(")");
+        }
+        query.push(format!(" ORDER BY created_on {order}, id {order} LIMIT ", order = order));
+        query.push_bind(limit);
+
+        match query.build()
+            .map(map_to_question)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(questions) => Ok(questions),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_questions_by_key {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn count_questions(&self, filter: &QuestionFilter) -> Result<i64, handle_errors::Error> {
+        let mut query = QueryBuilder::<Postgres>::new("SELECT COUNT(*) FROM questions WHERE true");
+        push_question_filter(&mut query, filter);
+
+        match query.build()
+            .map(|row: PgRow| row.get::<i64, _>(0))
+            .fetch_one(&self.connection)
+            .await {
+                Ok(count) => Ok(count),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::count_questions {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_question(&self, id: i32) -> Result<Option<Question>, handle_errors::Error> {
+        match sqlx::query("SELECT *, (SELECT display_name FROM accounts WHERE accounts.id = questions.account_id) AS author_name FROM questions WHERE id = $1")
+            .bind(id)
+            .map(map_to_question)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(question) => Ok(question),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_question {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn search_questions(&self, query: &str, limit: Option<i32>, offset: i32) -> Result<Vec<QuestionSearchResult>, handle_errors::Error> {
+        let highlight = format!("StartSel={}, StopSel={}", HIGHLIGHT_START, HIGHLIGHT_STOP);
+        match sqlx::query(&format!(
+            "SELECT q.id, q.title, q.content, q.tags, q.created_on, q.account_id, q.score, q.accepted_answer_id, \
+                (SELECT display_name FROM accounts WHERE accounts.id = q.account_id) AS author_name, \
+                (ts_rank(q.search_vector, query) + COALESCE(a.rank, 0) * 0.5)::real AS rank, \
+                ts_headline('english', {}, query, $2 || ', HighlightAll=true') AS title_snippet, \
+                ts_headline('english', {}, query, $2) AS content_snippet, \
+                ts_headline('english', {}, query, $2) AS answer_snippet \
+            FROM questions q \
+            CROSS JOIN websearch_to_tsquery('english', $1) AS query \
+            LEFT JOIN LATERAL ( \
+                SELECT content, ts_rank(search_vector, query) AS rank FROM answers \
+                WHERE question_id = q.id AND search_vector @@ query \
+                ORDER BY rank DESC LIMIT 1 \
+            ) a ON true \
+            WHERE q.search_vector @@ query OR a.content IS NOT NULL \
+            ORDER BY rank DESC, q.id \
+            LIMIT $3 OFFSET $4",
+            escape_html_sql("q.title"),
+            escape_html_sql("q.content"),
+            escape_html_sql("a.content"),
+        ))
+            .bind(query)
+            .bind(highlight)
+            .bind(limit)
+            .bind(offset)
+            .map(map_to_search_result)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(results) => Ok(results),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::search_questions {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn add_question(&self, new_question: NewQuestion, account_id: &AccountId) -> Result<Question, handle_errors::Error> {
+        match sqlx::query("INSERT INTO questions (title, content, tags, account_id) VALUES ($1, $2, $3, $4) RETURNING id, title, content, tags, created_on, account_id, score, accepted_answer_id, (SELECT display_name FROM accounts WHERE accounts.id = questions.account_id) AS author_name")
+            .bind(new_question.title)
+            .bind(new_question.content)
+            .bind(new_question.tags)
+            .bind(account_id.0)
+            .map(map_to_question)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(question) => Ok(question),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::add_question {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn update_question(&self, id: i32, question: Question, account_id: &AccountId) -> Result<Question, handle_errors::Error> {
+        match sqlx::query("UPDATE questions SET title = $1, content = $2, tags = $3 WHERE id = $4 AND account_id = $5 RETURNING id, title, content, tags, created_on, account_id, score, accepted_answer_id, (SELECT display_name FROM accounts WHERE accounts.id = questions.account_id) AS author_name")
+            .bind(question.title)
+            .bind(question.content)
+            .bind(question.tags)
+            .bind(id)
+            .bind(account_id.0)
+            .map(map_to_question)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(question) => Ok(question),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::update_question {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn delete_question(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("DELETE FROM questions WHERE id = $1 AND account_id =$2")
+            .bind(id)
+            .bind(account_id.0)
+            .execute(&self.connection)
+            .await {
+                Ok(_) => Ok(true),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::delete_question {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn is_question_owner(&self, question_id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("SELECT id FROM questions WHERE id = $1 and account_id = $2")
+            .bind(question_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(question) => Ok(question.is_some()),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::is_question_owner {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_question_owner(&self, question_id: i32) -> Result<Option<AccountId>, handle_errors::Error> {
+        match sqlx::query("SELECT account_id FROM questions WHERE id = $1")
+            .bind(question_id)
+            .map(|row: PgRow| AccountId(row.get("account_id")))
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(owner) => Ok(owner),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_question_owner {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_questions_by_account(&self, account_id: &AccountId) -> Result<Vec<Question>, handle_errors::Error> {
+        match sqlx::query("SELECT *, (SELECT display_name FROM accounts WHERE accounts.id = questions.account_id) AS author_name FROM questions WHERE account_id = $1 ORDER BY id")
+            .bind(account_id.0)
+            .map(map_to_question)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(questions) => Ok(questions),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_questions_by_account {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn accept_answer(&self, question_id: i32, answer_id: i32) -> Result<bool, handle_errors::Error> {
+        match sqlx::query(
+            "UPDATE questions SET accepted_answer_id = $2 \
+            WHERE id = $1 AND EXISTS (SELECT 1 FROM answers WHERE answers.id = $2 AND answers.question_id = $1)")
+            .bind(question_id)
+            .bind(answer_id)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::accept_answer {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn unaccept_answer(&self, question_id: i32, answer_id: i32) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("UPDATE questions SET accepted_answer_id = NULL WHERE id = $1 AND accepted_answer_id = $2")
+            .bind(question_id)
+            .bind(answer_id)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::unaccept_answer {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+}
+
+#[async_trait]
+impl AnswerRepository for Store {
+    async fn add_answer(&self, new_answer: NewAnswer, account_id: &AccountId) -> Result<Answer, handle_errors::Error> {
+        match sqlx::query("INSERT INTO answers (content, question_id, account_id) VALUES ($1, $2, $3) RETURNING id, content, question_id, account_id, score, (SELECT display_name FROM accounts WHERE accounts.id = answers.account_id) AS author_name")
+            .bind(new_answer.content)
+            .bind(new_answer.question_id.0)
+            .bind(account_id.0)
+            .map(map_to_answer)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(answer) => Ok(answer),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::add_answer {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn get_answers(&self, question_id: i32, sort: AnswerSort, limit: Option<i32>, offset: i32) -> Result<Vec<Answer>, handle_errors::Error> {
+        let order = match sort {
+            AnswerSort::Id => "id",
+            AnswerSort::Score => "score DESC, id",
+        };
+        match sqlx::query(&format!(
+            "SELECT *, (SELECT display_name FROM accounts WHERE accounts.id = answers.account_id) AS author_name FROM answers WHERE question_id = $1 \
+            ORDER BY id IS NOT DISTINCT FROM (SELECT accepted_answer_id FROM questions WHERE id = $1) DESC, {} LIMIT $2 OFFSET $3",
+            order
+        ))
+            .bind(question_id)
+            .bind(limit)
+            .bind(offset)
+            .map(map_to_answer)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(answers) => Ok(answers),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_answers {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_answer(&self, id: i32) -> Result<Option<Answer>, handle_errors::Error> {
+        match sqlx::query("SELECT *, (SELECT display_name FROM accounts WHERE accounts.id = answers.account_id) AS author_name FROM answers WHERE id = $1")
+            .bind(id)
+            .map(map_to_answer)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(answer) => Ok(answer),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_answer {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn update_answer(&self, id: i32, answer: Answer, account_id: &AccountId) -> Result<Answer, handle_errors::Error> {
+        match sqlx::query("UPDATE answers SET content = $1 WHERE id = $2 AND account_id = $3 RETURNING id, content, question_id, account_id, score, (SELECT display_name FROM accounts WHERE accounts.id = answers.account_id) AS author_name")
+            .bind(answer.content)
+            .bind(id)
+            .bind(account_id.0)
+            .map(map_to_answer)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(answer) => Ok(answer),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::update_answer {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn delete_answer(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("DELETE FROM answers WHERE id = $1 AND account_id = $2")
+            .bind(id)
+            .bind(account_id.0)
+            .execute(&self.connection)
+            .await {
+                Ok(_) => Ok(true),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::delete_answer {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn is_answer_owner(&self, answer_id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("SELECT id FROM answers WHERE id = $1 and account_id = $2")
+            .bind(answer_id)
+            .bind(account_id.0)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(answer) => Ok(answer.is_some()),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::is_answer_owner {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_answer_owner(&self, answer_id: i32) -> Result<Option<AccountId>, handle_errors::Error> {
+        match sqlx::query("SELECT account_id FROM answers WHERE id = $1")
+            .bind(answer_id)
+            .map(|row: PgRow| AccountId(row.get("account_id")))
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(owner) => Ok(owner),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_answer_owner {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_answers_by_account(&self, account_id: &AccountId) -> Result<Vec<Answer>, handle_errors::Error> {
+        match sqlx::query("SELECT *, (SELECT display_name FROM accounts WHERE accounts.id = answers.account_id) AS author_name FROM answers WHERE account_id = $1 ORDER BY id")
+            .bind(account_id.0)
+            .map(map_to_answer)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(answers) => Ok(answers),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_answers_by_account {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+}
+
+#[async_trait]
+impl AccountRepository for Store {
+    async fn add_account(&self, new_account: Account) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("INSERT INTO accounts (email, password) VALUES ($1, $2)")
+            .bind(new_account.email)
+            .bind(new_account.password)
+            .execute(&self.connection)
+            .await {
+                Ok(_) => Ok(true),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::add_account {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_account(&self, email: String) -> Result<Account, handle_errors::Error> {
+        match sqlx::query("SELECT * FROM accounts WHERE LOWER(email) = LOWER($1)")
+            .bind(email)
+            .map(map_to_account)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(account) => Ok(account),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_account {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_account_by_id(&self, account_id: &AccountId) -> Result<Account, handle_errors::Error> {
+        match sqlx::query("SELECT * FROM accounts WHERE id = $1")
+            .bind(account_id.0)
+            .map(map_to_account)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(account) => Ok(account),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_account_by_id {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_accounts(&self) -> Result<Vec<Account>, handle_errors::Error> {
+        match sqlx::query("SELECT * FROM accounts ORDER BY id")
+            .map(map_to_account)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(accounts) => Ok(accounts),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_accounts {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn update_password(&self, account_id: &AccountId, password: &str) -> Result<(), handle_errors::Error> {
+        match sqlx::query("UPDATE accounts SET password = $1 WHERE id = $2")
+            .bind(password)
+            .bind(account_id.0)
+            .execute(&self.connection)
+            .await {
+                Ok(result) if result.rows_affected() > 0 => Ok(()),
+                Ok(_) => Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound)),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::update_password {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn verify_email(&self, account_id: &AccountId, email: &str) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("UPDATE accounts SET email_verified = TRUE WHERE id = $1 AND email = $2 AND NOT email_verified")
+            .bind(account_id.0)
+            .bind(email)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::verify_email {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn set_account_role(&self, account_id: &AccountId, role: Role) -> Result<Account, handle_errors::Error> {
+        match sqlx::query("UPDATE accounts SET role = $1 WHERE id = $2 RETURNING *")
+            .bind(role.as_str())
+            .bind(account_id.0)
+            .map(map_to_account)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(account) => Ok(account),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::set_account_role {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn update_profile(&self, account_id: &AccountId, profile: &Profile) -> Result<Account, handle_errors::Error> {
+        match sqlx::query("UPDATE accounts SET display_name = $1, bio = $2, avatar_url = $3 WHERE id = $4 RETURNING *")
+            .bind(&profile.display_name)
+            .bind(&profile.bio)
+            .bind(&profile.avatar_url)
+            .bind(account_id.0)
+            .map(map_to_account)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(account) => Ok(account),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::update_profile {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_public_profile(&self, account_id: &AccountId) -> Result<PublicProfile, handle_errors::Error> {
+        match sqlx::query(
+            "SELECT id, display_name, bio, avatar_url, \
+                (SELECT COUNT(*) FROM questions WHERE questions.account_id = accounts.id) AS question_count, \
+                (SELECT COUNT(*) FROM answers WHERE answers.account_id = accounts.id) AS answer_count \
+            FROM accounts WHERE id = $1")
+            .bind(account_id.0)
+            .map(map_to_public_profile)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(profile) => Ok(profile),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_public_profile {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn anonymize_account(&self, account_id: &AccountId, password: &str) -> Result<bool, handle_errors::Error> {
+        match self.anonymize_account_rows(account_id, password).await {
+            Ok(anonymized) => Ok(anonymized),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "store::anonymize_account {:?}", e);
+                Err(handle_errors::Error::DatabaseQueryError(e))
+            }
+        }
+    }
+    async fn delete_account(&self, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        match self.delete_account_rows(account_id).await {
+            Ok(deleted) => Ok(deleted),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "store::delete_account {:?}", e);
+                Err(handle_errors::Error::DatabaseQueryError(e))
+            }
+        }
+    }
+    async fn get_account_by_identity(&self, issuer: &str, subject: &str) -> Result<Option<Account>, handle_errors::Error> {
+        match sqlx::query(
+            "SELECT accounts.* FROM accounts \
+            JOIN account_identities ON account_identities.account_id = accounts.id \
+            WHERE account_identities.issuer = $1 AND account_identities.subject = $2")
+            .bind(issuer)
+            .bind(subject)
+            .map(map_to_account)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(account) => Ok(account),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_account_by_identity {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn link_identity(&self, account_id: &AccountId, issuer: &str, subject: &str) -> Result<(), handle_errors::Error> {
+        match sqlx::query("INSERT INTO account_identities (issuer, subject, account_id) VALUES ($1, $2, $3)")
+            .bind(issuer)
+            .bind(subject)
+            .bind(account_id.0)
+            .execute(&self.connection)
+            .await {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::link_identity {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+}
+
+#[async_trait]
+impl SessionRepository for Store {
+    async fn add_session(&self, session: &StoredSession, refresh_token_hash: &str, expires_on: NaiveDateTime) -> Result<(), handle_errors::Error> {
+        match sqlx::query("INSERT INTO sessions (id, account_id, refresh_token_hash, expires_on) VALUES ($1, $2, $3, $4)")
+            .bind(&session.id)
+            .bind(session.account_id.0)
+            .bind(refresh_token_hash)
+            .bind(expires_on)
+            .execute(&self.connection)
+            .await {
+                Ok(_) => Ok(()),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::add_session {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn rotate_refresh_token(&self, refresh_token_hash: &str, new_refresh_token_hash: &str, now: NaiveDateTime) -> Result<Option<StoredSession>, handle_errors::Error> {
+        match sqlx::query(
+            "UPDATE sessions SET refresh_token_hash = $1 \
+             WHERE refresh_token_hash = $2 AND revoked_on IS NULL AND expires_on > $3 \
+             RETURNING id, account_id"
+        )
+            .bind(new_refresh_token_hash)
+            .bind(refresh_token_hash)
+            .bind(now)
+            .map(map_to_session)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(session) => Ok(session),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::rotate_refresh_token {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn is_session_active(&self, id: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("SELECT id FROM sessions WHERE id = $1 AND revoked_on IS NULL AND expires_on > $2")
+            .bind(id)
+            .bind(now)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(session) => Ok(session.is_some()),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::is_session_active {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn revoke_session(&self, id: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("UPDATE sessions SET revoked_on = $1 WHERE id = $2 AND revoked_on IS NULL")
+            .bind(now)
+            .bind(id)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::revoke_session {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn revoke_sessions(&self, account_id: &AccountId, now: NaiveDateTime) -> Result<u64, handle_errors::Error> {
+        match sqlx::query("UPDATE sessions SET revoked_on = $1 WHERE account_id = $2 AND revoked_on IS NULL AND expires_on > $1")
+            .bind(now)
+            .bind(account_id.0)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected()),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::revoke_sessions {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+}
+
+#[async_trait]
+impl TagRepository for Store {
+    async fn get_tags(&self) -> Result<Vec<Tag>, handle_errors::Error> {
+        match sqlx::query(
+            "SELECT tag AS name, COUNT(DISTINCT id) AS question_count \
+            FROM questions, unnest(tags) AS tag \
+            GROUP BY tag ORDER BY question_count DESC, name")
+            .map(map_to_tag)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(tags) => Ok(tags),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_tags {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+}
+
+#[async_trait]
+impl ApiKeyRepository for Store {
+    async fn add_api_key(&self, account_id: &AccountId, new_api_key: &NewApiKey, prefix: &str, key_hash: &str) -> Result<ApiKey, handle_errors::Error> {
+        match sqlx::query("INSERT INTO api_keys (account_id, name, prefix, key_hash, scopes) VALUES ($1, $2, $3, $4, $5) RETURNING *")
+            .bind(account_id.0)
+            .bind(&new_api_key.name)
+            .bind(prefix)
+            .bind(key_hash)
+            .bind(new_api_key.scopes.iter().map(Scope::as_str).collect::<Vec<_>>())
+            .map(map_to_api_key)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(api_key) => Ok(api_key),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::add_api_key {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_api_keys(&self, account_id: &AccountId) -> Result<Vec<ApiKey>, handle_errors::Error> {
+        match sqlx::query("SELECT * FROM api_keys WHERE account_id = $1 ORDER BY id")
+            .bind(account_id.0)
+            .map(map_to_api_key)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(api_keys) => Ok(api_keys),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_api_keys {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn revoke_api_key(&self, id: &ApiKeyId, account_id: &AccountId, now: NaiveDateTime) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("UPDATE api_keys SET revoked_on = $3 WHERE id = $1 AND account_id = $2 AND revoked_on IS NULL")
+            .bind(id.0)
+            .bind(account_id.0)
+            .bind(now)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::revoke_api_key {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn use_api_key(&self, key_hash: &str, now: NaiveDateTime) -> Result<Option<ApiKey>, handle_errors::Error> {
+        match sqlx::query("UPDATE api_keys SET last_used_on = $2 WHERE key_hash = $1 AND revoked_on IS NULL RETURNING *")
+            .bind(key_hash)
+            .bind(now)
+            .map(map_to_api_key)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(api_key) => Ok(api_key),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::use_api_key {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+}
+
+#[async_trait]
+impl TwoFactorRepository for Store {
+    async fn get_two_factor(&self, account_id: &AccountId) -> Result<Option<TwoFactor>, handle_errors::Error> {
+        match sqlx::query("SELECT * FROM two_factor WHERE account_id = $1")
+            .bind(account_id.0)
+            .map(|row: PgRow| TwoFactor {
+                secret: row.get("secret"),
+                enabled: row.get("enabled"),
+                last_used_step: row.get("last_used_step"),
+            })
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(two_factor) => Ok(two_factor),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_two_factor {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn set_two_factor_secret(&self, account_id: &AccountId, secret: &str) -> Result<bool, handle_errors::Error> {
+        match sqlx::query(
+            "INSERT INTO two_factor (account_id, secret) VALUES ($1, $2) \
+            ON CONFLICT (account_id) DO UPDATE SET secret = EXCLUDED.secret, last_used_step = NULL \
+            WHERE NOT two_factor.enabled")
+            .bind(account_id.0)
+            .bind(secret)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::set_two_factor_secret {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn enable_two_factor(&self, account_id: &AccountId, step: i64, recovery_code_hashes: &[String]) -> Result<bool, handle_errors::Error> {
+        match self.enable_two_factor_rows(account_id, step, recovery_code_hashes).await {
+            Ok(enabled) => Ok(enabled),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "store::enable_two_factor {:?}", e);
+                Err(handle_errors::Error::DatabaseQueryError(e))
+            }
+        }
+    }
+    async fn disable_two_factor(&self, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        match self.disable_two_factor_rows(account_id).await {
+            Ok(disabled) => Ok(disabled),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "store::disable_two_factor {:?}", e);
+                Err(handle_errors::Error::DatabaseQueryError(e))
+            }
+        }
+    }
+    async fn use_totp_step(&self, account_id: &AccountId, step: i64) -> Result<bool, handle_errors::Error> {
+        match sqlx::query(
+            "UPDATE two_factor SET last_used_step = $2 \
+            WHERE account_id = $1 AND enabled AND (last_used_step IS NULL OR last_used_step < $2)")
+            .bind(account_id.0)
+            .bind(step)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::use_totp_step {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn use_recovery_code(&self, account_id: &AccountId, code_hash: &str, now: NaiveDateTime) -> Result<bool, handle_errors::Error> {
+        match sqlx::query(
+            "UPDATE recovery_codes SET used_on = $3 \
+            WHERE id = (SELECT id FROM recovery_codes WHERE account_id = $1 AND code_hash = $2 AND used_on IS NULL LIMIT 1)")
+            .bind(account_id.0)
+            .bind(code_hash)
+            .bind(now)
+            .execute(&self.connection)
+            .await {
+                Ok(result) => Ok(result.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::use_recovery_code {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+}
+
+#[async_trait]
+impl VoteRepository for Store {
+    async fn vote_question(&self, question_id: i32, account_id: &AccountId, value: i16) -> Result<Option<i32>, handle_errors::Error> {
+        match self.vote_rows(QUESTION_VOTES, question_id, account_id, value).await {
+            Ok(score) => Ok(score),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "store::vote_question {:?}", e);
+                Err(handle_errors::Error::DatabaseQueryError(e))
+            }
+        }
+    }
+    async fn vote_answer(&self, answer_id: i32, account_id: &AccountId, value: i16) -> Result<Option<i32>, handle_errors::Error> {
+        match self.vote_rows(ANSWER_VOTES, answer_id, account_id, value).await {
+            Ok(score) => Ok(score),
+            Err(e) => {
+                tracing::event!(tracing::Level::ERROR, "store::vote_answer {:?}", e);
+                Err(handle_errors::Error::DatabaseQueryError(e))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl CommentRepository for Store {
+    async fn add_comment(&self, target: &CommentTarget, content: String, account_id: &AccountId) -> Result<Comment, handle_errors::Error> {
+        let (column, id) = comment_target_column(target);
+        match sqlx::query(&format!("INSERT INTO comments (content, {}, account_id) VALUES ($1, $2, $3) RETURNING {}", column, COMMENT_COLUMNS))
+            .bind(content)
+            .bind(id)
+            .bind(account_id.0)
+            .map(map_to_comment)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(comment) => Ok(comment),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::add_comment {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn get_comments(&self, target: &CommentTarget, limit: Option<i32>, offset: i32) -> Result<Vec<Comment>, handle_errors::Error> {
+        let (column, id) = comment_target_column(target);
+        match sqlx::query(&format!("SELECT {} FROM comments WHERE {} = $1 ORDER BY id LIMIT $2 OFFSET $3", COMMENT_COLUMNS, column))
+            .bind(id)
+            .bind(limit)
+            .bind(offset)
+            .map(map_to_comment)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(comments) => Ok(comments),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_comments {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn get_comment(&self, id: i32) -> Result<Option<Comment>, handle_errors::Error> {
+        match sqlx::query(&format!("SELECT {} FROM comments WHERE id = $1", COMMENT_COLUMNS))
+            .bind(id)
+            .map(map_to_comment)
+            .fetch_optional(&self.connection)
+            .await {
+                Ok(comment) => Ok(comment),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_comment {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+    async fn update_comment(&self, id: i32, content: String, account_id: &AccountId) -> Result<Comment, handle_errors::Error> {
+        match sqlx::query(&format!("UPDATE comments SET content = $1 WHERE id = $2 AND account_id = $3 RETURNING {}", COMMENT_COLUMNS))
+            .bind(content)
+            .bind(id)
+            .bind(account_id.0)
+            .map(map_to_comment)
+            .fetch_one(&self.connection)
+            .await {
+                Ok(comment) => Ok(comment),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::update_comment {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn delete_comment(&self, id: i32, account_id: &AccountId) -> Result<bool, handle_errors::Error> {
+        match sqlx::query("DELETE FROM comments WHERE id = $1 AND account_id = $2")
+            .bind(id)
+            .bind(account_id.0)
+            .execute(&self.connection)
+            .await {
+                Ok(res) => Ok(res.rows_affected() > 0),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::delete_comment {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                },
+            }
+    }
+    async fn get_comments_by_account(&self, account_id: &AccountId) -> Result<Vec<Comment>, handle_errors::Error> {
+        match sqlx::query(&format!("SELECT {} FROM comments WHERE account_id = $1 ORDER BY id", COMMENT_COLUMNS))
+            .bind(account_id.0)
+            .map(map_to_comment)
+            .fetch_all(&self.connection)
+            .await {
+                Ok(comments) => Ok(comments),
+                Err(e) => {
+                    tracing::event!(tracing::Level::ERROR, "store::get_comments_by_account {:?}", e);
+                    Err(handle_errors::Error::DatabaseQueryError(e))
+                }
+            }
+    }
+}
+
+/// Column of the comments table referencing `target`, with the id it holds.
+fn comment_target_column(target: &CommentTarget) -> (&'static str, i32) {
+    match target {
+        CommentTarget::Question(id) => ("question_id", id.0),
+        CommentTarget::Answer(id) => ("answer_id", id.0),
+    }
+}
+
+fn push_question_filter(query: &mut QueryBuilder<Postgres>, filter: &QuestionFilter) {
+    if !filter.tags.is_empty() {
+        query.push(match filter.tag_match {
+            TagMatch::Any => " AND tags && ",
+            TagMatch::All => " AND tags @> ",
+        });
+        query.push_bind(filter.tags.clone());
+    }
+    if let Some(start) = filter.start {
+        query.push(" AND created_on >= ");
+        query.push_bind(start);
+    }
+    if let Some(end) = filter.end {
+        query.push(" AND created_on <= ");
+        query.push_bind(end);
+    }
+    match filter.answered {
+        Some(true) => { query.push(" AND accepted_answer_id IS NOT NULL"); },
+        Some(false) => { query.push(" AND accepted_answer_id IS NULL"); },
+        None => {},
+    }
+}
+
+fn map_to_question(row: PgRow) -> Question {
+    Question {
+        id: QuestionId(row.get("id")),
+        title: row.get("title"),
+        content: row.get("content"),
+        tags: row.get("tags"),
+        created_on: row.get("created_on"),
+        score: row.get("score"),
+        accepted_answer_id: row.get::<Option<i32>, _>("accepted_answer_id").map(AnswerId),
+        author: Some(map_to_author(&row)),
+    }
+}
+
+/// SQL expression escaping `column` like `escape_html`, so `ts_headline`
+/// only adds markup of its own.
+fn escape_html_sql(column: &str) -> String {
+    HTML_ESCAPES.iter().fold(column.to_string(), |expression, (special, entity)| {
+        format!("replace({}, '{}', '{}')", expression, special.to_string().replace('\'', "''"), entity)
+    })
+}
+
+fn map_to_search_result(row: PgRow) -> QuestionSearchResult {
+    QuestionSearchResult {
+        question: Question {
+            id: QuestionId(row.get("id")),
+            title: row.get("title"),
+            content: row.get("content"),
+            tags: row.get("tags"),
+            created_on: row.get("created_on"),
+            score: row.get("score"),
+            accepted_answer_id: row.get::<Option<i32>, _>("accepted_answer_id").map(AnswerId),
+            author: Some(map_to_author(&row)),
+        },
+        rank: row.get("rank"),
+        title_snippet: row.get("title_snippet"),
+        content_snippet: row.get("content_snippet"),
+        answer_snippet: row.get("answer_snippet"),
+    }
+}
+
+fn map_to_answer(row: PgRow) -> Answer {
+    Answer {
+        id: AnswerId(row.get("id")),
+        content: row.get("content"),
+        question_id: QuestionId(row.get("question_id")),
+        score: row.get("score"),
+        author: Some(map_to_author(&row)),
+    }
+}
+
+fn map_to_comment(row: PgRow) -> Comment {
+    let question_id: Option<i32> = row.get("question_id");
+    let target = match question_id {
+        Some(id) => CommentTarget::question(id),
+        None => CommentTarget::answer(row.get("answer_id")),
+    };
+    Comment {
+        id: CommentId(row.get("id")),
+        content: row.get("content"),
+        target,
+        created_on: row.get("created_on"),
+        author: map_to_author(&row),
+    }
+}
+
+/// Reads `account_id` and the `author_name` subquery of a question, answer or comment row.
+fn map_to_author(row: &PgRow) -> Author {
+    Author::new(AccountId(row.get("account_id")), row.get("author_name"))
+}
+
+fn map_to_api_key(row: PgRow) -> ApiKey {
+    ApiKey {
+        id: ApiKeyId(row.get("id")),
+        account_id: AccountId(row.get("account_id")),
+        name: row.get("name"),
+        prefix: row.get("prefix"),
+        scopes: row
+            .get::<Vec<String>, _>("scopes")
+            .iter()
+            .filter_map(|scope| scope.parse().ok())
+            .collect(),
+        created_on: row.get("created_on"),
+        last_used_on: row.get("last_used_on"),
+        revoked_on: row.get("revoked_on"),
+    }
+}
+
+fn map_to_tag(row: PgRow) -> Tag {
+    Tag {
+        name: row.get("name"),
+        question_count: row.get("question_count"),
+    }
+}
+
+fn map_to_session(row: PgRow) -> StoredSession {
+    StoredSession {
+        id: row.get("id"),
+        account_id: AccountId(row.get("account_id")),
+    }
+}
+
+fn map_to_account(row: PgRow) -> Account {
+    Account {
+        id: Some(AccountId(row.get("id"))),
+        email: row.get("email"),
+        password: row.get("password"),
+        role: row.get::<String, _>("role").parse().unwrap_or_default(),
+        email_verified: row.get("email_verified"),
+        profile: Profile {
+            display_name: row.get("display_name"),
+            bio: row.get("bio"),
+            avatar_url: row.get("avatar_url"),
+        },
+    }
+}
+
+fn map_to_public_profile(row: PgRow) -> PublicProfile {
+    let id = AccountId(row.get("id"));
+    PublicProfile {
+        display_name: public_name(&id, row.get("display_name")),
+        id,
+        bio: row.get("bio"),
+        avatar_url: row.get("avatar_url"),
+        question_count: row.get("question_count"),
+        answer_count: row.get("answer_count"),
+    }
+}
+
+
+#[cfg(test)]
+mod store_tests {
+    //! Runs every `Store` method against a throwaway Postgres database.
+    //!
+    //! Point `TEST_DATABASE_URL` at a server the tests may create databases on,
+    //! e.g. `postgres://postgres@localhost:5432/postgres`. Each test migrates
+    //! its own fresh database and drops it afterwards. The tests are ignored
+    //! by default, run them with `cargo test -- --ignored`.
+    use super::*;
+
+    async fn with_store<F, Fut>(test: F)
+    where
+        F: FnOnce(Store) -> Fut,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let admin_url = std::env::var("TEST_DATABASE_URL")
+            .expect("TEST_DATABASE_URL must point at a Postgres server");
+        let admin = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&admin_url)
+            .await
+            .expect("Unable to connect to TEST_DATABASE_URL");
+
+        let db_name = format!("web_questions_test_{}", uuid::Uuid::new_v4().simple());
+        sqlx::query(&format!("CREATE DATABASE {}", db_name))
+            .execute(&admin)
+            .await
+            .unwrap();
+
+        let (server_url, _) = admin_url.rsplit_once('/').unwrap();
+        let store = Store::new(&format!("{}/{}", server_url, db_name)).await;
+
+        let result = tokio::spawn(test(store.clone())).await;
+
+        store.connection.close().await;
+        sqlx::query(&format!("DROP DATABASE {} WITH (FORCE)", db_name))
+            .execute(&admin)
+            .await
+            .unwrap();
+
+        if let Err(e) = result {
+            std::panic::resume_unwind(e.into_panic());
+        }
+    }
+
+    async fn create_account(store: &Store, email: &str) -> AccountId {
+        store.add_account(Account {
+            id: None,
+            email: email.to_string(),
+            password: "hashed".to_string(),
+            role: Role::User,
+            email_verified: false,
+            profile: Profile::default(),
+        }).await.unwrap();
+
+        store.get_account(email.to_string()).await.unwrap().id.unwrap()
+    }
+
+    fn new_question(title: &str) -> NewQuestion {
+        NewQuestion {
+            title: title.to_string(),
+            content: "How do I test this?".to_string(),
+            tags: Some(vec!["rust".to_string()]),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn accounts() {
+        with_store(|store| async move {
+            // arrange
+            let account = Account {
+                id: None,
+                email: "test@example.com".to_string(),
+                password: "hashed".to_string(),
+                role: Role::Admin,
+                email_verified: true,
+                profile: Profile::default(),
+            };
+
+            // act
+            let added = store.add_account(account.clone()).await.unwrap();
+            let duplicate = store.add_account(account).await;
+            let fetched = store.get_account("test@example.com".to_string()).await.unwrap();
+            let missing = store.get_account("nobody@example.com".to_string()).await;
+            let promoted = store.set_account_role(fetched.id.as_ref().unwrap(), Role::Moderator).await.unwrap();
+            let unknown = store.set_account_role(&AccountId(-1), Role::Moderator).await;
+            let accounts = store.get_accounts().await.unwrap();
+
+            // assert
+            assert!(added);
+            assert!(matches!(duplicate, Err(handle_errors::Error::DatabaseQueryError(_))));
+            assert!(fetched.id.is_some());
+            assert_eq!(fetched.email, "test@example.com");
+            assert_eq!(fetched.password, "hashed");
+            assert_eq!(fetched.role, Role::User);
+            assert!(!fetched.email_verified);
+            assert!(missing.is_err());
+            let id = fetched.id.as_ref().unwrap();
+            assert!(!store.verify_email(id, "other@example.com").await.unwrap());
+            assert!(store.verify_email(id, "test@example.com").await.unwrap());
+            assert!(!store.verify_email(id, "test@example.com").await.unwrap());
+            assert!(store.get_account_by_id(id).await.unwrap().email_verified);
+            store.update_password(id, "rehashed").await.unwrap();
+            assert_eq!(store.get_account_by_id(id).await.unwrap().password, "rehashed");
+            assert!(store.update_password(&AccountId(-1), "rehashed").await.is_err());
+            assert_eq!(promoted.role, Role::Moderator);
+            assert_eq!(accounts.len(), 1);
+            assert_eq!(accounts[0].role, Role::Moderator);
+            assert!(matches!(unknown, Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound))));
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn questions() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let other = create_account(&store, "other@example.com").await;
+
+            // act
+            let first = store.add_question(new_question("First"), &owner).await.unwrap();
+            let second = store.add_question(new_question("Second"), &owner).await.unwrap();
+
+            // assert
+            assert_eq!(first.title, "First");
+            assert_eq!(first.tags, Some(vec!["rust".to_string()]));
+
+            let all = store.get_questions(&QuestionFilter::default(), &QuestionSort::default(), None, 0).await.unwrap();
+            assert_eq!(all.len(), 2);
+            let page = store.get_questions(&QuestionFilter::default(), &QuestionSort::default(), Some(1), 1).await.unwrap();
+            assert_eq!(page.len(), 1);
+            assert_eq!(page[0].id, second.id);
+
+            let fetched = store.get_question(first.id.0).await.unwrap().unwrap();
+            assert_eq!(fetched.content, "How do I test this?");
+            assert!(store.get_question(-1).await.unwrap().is_none());
+
+            assert!(store.is_question_owner(first.id.0, &owner).await.unwrap());
+            assert!(!store.is_question_owner(first.id.0, &other).await.unwrap());
+            assert_eq!(store.get_question_owner(first.id.0).await.unwrap(), Some(owner.clone()));
+            assert_eq!(store.get_question_owner(-1).await.unwrap(), None);
+
+            let updated = store.update_question(
+                first.id.0,
+                Question {
+                    id: first.id.clone(),
+                    title: "First, edited".to_string(),
+                    content: "Edited".to_string(),
+                    tags: None,
+                    created_on: None,
+                    score: 0,
+                    accepted_answer_id: None,
+                    author: None,
+                },
+                &owner,
+            ).await.unwrap();
+            assert_eq!(updated.title, "First, edited");
+            assert_eq!(updated.tags, None);
+
+            let not_owned = store.update_question(first.id.0, updated.clone(), &other).await;
+            assert!(not_owned.is_err());
+
+            assert!(store.delete_question(second.id.0, &owner).await.unwrap());
+            assert!(store.get_question(second.id.0).await.unwrap().is_none());
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn tags() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let tagged = |title: &str, tags: &[&str]| NewQuestion {
+                title: title.to_string(),
+                content: "content".to_string(),
+                tags: Some(tags.iter().map(|t| t.to_string()).collect()),
+            };
+            let rust = store.add_question(tagged("Rust", &["rust"]), &owner).await.unwrap();
+            let both = store.add_question(tagged("Both", &["rust", "warp"]), &owner).await.unwrap();
+            store.add_question(tagged("Go", &["go"]), &owner).await.unwrap();
+            store.add_question(NewQuestion { tags: None, ..tagged("Untagged", &[]) }, &owner).await.unwrap();
+            let any = QuestionFilter {
+                tags: vec!["rust".to_string(), "warp".to_string()],
+                tag_match: TagMatch::Any,
+                ..QuestionFilter::default()
+            };
+            let all = QuestionFilter { tag_match: TagMatch::All, ..any.clone() };
+
+            // act
+            let any_questions = store.get_questions(&any, &QuestionSort::default(), None, 0).await.unwrap();
+            let all_questions = store.get_questions(&all, &QuestionSort::default(), None, 0).await.unwrap();
+            let tags = store.get_tags().await.unwrap();
+
+            // assert
+            assert_eq!(any_questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>(), vec![rust.id, both.id.clone()]);
+            assert_eq!(all_questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>(), vec![both.id]);
+            assert_eq!(tags, vec![
+                Tag { name: "rust".to_string(), question_count: 2 },
+                Tag { name: "go".to_string(), question_count: 1 },
+                Tag { name: "warp".to_string(), question_count: 1 },
+            ]);
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn sorting_and_date_range() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let b = store.add_question(new_question("B"), &owner).await.unwrap();
+            let c = store.add_question(new_question("C"), &owner).await.unwrap();
+            let a = store.add_question(new_question("A"), &owner).await.unwrap();
+            store.add_answer(NewAnswer { content: "1".to_string(), question_id: c.id.clone() }, &owner).await.unwrap();
+            store.add_answer(NewAnswer { content: "2".to_string(), question_id: c.id.clone() }, &owner).await.unwrap();
+            store.add_answer(NewAnswer { content: "3".to_string(), question_id: a.id.clone() }, &owner).await.unwrap();
+            sqlx::query("UPDATE questions SET created_on = '2023-03-01 10:00:00' WHERE id = $1")
+                .bind(b.id.0)
+                .execute(&store.connection)
+                .await
+                .unwrap();
+            let ids = |questions: Vec<Question>| questions.into_iter().map(|q| q.id).collect::<Vec<_>>();
+            let sort = |field, order| QuestionSort { field, order };
+            let no_filter = QuestionFilter::default();
+
+            // act
+            let by_title = store.get_questions(&no_filter, &sort(SortField::Title, SortOrder::Asc), None, 0).await.unwrap();
+            let by_answers = store.get_questions(&no_filter, &sort(SortField::AnswerCount, SortOrder::Desc), None, 0).await.unwrap();
+            let by_created = store.get_questions(&no_filter, &sort(SortField::CreatedOn, SortOrder::Desc), None, 0).await.unwrap();
+            let in_march = store.get_questions(
+                &QuestionFilter::new(&[
+                    ("start".to_string(), "2023-03-01".to_string()),
+                    ("end".to_string(), "2023-03-01".to_string()),
+                ]).unwrap(),
+                &QuestionSort::default(),
+                None,
+                0,
+            ).await.unwrap();
+
+            // assert
+            assert!(a.created_on.is_some());
+            assert_eq!(ids(by_title), vec![a.id.clone(), b.id.clone(), c.id.clone()]);
+            assert_eq!(ids(by_answers), vec![c.id.clone(), a.id.clone(), b.id.clone()]);
+            assert_eq!(ids(by_created), vec![a.id, c.id, b.id.clone()]);
+            assert_eq!(ids(in_march), vec![b.id]);
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn keyset_pagination() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let mut ids = Vec::new();
+            for title in ["1", "2", "3", "4"] {
+                ids.push(store.add_question(new_question(title), &owner).await.unwrap().id);
+            }
+            // same created_on for the middle two, so the id has to break the tie
+            sqlx::query("UPDATE questions SET created_on = '2023-03-01 10:00:00' WHERE id = $1 OR id = $2")
+                .bind(ids[1].0)
+                .bind(ids[2].0)
+                .execute(&store.connection)
+                .await
+                .unwrap();
+            sqlx::query("UPDATE questions SET created_on = '2023-03-01 09:00:00' WHERE id = $1")
+                .bind(ids[0].0)
+                .execute(&store.connection)
+                .await
+                .unwrap();
+            let no_filter = QuestionFilter::default();
+            let key = |q: &Question| Cursor {
+                created_on: q.created_on.unwrap(),
+                id: q.id.0,
+                direction: crate::types::pagination::Direction::Next,
+            };
+
+            // act
+            let first = store.get_questions_by_key(&no_filter, SortOrder::Asc, None, Some(2)).await.unwrap();
+            let second = store.get_questions_by_key(&no_filter, SortOrder::Asc, Some(&key(&first[1])), Some(2)).await.unwrap();
+            let backwards = store.get_questions_by_key(&no_filter, SortOrder::Desc, Some(&key(&second[0])), None).await.unwrap();
+            let total = store.count_questions(&no_filter).await.unwrap();
+            let tagged = store.count_questions(&QuestionFilter::with_tag("go".to_string())).await.unwrap();
+
+            // assert
+            let page_ids = |questions: &[Question]| questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>();
+            assert_eq!(page_ids(&first), vec![ids[0].clone(), ids[1].clone()]);
+            assert_eq!(page_ids(&second), vec![ids[2].clone(), ids[3].clone()]);
+            assert_eq!(page_ids(&backwards), vec![ids[1].clone(), ids[0].clone()]);
+            assert_eq!(total, 4);
+            assert_eq!(tagged, 0);
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn search() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let borrow = store.add_question(NewQuestion {
+                title: "Borrow checker errors".to_string(),
+                content: "Why does the compiler reject my references?".to_string(),
+                tags: None,
+            }, &owner).await.unwrap();
+            let lifetimes = store.add_question(NewQuestion {
+                title: "Lifetimes in structs".to_string(),
+                content: "When do I need explicit lifetimes?".to_string(),
+                tags: None,
+            }, &owner).await.unwrap();
+            store.add_question(new_question("Unrelated"), &owner).await.unwrap();
+            store.add_answer(NewAnswer {
+                content: "Lifetimes tie references to the borrow scope.".to_string(),
+                question_id: borrow.id.clone(),
+            }, &owner).await.unwrap();
+            store.add_question(NewQuestion {
+                title: "Unsafe <b>markup</b>".to_string(),
+                content: "<img src=x onerror=alert(1)> is unsafe".to_string(),
+                tags: None,
+            }, &owner).await.unwrap();
+
+            // act
+            let by_title = store.search_questions("borrowing", None, 0).await.unwrap();
+            let by_answer = store.search_questions("lifetimes", None, 0).await.unwrap();
+            let none = store.search_questions("javascript", None, 0).await.unwrap();
+            let paged = store.search_questions("lifetimes", Some(1), 1).await.unwrap();
+            let markup = store.search_questions("unsafe", None, 0).await.unwrap();
+
+            // assert
+            assert_eq!(by_title.len(), 1);
+            assert_eq!(by_title[0].question.id, borrow.id);
+            assert_eq!(by_title[0].title_snippet, "<mark>Borrow</mark> checker errors");
+            assert_eq!(by_title[0].answer_snippet.as_deref(), Some("Lifetimes tie references to the <mark>borrow</mark> scope."));
+
+            assert_eq!(by_answer.len(), 2);
+            assert_eq!(by_answer[0].question.id, lifetimes.id);
+            assert_eq!(by_answer[1].question.id, borrow.id);
+            assert_eq!(markup[0].title_snippet, "<mark>Unsafe</mark> &lt;b&gt;markup&lt;/b&gt;");
+            assert_eq!(markup[0].content_snippet, "&lt;img src=x onerror=alert(1)&gt; is <mark>unsafe</mark>");
+            assert!(by_answer[0].rank > by_answer[1].rank);
+            assert!(by_answer[0].content_snippet.contains("<mark>lifetimes</mark>"));
+
+            assert!(none.is_empty());
+            assert_eq!(paged.len(), 1);
+            assert_eq!(paged[0].question.id, borrow.id);
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn answers() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let other = create_account(&store, "other@example.com").await;
+            let question = store.add_question(new_question("Question"), &owner).await.unwrap();
+
+            // act
+            let first = store.add_answer(
+                NewAnswer { content: "First answer".to_string(), question_id: question.id.clone() },
+                &other,
+            ).await.unwrap();
+            let second = store.add_answer(
+                NewAnswer { content: "Second answer".to_string(), question_id: question.id.clone() },
+                &other,
+            ).await.unwrap();
+
+            // assert
+            assert_eq!(first.content, "First answer");
+            assert_eq!(first.question_id, question.id);
+
+            let orphan = store.add_answer(
+                NewAnswer { content: "Orphan".to_string(), question_id: QuestionId(-1) },
+                &other,
+            ).await;
+            assert!(orphan.is_err());
+
+            let all = store.get_answers(question.id.0, AnswerSort::Id, None, 0).await.unwrap();
+            assert_eq!(all.len(), 2);
+            let page = store.get_answers(question.id.0, AnswerSort::Id, Some(1), 1).await.unwrap();
+            assert_eq!(page.len(), 1);
+            assert_eq!(page[0].id, second.id);
+
+            let fetched = store.get_answer(first.id.0).await.unwrap().unwrap();
+            assert_eq!(fetched.content, "First answer");
+            assert!(store.get_answer(-1).await.unwrap().is_none());
+
+            assert!(store.is_answer_owner(first.id.0, &other).await.unwrap());
+            assert!(!store.is_answer_owner(first.id.0, &owner).await.unwrap());
+            assert_eq!(store.get_answer_owner(first.id.0).await.unwrap(), Some(other.clone()));
+
+            let updated = store.update_answer(
+                first.id.0,
+                Answer { content: "Edited".to_string(), ..first.clone() },
+                &other,
+            ).await.unwrap();
+            assert_eq!(updated.content, "Edited");
+            assert!(store.update_answer(first.id.0, updated, &owner).await.is_err());
+
+            assert!(store.delete_answer(second.id.0, &other).await.unwrap());
+            assert!(store.get_answer(second.id.0).await.unwrap().is_none());
+
+            store.delete_question(question.id.0, &owner).await.unwrap();
+            assert!(store.get_answer(first.id.0).await.unwrap().is_none());
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn sessions() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let other = create_account(&store, "other@example.com").await;
+            let now = chrono::Utc::now().naive_utc();
+            let session = |id: &str, account_id: &AccountId| StoredSession { id: id.to_string(), account_id: account_id.clone() };
+            store.add_session(&session("a", &owner), "hash-a", now + chrono::Duration::days(1)).await.unwrap();
+            store.add_session(&session("b", &owner), "hash-b", now + chrono::Duration::days(1)).await.unwrap();
+            store.add_session(&session("c", &other), "hash-c", now + chrono::Duration::days(1)).await.unwrap();
+            store.add_session(&session("old", &owner), "hash-old", now - chrono::Duration::days(1)).await.unwrap();
+
+            // act
+            let rotated = store.rotate_refresh_token("hash-a", "hash-a2", now).await.unwrap();
+            let reused = store.rotate_refresh_token("hash-a", "hash-a3", now).await.unwrap();
+            let expired = store.rotate_refresh_token("hash-old", "hash-old2", now).await.unwrap();
+            let revoked = store.revoke_session("a", now).await.unwrap();
+            let revoked_again = store.revoke_session("a", now).await.unwrap();
+            let revoked_all = store.revoke_sessions(&owner, now).await.unwrap();
+
+            // assert
+            assert_eq!(rotated, Some(session("a", &owner)));
+            assert_eq!(reused, None);
+            assert_eq!(expired, None);
+            assert!(revoked);
+            assert!(!revoked_again);
+            assert_eq!(revoked_all, 1);
+            assert!(!store.is_session_active("a", now).await.unwrap());
+            assert!(!store.is_session_active("b", now).await.unwrap());
+            assert!(store.is_session_active("c", now).await.unwrap());
+            assert!(!store.is_session_active("old", now).await.unwrap());
+            assert_eq!(store.get_account_by_id(&other).await.unwrap().email, "other@example.com");
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn profiles_and_authors() {
+        with_store(|store| async move {
+            // arrange
+            let jane = create_account(&store, "jane@example.com").await;
+            let question = store.add_question(new_question("Profiles"), &jane).await.unwrap();
+            store.add_answer(NewAnswer { content: "Answer".to_string(), question_id: question.id.clone() }, &jane).await.unwrap();
+            let profile = Profile {
+                display_name: Some("Jane".to_string()),
+                bio: Some("Rustacean".to_string()),
+                avatar_url: Some("https://example.com/jane.png".to_string()),
+            };
+
+            // act
+            let updated = store.update_profile(&jane, &profile).await.unwrap();
+            let public = store.get_public_profile(&jane).await.unwrap();
+            let missing = store.get_public_profile(&AccountId(-1)).await;
+            let fetched = store.get_question(question.id.0).await.unwrap().unwrap();
+            let listed = store.get_questions(&QuestionFilter::default(), &QuestionSort::default(), None, 0).await.unwrap();
+            let answers = store.get_answers(question.id.0, AnswerSort::Id, None, 0).await.unwrap();
+
+            // assert
+            assert_eq!(question.author, Some(Author::new(jane.clone(), None)));
+            assert_eq!(updated.profile, profile);
+            assert_eq!(public.display_name, "Jane");
+            assert_eq!(public.avatar_url, profile.avatar_url);
+            assert_eq!((public.question_count, public.answer_count), (1, 1));
+            assert!(matches!(missing, Err(handle_errors::Error::DatabaseQueryError(sqlx::Error::RowNotFound))));
+            assert_eq!(fetched.author, Some(Author::new(jane.clone(), Some("Jane".to_string()))));
+            assert_eq!(listed[0].author, fetched.author);
+            assert_eq!(answers[0].author.as_ref().unwrap().display_name, "Jane");
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn account_deletion() {
+        with_store(|store| async move {
+            // arrange
+            let anonymized = create_account(&store, "anonymized@example.com").await;
+            let deleted = create_account(&store, "deleted@example.com").await;
+            let other = create_account(&store, "other@example.com").await;
+            let kept = store.add_question(new_question("Kept"), &anonymized).await.unwrap();
+            let removed = store.add_question(new_question("Removed"), &deleted).await.unwrap();
+            let answer = |question: &Question| NewAnswer { content: "Answer".to_string(), question_id: question.id.clone() };
+            store.add_answer(answer(&kept), &deleted).await.unwrap();
+            store.add_answer(answer(&removed), &other).await.unwrap();
+            store.add_answer(answer(&kept), &other).await.unwrap();
+            let now = chrono::Utc::now().naive_utc();
+            let session = StoredSession { id: "a".to_string(), account_id: anonymized.clone() };
+            store.add_session(&session, "hash-a", now + chrono::Duration::days(1)).await.unwrap();
+
+            // act
+            let was_anonymized = store.anonymize_account(&anonymized, "unusable").await.unwrap();
+            let was_deleted = store.delete_account(&deleted).await.unwrap();
+            let deleted_again = store.delete_account(&deleted).await.unwrap();
+
+            // assert
+            assert!(was_anonymized);
+            assert!(was_deleted);
+            assert!(!deleted_again);
+            let account = store.get_account_by_id(&anonymized).await.unwrap();
+            assert_eq!(account.email, deleted_account_email(&anonymized));
+            assert_eq!(account.password, "unusable");
+            assert!(store.get_account("anonymized@example.com".to_string()).await.is_err());
+            assert!(!store.is_session_active("a", now).await.unwrap());
+            let question = store.get_question(kept.id.0).await.unwrap().unwrap();
+            assert_eq!(question.author.unwrap().display_name, DELETED_ACCOUNT_NAME);
+            assert!(store.get_question(removed.id.0).await.unwrap().is_none());
+            assert!(store.get_account_by_id(&deleted).await.is_err());
+            assert_eq!(store.get_answers(kept.id.0, AnswerSort::Id, None, 0).await.unwrap().len(), 1);
+            assert_eq!(store.get_answers_by_account(&other).await.unwrap().len(), 1);
+            assert_eq!(store.get_questions_by_account(&anonymized).await.unwrap().len(), 1);
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn api_keys() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let other = create_account(&store, "other@example.com").await;
+            let new_api_key = NewApiKey { name: "bot".to_string(), scopes: vec![Scope::QuestionsWrite, Scope::AnswersRead] };
+            let api_key = store.add_api_key(&owner, &new_api_key, "qa_abc", "hash-a").await.unwrap();
+            let now = chrono::Utc::now().naive_utc();
+
+            // act
+            let used = store.use_api_key("hash-a", now).await.unwrap();
+            let unknown = store.use_api_key("hash-b", now).await.unwrap();
+            let revoked_by_other = store.revoke_api_key(&api_key.id, &other, now).await.unwrap();
+            let revoked = store.revoke_api_key(&api_key.id, &owner, now).await.unwrap();
+            let used_after_revocation = store.use_api_key("hash-a", now).await.unwrap();
+            let listed = store.get_api_keys(&owner).await.unwrap();
+
+            // assert
+            assert_eq!(api_key.last_used_on, None);
+            assert!(used.unwrap().last_used_on.is_some());
+            assert_eq!(unknown, None);
+            assert!(!revoked_by_other);
+            assert!(revoked);
+            assert_eq!(used_after_revocation, None);
+            assert_eq!(listed.len(), 1);
+            assert_eq!(listed[0].scopes, vec![Scope::QuestionsWrite, Scope::AnswersRead]);
+            assert!(listed[0].revoked_on.is_some());
+            assert!(store.get_api_keys(&other).await.unwrap().is_empty());
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn identities() {
+        with_store(|store| async move {
+            // arrange
+            let linked = create_account(&store, "linked@example.com").await;
+            let other = create_account(&store, "other@example.com").await;
+
+            // act
+            let before = store.get_account_by_identity("https://idp.example.com", "sub-1").await.unwrap();
+            store.link_identity(&linked, "https://idp.example.com", "sub-1").await.unwrap();
+            let found = store.get_account_by_identity("https://idp.example.com", "sub-1").await.unwrap();
+            let other_issuer = store.get_account_by_identity("https://other.example.com", "sub-1").await.unwrap();
+            let duplicate = store.link_identity(&other, "https://idp.example.com", "sub-1").await;
+            store.anonymize_account(&linked, "unusable").await.unwrap();
+            let after_anonymizing = store.get_account_by_identity("https://idp.example.com", "sub-1").await.unwrap();
+
+            // assert
+            assert!(before.is_none());
+            assert_eq!(found.unwrap().email, "linked@example.com");
+            assert!(other_issuer.is_none());
+            assert_eq!(duplicate.unwrap_err().code(), "conflict");
+            assert!(after_anonymizing.is_none());
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn two_factor() {
+        with_store(|store| async move {
+            // arrange
+            let account = create_account(&store, "2fa@example.com").await;
+            let hashes = vec!["hash-a".to_string(), "hash-b".to_string()];
+            let now = chrono::Utc::now().naive_utc();
+
+            // act
+            let pending = store.set_two_factor_secret(&account, "SECRET").await.unwrap();
+            let replaced = store.set_two_factor_secret(&account, "NEWER").await.unwrap();
+            let unused_before_enabling = store.use_totp_step(&account, 10).await.unwrap();
+            let enabled = store.enable_two_factor(&account, 10, &hashes).await.unwrap();
+            let enabled_again = store.enable_two_factor(&account, 10, &hashes).await.unwrap();
+            let replaced_while_enabled = store.set_two_factor_secret(&account, "OTHER").await.unwrap();
+            let same_step = store.use_totp_step(&account, 10).await.unwrap();
+            let next_step = store.use_totp_step(&account, 11).await.unwrap();
+            let recovery_code = store.use_recovery_code(&account, "hash-a", now).await.unwrap();
+            let recovery_code_again = store.use_recovery_code(&account, "hash-a", now).await.unwrap();
+            let two_factor = store.get_two_factor(&account).await.unwrap();
+            store.delete_account(&account).await.unwrap();
+
+            // assert
+            assert!(pending);
+            assert!(replaced);
+            assert!(!unused_before_enabling);
+            assert!(enabled);
+            assert!(!enabled_again);
+            assert!(!replaced_while_enabled);
+            assert!(!same_step);
+            assert!(next_step);
+            assert!(recovery_code);
+            assert!(!recovery_code_again);
+            assert_eq!(two_factor, Some(TwoFactor { secret: "NEWER".to_string(), enabled: true, last_used_step: Some(11) }));
+            assert_eq!(store.get_two_factor(&account).await.unwrap(), None);
+            assert!(!store.use_recovery_code(&account, "hash-b", now).await.unwrap());
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn votes() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let voter = create_account(&store, "voter@example.com").await;
+            let other = create_account(&store, "other@example.com").await;
+            let first = store.add_question(new_question("First"), &owner).await.unwrap();
+            let second = store.add_question(new_question("Second"), &owner).await.unwrap();
+            let old = store.add_answer(NewAnswer { content: "Old".to_string(), question_id: first.id.clone() }, &owner).await.unwrap();
+            let new = store.add_answer(NewAnswer { content: "New".to_string(), question_id: first.id.clone() }, &owner).await.unwrap();
+
+            // act
+            let up = store.vote_question(second.id.0, &voter, 1).await.unwrap();
+            let up_again = store.vote_question(second.id.0, &voter, 1).await.unwrap();
+            let other_up = store.vote_question(second.id.0, &other, 1).await.unwrap();
+            let switched = store.vote_question(second.id.0, &voter, -1).await.unwrap();
+            let cleared = store.vote_question(second.id.0, &voter, 0).await.unwrap();
+            let missing = store.vote_question(-1, &voter, 1).await.unwrap();
+            store.vote_question(first.id.0, &voter, -1).await.unwrap();
+            store.vote_answer(new.id.0, &voter, 1).await.unwrap();
+            let by_score = store.get_questions(&QuestionFilter::default(), &QuestionSort { field: SortField::Score, order: SortOrder::Desc }, None, 0).await.unwrap();
+            let answers = store.get_answers(first.id.0, AnswerSort::Score, None, 0).await.unwrap();
+            let duplicate = sqlx::query("INSERT INTO answer_votes (answer_id, account_id, value) VALUES ($1, $2, 1)")
+                .bind(new.id.0)
+                .bind(voter.0)
+                .execute(&store.connection)
+                .await;
+            store.delete_account(&other).await.unwrap();
+
+            // assert
+            assert_eq!((up, up_again, other_up, switched, cleared, missing), (Some(1), Some(1), Some(2), Some(0), Some(1), None));
+            assert_eq!(by_score.iter().map(|q| (q.id.clone(), q.score)).collect::<Vec<_>>(), vec![(second.id.clone(), 1), (first.id.clone(), -1)]);
+            assert_eq!(answers.iter().map(|a| (a.id.clone(), a.score)).collect::<Vec<_>>(), vec![(new.id, 1), (old.id, 0)]);
+            assert!(duplicate.is_err());
+            assert_eq!(store.get_question(second.id.0).await.unwrap().unwrap().score, 0);
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn accepted_answers() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let solved = store.add_question(new_question("Solved"), &owner).await.unwrap();
+            let open = store.add_question(new_question("Open"), &owner).await.unwrap();
+            let answer = |question: &Question, content: &str| NewAnswer { content: content.to_string(), question_id: question.id.clone() };
+            let first = store.add_answer(answer(&solved, "First"), &owner).await.unwrap();
+            let second = store.add_answer(answer(&solved, "Second"), &owner).await.unwrap();
+            let elsewhere = store.add_answer(answer(&open, "Elsewhere"), &owner).await.unwrap();
+            let answered = QuestionFilter { answered: Some(true), ..QuestionFilter::default() };
+            let unanswered = QuestionFilter { answered: Some(false), ..QuestionFilter::default() };
+
+            // act
+            let wrong_question = store.accept_answer(solved.id.0, elsewhere.id.0).await.unwrap();
+            let accepted = store.accept_answer(solved.id.0, second.id.0).await.unwrap();
+            let answers = store.get_answers(solved.id.0, AnswerSort::Id, None, 0).await.unwrap();
+            let answered_questions = store.get_questions(&answered, &QuestionSort::default(), None, 0).await.unwrap();
+            let unanswered_questions = store.get_questions(&unanswered, &QuestionSort::default(), None, 0).await.unwrap();
+            let unaccept_other = store.unaccept_answer(solved.id.0, first.id.0).await.unwrap();
+            store.delete_answer(second.id.0, &owner).await.unwrap();
+
+            // assert
+            assert!(!wrong_question);
+            assert!(accepted);
+            assert_eq!(answers.iter().map(|a| a.id.clone()).collect::<Vec<_>>(), vec![second.id.clone(), first.id.clone()]);
+            assert_eq!(answered_questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>(), vec![solved.id.clone()]);
+            assert_eq!(answered_questions[0].accepted_answer_id, Some(second.id.clone()));
+            assert_eq!(unanswered_questions.iter().map(|q| q.id.clone()).collect::<Vec<_>>(), vec![open.id.clone()]);
+            assert!(!unaccept_other);
+            assert_eq!(store.get_question(solved.id.0).await.unwrap().unwrap().accepted_answer_id, None);
+            assert!(store.accept_answer(solved.id.0, first.id.0).await.unwrap());
+            assert!(store.unaccept_answer(solved.id.0, first.id.0).await.unwrap());
+            assert_eq!(store.count_questions(&answered).await.unwrap(), 0);
+        }).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "needs TEST_DATABASE_URL"]
+    async fn comments() {
+        with_store(|store| async move {
+            // arrange
+            let owner = create_account(&store, "owner@example.com").await;
+            let commenter = create_account(&store, "commenter@example.com").await;
+            let solved = store.add_question(new_question("Solved"), &owner).await.unwrap();
+            let removed = store.add_question(new_question("Removed"), &owner).await.unwrap();
+            let answer = store.add_answer(NewAnswer { content: "Answer".to_string(), question_id: solved.id.clone() }, &owner).await.unwrap();
+            let on_question = CommentTarget::Question(solved.id.clone());
+            let on_answer = CommentTarget::Answer(answer.id.clone());
+            let first = store.add_comment(&on_question, "First".to_string(), &commenter).await.unwrap();
+            let second = store.add_comment(&on_question, "Second".to_string(), &owner).await.unwrap();
+            let answer_comment = store.add_comment(&on_answer, "On the answer".to_string(), &commenter).await.unwrap();
+            let gone = store.add_comment(&CommentTarget::Question(removed.id.clone()), "Gone".to_string(), &owner).await.unwrap();
+
+            // act
+            let listed = store.get_comments(&on_question, None, 0).await.unwrap();
+            let paged = store.get_comments(&on_question, Some(1), 1).await.unwrap();
+            let updated = store.update_comment(first.id.0, "Edited".to_string(), &commenter).await.unwrap();
+            let update_by_other = store.update_comment(first.id.0, "Hijacked".to_string(), &owner).await;
+            let by_commenter = store.get_comments_by_account(&commenter).await.unwrap();
+            let delete_by_other = store.delete_comment(answer_comment.id.0, &owner).await.unwrap();
+            let deleted = store.delete_comment(second.id.0, &owner).await.unwrap();
+            store.delete_question(removed.id.0, &owner).await.unwrap();
+            store.delete_account(&commenter).await.unwrap();
+
+            // assert
+            assert_eq!(first.target, on_question);
+            assert_eq!(first.author.id, commenter);
+            assert_eq!(listed.iter().map(|c| c.id.clone()).collect::<Vec<_>>(), vec![first.id.clone(), second.id.clone()]);
+            assert_eq!(paged.iter().map(|c| c.id.clone()).collect::<Vec<_>>(), vec![second.id.clone()]);
+            assert_eq!(updated.content, "Edited");
+            assert!(update_by_other.is_err());
+            assert_eq!(by_commenter.iter().map(|c| c.id.clone()).collect::<Vec<_>>(), vec![first.id.clone(), answer_comment.id.clone()]);
+            assert!(!delete_by_other);
+            assert!(deleted);
+            assert!(store.get_comment(gone.id.0).await.unwrap().is_none());
+            assert!(store.get_comments(&on_question, None, 0).await.unwrap().is_empty());
+            assert!(store.get_comments(&on_answer, None, 0).await.unwrap().is_empty());
+        }).await;
+    }
+}
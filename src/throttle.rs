@@ -0,0 +1,229 @@
+//! Failed login bookkeeping, kept in process memory.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Thresholds of the login throttle, see the `login_*` options in `Config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThrottleSettings {
+    /// Failures in a row after which an account is locked.
+    pub max_account_failures: u32,
+    /// Failures in a row after which an IP address is locked.
+    pub max_ip_failures: u32,
+    /// Wait after the first failure, doubled with every further one.
+    pub backoff: Duration,
+    /// How long a lock lasts. Failures older than this are forgotten.
+    pub lockout: Duration,
+}
+
+impl ThrottleSettings {
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        ThrottleSettings {
+            max_account_failures: config.login_max_failures,
+            max_ip_failures: config.login_ip_max_failures,
+            backoff: Duration::from_millis(config.login_backoff_ms),
+            lockout: Duration::from_secs(config.login_lockout_minutes * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Attempts {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Account(String),
+    Ip(IpAddr),
+}
+
+/// Counts failed logins per account and per IP address. Each failure makes
+/// the next attempt wait exponentially longer, and too many lock the account
+/// or address for a while.
+#[derive(Debug)]
+pub struct LoginThrottle {
+    settings: ThrottleSettings,
+    attempts: Mutex<HashMap<Key, Attempts>>,
+}
+
+impl LoginThrottle {
+    pub fn new(settings: ThrottleSettings) -> Self {
+        LoginThrottle { settings, attempts: Mutex::new(HashMap::new()) }
+    }
+
+    /// Time left until `email` may try again from `ip`, `None` if it may now.
+    pub fn retry_after(&self, email: &str, ip: Option<IpAddr>, now: Instant) -> Option<Duration> {
+        let attempts = self.attempts.lock().unwrap();
+        keys(email, ip)
+            .iter()
+            .filter_map(|key| attempts.get(key))
+            .filter_map(|a| self.blocked_until(a, now))
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+            .max()
+    }
+
+    pub fn record_failure(&self, email: &str, ip: Option<IpAddr>, now: Instant) {
+        let mut attempts = self.attempts.lock().unwrap();
+        // failures past the lockout count for nothing, dropping them keeps
+        // made-up addresses from piling up
+        attempts.retain(|_, a| now.duration_since(a.last_failure) < self.settings.lockout);
+        for key in keys(email, ip) {
+            let max_failures = match key {
+                Key::Account(_) => self.settings.max_account_failures,
+                Key::Ip(_) => self.settings.max_ip_failures,
+            };
+            let entry = attempts.entry(key.clone()).or_insert(Attempts {
+                failures: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+            entry.failures += 1;
+            entry.last_failure = now;
+            if entry.failures >= max_failures && entry.locked_until.is_none() {
+                entry.locked_until = Some(now + self.settings.lockout);
+                match &key {
+                    Key::Account(email) => tracing::event!(
+                        target: "audit", tracing::Level::WARN,
+                        email = %email, failures = entry.failures, "account locked after failed logins"
+                    ),
+                    Key::Ip(ip) => tracing::event!(
+                        target: "audit", tracing::Level::WARN,
+                        ip = %ip, failures = entry.failures, "ip address locked after failed logins"
+                    ),
+                }
+            }
+        }
+    }
+
+    /// Forgets the failures of the account, the IP address keeps its count.
+    pub fn record_success(&self, email: &str) {
+        let mut attempts = self.attempts.lock().unwrap();
+        attempts.remove(&Key::Account(email.to_lowercase()));
+    }
+
+    fn blocked_until(&self, attempts: &Attempts, now: Instant) -> Option<Instant> {
+        if let Some(locked_until) = attempts.locked_until {
+            return Some(locked_until);
+        }
+        if now.duration_since(attempts.last_failure) >= self.settings.lockout {
+            return None;
+        }
+        let exponent = attempts.failures.saturating_sub(1).min(16);
+        let backoff = self.settings.backoff.saturating_mul(1 << exponent).min(self.settings.lockout);
+        Some(attempts.last_failure + backoff)
+    }
+}
+
+fn keys(email: &str, ip: Option<IpAddr>) -> Vec<Key> {
+    let mut keys = vec![Key::Account(email.to_lowercase())];
+    keys.extend(ip.map(Key::Ip));
+    keys
+}
+
+
+#[cfg(test)]
+mod throttle_tests {
+    use super::*;
+
+    fn throttle() -> LoginThrottle {
+        LoginThrottle::new(ThrottleSettings {
+            max_account_failures: 3,
+            max_ip_failures: 5,
+            backoff: Duration::from_secs(1),
+            lockout: Duration::from_secs(600),
+        })
+    }
+
+    #[test]
+    fn backoff_doubles() {
+        // arrange
+        let throttle = throttle();
+        let start = Instant::now();
+
+        // act
+        throttle.record_failure("a@b.c", None, start);
+        let after_one = throttle.retry_after("a@b.c", None, start);
+        throttle.record_failure("a@b.c", None, start);
+        let after_two = throttle.retry_after("A@B.C", None, start);
+
+        // assert
+        assert_eq!(after_one, Some(Duration::from_secs(1)));
+        assert_eq!(after_two, Some(Duration::from_secs(2)));
+        assert_eq!(throttle.retry_after("a@b.c", None, start + Duration::from_secs(2)), None);
+        assert_eq!(throttle.retry_after("other@b.c", None, start), None);
+    }
+
+    #[test]
+    fn locks_account_and_forgets_after_lockout() {
+        // arrange
+        let throttle = throttle();
+        let start = Instant::now();
+
+        // act
+        for _ in 0..3 {
+            throttle.record_failure("a@b.c", None, start);
+        }
+
+        // assert
+        assert_eq!(throttle.retry_after("a@b.c", None, start + Duration::from_secs(10)), Some(Duration::from_secs(590)));
+        assert_eq!(throttle.retry_after("a@b.c", None, start + Duration::from_secs(600)), None);
+        throttle.record_failure("a@b.c", None, start + Duration::from_secs(600));
+        assert_eq!(throttle.retry_after("a@b.c", None, start + Duration::from_secs(602)), None);
+    }
+
+    #[test]
+    fn locks_ip_across_accounts() {
+        // arrange
+        let throttle = throttle();
+        let start = Instant::now();
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+
+        // act
+        for i in 0..5 {
+            throttle.record_failure(&format!("{}@b.c", i), Some(ip), start);
+        }
+
+        // assert
+        assert!(throttle.retry_after("new@b.c", Some(ip), start + Duration::from_secs(60)).is_some());
+        assert_eq!(throttle.retry_after("new@b.c", None, start + Duration::from_secs(60)), None);
+    }
+
+    #[test]
+    fn success_resets_account() {
+        // arrange
+        let throttle = throttle();
+        let start = Instant::now();
+        throttle.record_failure("a@b.c", None, start);
+
+        // act
+        throttle.record_success("a@b.c");
+
+        // assert
+        assert_eq!(throttle.retry_after("a@b.c", None, start), None);
+    }
+
+    #[test]
+    fn forgets_old_failures() {
+        // arrange
+        let throttle = throttle();
+        let start = Instant::now();
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        for i in 0..3 {
+            throttle.record_failure(&format!("{}@b.c", i), Some(ip), start);
+        }
+
+        // act
+        throttle.record_failure("late@b.c", None, start + Duration::from_secs(600));
+
+        // assert
+        let attempts = throttle.attempts.lock().unwrap();
+        assert_eq!(attempts.len(), 1);
+        assert!(attempts.contains_key(&Key::Account("late@b.c".to_string())));
+    }
+}
@@ -0,0 +1,115 @@
+//! Time-based one-time passwords (RFC 6238) as shown by authenticator apps:
+//! HMAC-SHA1, six digits, 30 second steps.
+
+use hmac::{Hmac, Mac};
+use rand::Rng;
+
+const STEP_SECONDS: i64 = 30;
+const DIGITS: usize = 6;
+/// Steps before and after the current one that are accepted, for clocks
+/// that are a little off and codes typed just before they changed.
+const ALLOWED_SKEW: i64 = 1;
+
+/// Random 160 bit secret, base32 encoded as authenticator apps expect it.
+pub fn generate_secret() -> String {
+    let bytes = rand::thread_rng().gen::<[u8; 20]>();
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, &bytes)
+}
+
+/// Time step `unix_time` falls into.
+pub fn step(unix_time: i64) -> i64 {
+    unix_time.div_euclid(STEP_SECONDS)
+}
+
+/// Code for the base32 `secret` at `step`, `None` if the secret is not
+/// valid base32.
+pub fn code(secret: &str, step: i64) -> Option<String> {
+    let key = base32::decode(base32::Alphabet::RFC4648 { padding: false }, secret)?;
+    let mut mac = Hmac::<sha1::Sha1>::new_from_slice(&key).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = usize::from(hash[hash.len() - 1] & 0x0f);
+    let binary = u32::from_be_bytes([hash[offset], hash[offset + 1], hash[offset + 2], hash[offset + 3]]) & 0x7fff_ffff;
+    Some(format!("{:0width$}", binary % 10u32.pow(DIGITS as u32), width = DIGITS))
+}
+
+/// Step whose code `code` is at `unix_time`, allowing for clock skew.
+pub fn verify(secret: &str, code: &str, unix_time: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let current = step(unix_time);
+
+    (current - ALLOWED_SKEW..=current + ALLOWED_SKEW)
+        .find(|&step| self::code(secret, step).is_some_and(|expected| constant_time_eq(&expected, code)))
+}
+
+/// `otpauth://` URI for enrolling the secret, usually shown as QR code.
+pub fn otpauth_uri(issuer: &str, account_name: &str, secret: &str) -> String {
+    use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+
+    let issuer = utf8_percent_encode(issuer, NON_ALPHANUMERIC).to_string();
+    let account_name = utf8_percent_encode(account_name, NON_ALPHANUMERIC);
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        issuer, account_name, secret, issuer, DIGITS, STEP_SECONDS
+    )
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+
+#[cfg(test)]
+mod totp_tests {
+    use super::*;
+
+    /// "12345678901234567890", the SHA1 secret of the RFC 6238 test vectors.
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn codes_match_rfc_6238() {
+        // assert, the last six digits of the RFC 6238 appendix B values
+        assert_eq!(code(RFC_SECRET, step(59)).unwrap(), "287082");
+        assert_eq!(code(RFC_SECRET, step(1111111109)).unwrap(), "081804");
+        assert_eq!(code(RFC_SECRET, step(1111111111)).unwrap(), "050471");
+        assert_eq!(code(RFC_SECRET, step(1234567890)).unwrap(), "005924");
+        assert_eq!(code(RFC_SECRET, step(2000000000)).unwrap(), "279037");
+        assert_eq!(code("not base32!", 1), None);
+    }
+
+    #[test]
+    fn verify_allows_one_step_of_skew() {
+        // arrange, a fixed clock
+        let now = 1111111111;
+        let current = code(RFC_SECRET, step(now)).unwrap();
+        let previous = code(RFC_SECRET, step(now) - 1).unwrap();
+        let too_old = code(RFC_SECRET, step(now) - 2).unwrap();
+
+        // assert
+        assert_eq!(verify(RFC_SECRET, &current, now), Some(step(now)));
+        assert_eq!(verify(RFC_SECRET, &format!(" {} ", current), now), Some(step(now)));
+        assert_eq!(verify(RFC_SECRET, &previous, now), Some(step(now) - 1));
+        assert_eq!(verify(RFC_SECRET, &too_old, now), None);
+        assert_eq!(verify(RFC_SECRET, "12345", now), None);
+        assert_eq!(verify(RFC_SECRET, "abcdef", now), None);
+    }
+
+    #[test]
+    fn secrets_and_uris() {
+        // act
+        let secret = generate_secret();
+        let uri = otpauth_uri("Web Questions", "jane@example.com", &secret);
+
+        // assert
+        assert_eq!(secret.len(), 32);
+        assert!(code(&secret, 0).is_some());
+        assert_eq!(
+            uri,
+            format!("otpauth://totp/Web%20Questions:jane%40example%2Ecom?secret={}&issuer=Web%20Questions&algorithm=SHA1&digits=6&period=30", secret)
+        );
+    }
+}
@@ -6,14 +6,311 @@ pub struct Account {
   pub id: Option<AccountId>,
   pub email: String,
   pub password: String,
+  /// Never taken from a request body, new accounts always start as users.
+  #[serde(default, skip_deserializing)]
+  pub role: Role,
+  /// Set once the owner opened the link sent to `email`.
+  #[serde(default, skip_deserializing)]
+  pub email_verified: bool,
+  /// Changed through `PUT /account` only.
+  #[serde(default, skip_deserializing)]
+  pub profile: Profile,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AccountId(pub i32);
 
+/// What an account may do beyond managing its own content. Each role
+/// includes the ones before it.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+  #[default]
+  User,
+  /// May edit and delete any question or answer.
+  Moderator,
+  /// May additionally manage accounts.
+  Admin,
+}
+
+impl Role {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Role::User => "user",
+      Role::Moderator => "moderator",
+      Role::Admin => "admin",
+    }
+  }
+}
+
+impl std::str::FromStr for Role {
+  type Err = handle_errors::Error;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "user" => Ok(Role::User),
+      "moderator" => Ok(Role::Moderator),
+      "admin" => Ok(Role::Admin),
+      _ => Err(handle_errors::Error::InvalidParameter("role".to_string())),
+    }
+  }
+}
+
+/// Account as shown to its owner and to admins, without the password hash.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AccountInfo {
+  pub id: AccountId,
+  pub email: String,
+  pub role: Role,
+  pub email_verified: bool,
+  #[serde(flatten)]
+  pub profile: Profile,
+}
+
+impl From<Account> for AccountInfo {
+  fn from(account: Account) -> Self {
+    AccountInfo {
+      id: account.id.expect("stored accounts have an id"),
+      email: account.email,
+      role: account.role,
+      email_verified: account.email_verified,
+      profile: account.profile,
+    }
+  }
+}
+
+/// What an account tells others about itself, also the body of `PUT /account`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profile {
+  #[serde(default)]
+  pub display_name: Option<String>,
+  #[serde(default)]
+  pub bio: Option<String>,
+  #[serde(default)]
+  pub avatar_url: Option<String>,
+}
+
+/// Profile of any account as shown by `GET /users/{id}`, never with the
+/// e-mail address.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PublicProfile {
+  pub id: AccountId,
+  pub display_name: String,
+  pub bio: Option<String>,
+  pub avatar_url: Option<String>,
+  pub question_count: i64,
+  pub answer_count: i64,
+}
+
+/// Display name left on the content of anonymized accounts.
+pub const DELETED_ACCOUNT_NAME: &str = "Deleted user";
+
+/// Address an anonymized account is left with, `.invalid` never receives mail.
+pub fn deleted_account_email(id: &AccountId) -> String {
+  format!("deleted-{}@invalid", id.0)
+}
+
+/// Who wrote a question or answer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Author {
+  pub id: AccountId,
+  pub display_name: String,
+}
+
+impl Author {
+  pub fn new(id: AccountId, display_name: Option<String>) -> Self {
+    Author {
+      display_name: public_name(&id, display_name),
+      id,
+    }
+  }
+}
+
+/// Display name of the account, or a stand-in derived from its id for
+/// accounts that never chose one.
+pub fn public_name(id: &AccountId, display_name: Option<String>) -> String {
+  display_name.unwrap_or_else(|| format!("user{}", id.0))
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RoleUpdate {
+  pub role: Role,
+}
+
+/// Returned on login and refresh. The access token authenticates requests
+/// for `expires_in` seconds, the refresh token obtains the next pair.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenPair {
+  pub access_token: String,
+  pub refresh_token: String,
+  pub token_type: String,
+  pub expires_in: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct VerificationQuery {
+  pub token: String,
+}
+
+/// Query the OpenID Connect provider redirects back with, carrying either
+/// a `code` or an `error`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct OidcCallbackQuery {
+  pub code: Option<String>,
+  pub state: Option<String>,
+  pub error: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PasswordForgot {
+  pub email: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PasswordReset {
+  pub token: String,
+  pub password: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PasswordChange {
+  pub old_password: String,
+  pub new_password: String,
+}
+
+/// Body of `DELETE /account`, confirmed by the password or, with two
+/// factors enabled, a TOTP or recovery code.
+#[derive(Deserialize, Debug, Clone)]
+pub struct AccountDeletion {
+  pub password: Option<String>,
+  pub code: Option<String>,
+}
+
+/// Everything stored about an account, as returned by `GET /account/export`.
+#[derive(Serialize, Debug, Clone)]
+pub struct AccountExport {
+  pub account: AccountInfo,
+  pub questions: Vec<crate::types::question::Question>,
+  pub answers: Vec<crate::types::answer::Answer>,
+  pub comments: Vec<crate::types::comment::Comment>,
+  pub exported_on: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct RefreshRequest {
+  pub refresh_token: String,
+}
+
+/// Server-side session a refresh token belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredSession {
+  pub id: String,
+  pub account_id: AccountId,
+}
+
+/// Claims of a verified access token.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Session {
   pub exp: DateTime<Utc>,
   pub account_id: AccountId,
   pub nbf: DateTime<Utc>,
+  /// Id of the server-side session the token was issued for.
+  pub jti: String,
+  /// Tokens issued before roles existed carry no role claim.
+  #[serde(default)]
+  pub role: Role,
+  /// Set instead of a token session when the request came with an API key.
+  #[serde(skip)]
+  pub api_key: Option<crate::types::api_key::ApiKeyId>,
+}
+
+impl Session {
+  pub fn has_role(&self, required: Role) -> bool {
+    self.role >= required
+  }
+
+  /// Owners may change their own content, moderators anybody's.
+  pub fn may_modify(&self, owner: &AccountId) -> bool {
+    self.account_id == *owner || self.has_role(Role::Moderator)
+  }
+}
+
+
+#[cfg(test)]
+mod account_tests {
+  use super::*;
+
+  fn session(account_id: i32, role: Role) -> Session {
+    Session { exp: Utc::now(), account_id: AccountId(account_id), nbf: Utc::now(), jti: "jti".to_string(), role, api_key: None }
+  }
+
+  #[test]
+  fn roles_include_lower_roles() {
+    // arrange
+    let admin = session(1, Role::Admin);
+    let user = session(2, Role::User);
+
+    // assert
+    assert!(admin.has_role(Role::Moderator));
+    assert!(admin.has_role(Role::User));
+    assert!(!user.has_role(Role::Moderator));
+  }
+
+  #[test]
+  fn moderators_may_modify_any_content() {
+    // arrange
+    let owner = AccountId(1);
+
+    // assert
+    assert!(session(1, Role::User).may_modify(&owner));
+    assert!(!session(2, Role::User).may_modify(&owner));
+    assert!(session(2, Role::Moderator).may_modify(&owner));
+  }
+
+  #[test]
+  fn role_is_not_taken_from_registration() {
+    // act
+    let account: Account = serde_json::from_str(
+      r#"{"email": "a@b.c", "password": "secret", "role": "admin"}"#
+    ).unwrap();
+
+    // assert
+    assert_eq!(account.role, Role::User);
+  }
+
+  #[test]
+  fn profile_is_not_taken_from_registration() {
+    // act
+    let account: Account = serde_json::from_str(
+      r#"{"email": "a@b.c", "password": "secret", "profile": {"display_name": "admin"}}"#
+    ).unwrap();
+
+    // assert
+    assert_eq!(account.profile, Profile::default());
+  }
+
+  #[test]
+  fn authors_without_display_name() {
+    // act
+    let named = Author::new(AccountId(1), Some("Ada".to_string()));
+    let unnamed = Author::new(AccountId(2), None);
+
+    // assert
+    assert_eq!(named.display_name, "Ada");
+    assert_eq!(unnamed.display_name, "user2");
+  }
+
+  #[test]
+  fn session_without_role_claim() {
+    // act
+    let session: Session = serde_json::from_value(serde_json::json!({
+      "exp": "2030-01-01T00:00:00Z",
+      "nbf": "2020-01-01T00:00:00Z",
+      "account_id": 3,
+      "jti": "b5c5b1d4",
+    })).unwrap();
+
+    // assert
+    assert_eq!(session.role, Role::User);
+  }
 }
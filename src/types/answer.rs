@@ -5,6 +5,12 @@ pub struct Answer {
     pub id: AnswerId,
     pub content: String,
     pub question_id: crate::types::question::QuestionId,
+    /// Up minus down votes, kept by the store and ignored in request bodies.
+    #[serde(default, skip_deserializing)]
+    pub score: i32,
+    /// Filled in by the store, ignored in request bodies.
+    #[serde(default, skip_deserializing)]
+    pub author: Option<crate::types::account::Author>,
 }
 
 impl std::str::FromStr for AnswerId {
@@ -25,3 +31,24 @@ pub struct NewAnswer {
     pub content: String,
     pub question_id: crate::types::question::QuestionId,
 }
+
+/// Order of the answers to a question.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnswerSort {
+    /// Oldest first
+    #[default]
+    Id,
+    /// Highest score first, ties oldest first
+    Score,
+}
+impl AnswerSort {
+    /// Reads an optional `sort=id|score`.
+    pub fn new(params: &std::collections::HashMap<String, String>) -> Result<Self, handle_errors::Error> {
+        match params.get("sort").map(|sort| sort.trim()) {
+            None | Some("id") => Ok(AnswerSort::Id),
+            Some("score") => Ok(AnswerSort::Score),
+            Some("") => Err(handle_errors::Error::MissingParameters),
+            Some(_) => Err(handle_errors::Error::InvalidParameter("sort".to_string())),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::account::AccountId;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApiKeyId(pub i32);
+
+/// What a request authenticated with an API key may do. Reading needs no
+/// key, but a request that sends one needs the read scope: `questions:read`
+/// for questions, tags and user pages, `answers:read` for answers.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Scope {
+    #[serde(rename = "questions:read")]
+    QuestionsRead,
+    #[serde(rename = "questions:write")]
+    QuestionsWrite,
+    #[serde(rename = "answers:read")]
+    AnswersRead,
+    #[serde(rename = "answers:write")]
+    AnswersWrite,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::QuestionsRead => "questions:read",
+            Scope::QuestionsWrite => "questions:write",
+            Scope::AnswersRead => "answers:read",
+            Scope::AnswersWrite => "answers:write",
+        }
+    }
+}
+
+impl std::str::FromStr for Scope {
+    type Err = handle_errors::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "questions:read" => Ok(Scope::QuestionsRead),
+            "questions:write" => Ok(Scope::QuestionsWrite),
+            "answers:read" => Ok(Scope::AnswersRead),
+            "answers:write" => Ok(Scope::AnswersWrite),
+            _ => Err(handle_errors::Error::InvalidParameter("scopes".to_string())),
+        }
+    }
+}
+
+/// API key as listed to its owner. The key itself is only shown once, on
+/// creation; the store keeps a hash of it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ApiKey {
+    pub id: ApiKeyId,
+    pub account_id: AccountId,
+    pub name: String,
+    /// First characters of the key, to tell keys apart.
+    pub prefix: String,
+    pub scopes: Vec<Scope>,
+    pub created_on: chrono::NaiveDateTime,
+    pub last_used_on: Option<chrono::NaiveDateTime>,
+    pub revoked_on: Option<chrono::NaiveDateTime>,
+}
+
+impl ApiKey {
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        self.scopes.contains(&scope)
+    }
+}
+
+/// Body of `POST /account/api-keys`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewApiKey {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// Reply to `POST /account/api-keys`, the only time `key` is revealed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CreatedApiKey {
+    pub key: String,
+    #[serde(flatten)]
+    pub api_key: ApiKey,
+}
+
+
+#[cfg(test)]
+mod api_key_tests {
+    use super::*;
+
+    #[test]
+    fn scopes_round_trip() {
+        // act
+        let scopes: Vec<Scope> = serde_json::from_str(r#"["questions:write", "answers:read"]"#).unwrap();
+        let unknown = serde_json::from_str::<Vec<Scope>>(r#"["questions:delete"]"#);
+
+        // assert
+        assert_eq!(scopes, vec![Scope::QuestionsWrite, Scope::AnswersRead]);
+        assert!(unknown.is_err());
+        for scope in scopes {
+            assert_eq!(scope.as_str().parse::<Scope>().unwrap(), scope);
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::account::Author;
+use crate::types::answer::AnswerId;
+use crate::types::question::QuestionId;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommentId(pub i32);
+
+/// Question or answer a comment is attached to, serialized as its
+/// `question_id` or `answer_id`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum CommentTarget {
+    #[serde(rename = "question_id")]
+    Question(QuestionId),
+    #[serde(rename = "answer_id")]
+    Answer(AnswerId),
+}
+impl CommentTarget {
+    pub fn question(id: i32) -> Self {
+        CommentTarget::Question(QuestionId(id))
+    }
+
+    pub fn answer(id: i32) -> Self {
+        CommentTarget::Answer(AnswerId(id))
+    }
+}
+
+/// Short remark on a question or answer, lighter than an answer.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Comment {
+    pub id: CommentId,
+    pub content: String,
+    #[serde(flatten)]
+    pub target: CommentTarget,
+    pub created_on: chrono::NaiveDateTime,
+    pub author: Author,
+}
+
+/// Body of adding or editing a comment.
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewComment {
+    pub content: String,
+}
+
+#[cfg(test)]
+mod comment_tests {
+    use super::*;
+    use crate::types::account::AccountId;
+
+    #[test]
+    fn target_is_flattened() {
+        // arrange
+        let comment = Comment {
+            id: CommentId(1),
+            content: "Nice".to_string(),
+            target: CommentTarget::answer(2),
+            created_on: chrono::NaiveDate::from_ymd_opt(2023, 6, 10).unwrap().and_hms_opt(0, 0, 0).unwrap(),
+            author: Author::new(AccountId(3), None),
+        };
+
+        // act
+        let json = serde_json::to_value(&comment).unwrap();
+        let parsed: Comment = serde_json::from_value(json.clone()).unwrap();
+
+        // assert
+        assert_eq!(json["answer_id"], 2);
+        assert!(json.get("question_id").is_none());
+        assert_eq!(parsed.target, CommentTarget::answer(2));
+    }
+}
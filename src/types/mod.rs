@@ -1,4 +1,10 @@
 pub mod answer;
+pub mod comment;
 pub mod pagination;
 pub mod question;
-pub mod account;
\ No newline at end of file
+pub mod account;
+pub mod api_key;
+pub mod two_factor;
+pub mod search;
+pub mod tag;
+pub mod vote;
@@ -1,7 +1,12 @@
+use base64::Engine;
+
+/// Offset pagination (`limit`/`offset`) or, when a `cursor` parameter is
+/// present, keyset pagination over `(created_on, id)`.
 #[derive(Debug)]
 pub struct Pagination {
     limit: Option<u32>,
     offset: u32,
+    cursor: Option<String>,
 }
 impl Pagination {
     pub fn new(
@@ -9,17 +14,26 @@ impl Pagination {
     ) -> Self {
         let limit = Pagination::get_value("limit", params, None);
         let offset = Pagination::get_value("offset", params, Some(0_u32));
+        let cursor = params.get("cursor").cloned();
 
-        Pagination { limit: limit, offset: offset.unwrap() }
+        Pagination { limit, offset: offset.unwrap(), cursor }
     }
-    pub fn get_limit(&self) -> Option<i32> {
-        match self.limit {
-            Some(limit) => Some(limit as i32),
-            None => None,
+    /// Whether the keyset mode was requested; an empty `cursor` asks for the first page.
+    pub fn is_keyset(&self) -> bool {
+        self.cursor.is_some()
+    }
+    pub fn get_cursor(&self) -> Result<Option<Cursor>, handle_errors::Error> {
+        match self.cursor.as_deref() {
+            Some(cursor) if !cursor.is_empty() => Cursor::decode(cursor).map(Some),
+            _ => Ok(None),
         }
     }
+    /// Limits beyond what the database takes are capped.
+    pub fn get_limit(&self) -> Option<i32> {
+        self.limit.map(|limit| i32::try_from(limit).unwrap_or(i32::MAX))
+    }
     pub fn get_offset(&self) -> i32 {
-        self.offset as i32
+        i32::try_from(self.offset).unwrap_or(i32::MAX)
     }
     fn get_value(
         key: &str,
@@ -49,10 +63,140 @@ pub fn get_pagination(
     Pagination::new(&params)
 }
 
+/// Which way a cursor pages from the row it points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Next,
+    Prev,
+}
+
+/// Opaque position in a keyset listing: the `(created_on, id)` of the last
+/// row seen, and whether the page after or before it is wanted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_on: chrono::NaiveDateTime,
+    pub id: i32,
+    pub direction: Direction,
+}
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let direction = match self.direction {
+            Direction::Next => "n",
+            Direction::Prev => "p",
+        };
+        let raw = format!(
+            "{}|{}|{}",
+            direction,
+            self.created_on.format("%Y-%m-%dT%H:%M:%S%.f"),
+            self.id
+        );
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw)
+    }
+    pub fn decode(cursor: &str) -> Result<Self, handle_errors::Error> {
+        let invalid = || handle_errors::Error::InvalidParameter("cursor".to_string());
+        let raw = base64::engine::general_purpose::URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| invalid())?;
+        let raw = String::from_utf8(raw).map_err(|_| invalid())?;
+
+        let mut parts = raw.splitn(3, '|');
+        let direction = match parts.next() {
+            Some("n") => Direction::Next,
+            Some("p") => Direction::Prev,
+            _ => return Err(invalid()),
+        };
+        let created_on = parts
+            .next()
+            .and_then(|created_on| created_on.parse::<chrono::NaiveDateTime>().ok())
+            .ok_or_else(invalid)?;
+        let id = parts
+            .next()
+            .and_then(|id| id.parse::<i32>().ok())
+            .ok_or_else(invalid)?;
+
+        Ok(Cursor { created_on, id, direction })
+    }
+}
+
+/// One page of a keyset listing.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    pub total: i64,
+}
+impl<T> CursorPage<T> {
+    /// Builds a page from `rows` fetched in scan order with `limit + 1` as the
+    /// limit, so that an extra row tells whether more rows follow. `key` reads
+    /// the `(created_on, id)` of a row.
+    pub fn new(
+        mut rows: Vec<T>,
+        limit: Option<i32>,
+        cursor: Option<&Cursor>,
+        total: i64,
+        key: impl Fn(&T) -> Option<(chrono::NaiveDateTime, i32)>,
+    ) -> Self {
+        let has_more = limit.is_some_and(|limit| rows.len() > limit.max(0) as usize);
+        if let Some(limit) = limit {
+            rows.truncate(limit.max(0) as usize);
+        }
+        let backwards = cursor.is_some_and(|c| c.direction == Direction::Prev);
+        if backwards {
+            rows.reverse();
+        }
+
+        let cursor_at = |row: Option<&T>, direction| {
+            row.and_then(&key).map(|(created_on, id)| Cursor { created_on, id, direction }.encode())
+        };
+        let (has_next, has_prev) = match cursor {
+            None => (has_more, false),
+            Some(_) if backwards => (true, has_more),
+            Some(_) => (has_more, true),
+        };
+
+        CursorPage {
+            next_cursor: if has_next { cursor_at(rows.last(), Direction::Next) } else { None },
+            prev_cursor: if has_prev { cursor_at(rows.first(), Direction::Prev) } else { None },
+            items: rows,
+            total,
+        }
+    }
+
+    /// RFC 8288 `Link` header value pointing at the next and previous pages
+    /// of `path`, keeping every query parameter but the cursor.
+    pub fn link_header(&self, path: &str, params: &[(String, String)]) -> Option<String> {
+        let link = |cursor: &String, rel: &str| {
+            let mut params = params
+                .iter()
+                .filter(|(key, _)| key != "cursor")
+                .cloned()
+                .collect::<Vec<_>>();
+            params.push((String::from("cursor"), cursor.clone()));
+            let query = serde_urlencoded::to_string(params).unwrap_or_default();
+            format!("<{}?{}>; rel=\"{}\"", path, query, rel)
+        };
+
+        let links = [
+            self.next_cursor.as_ref().map(|cursor| link(cursor, "next")),
+            self.prev_cursor.as_ref().map(|cursor| link(cursor, "prev")),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+        if links.is_empty() {
+            None
+        } else {
+            Some(links.join(", "))
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod pagination_tests {
-    use super::{Pagination, get_pagination};
+    use super::{Cursor, CursorPage, Direction, Pagination, get_pagination};
 
     #[test]
     fn valid_pagination() {
@@ -101,6 +245,22 @@ mod pagination_tests {
         assert_eq!(pagination.get_offset(), 0);
     }
 
+    #[test]
+    fn huge_pagination_is_capped() {
+        // arrange
+        let params = std::collections::HashMap::from([
+            (String::from("limit"), String::from("4294967295")),
+            (String::from("offset"), String::from("2147483648")),
+        ]);
+
+        // act
+        let pagination = Pagination::new(&params);
+
+        // assert
+        assert_eq!(pagination.get_limit(), Some(i32::MAX));
+        assert_eq!(pagination.get_offset(), i32::MAX);
+    }
+
     #[test]
     fn missing_limit_pagination() {
         // arrange
@@ -144,4 +304,112 @@ mod pagination_tests {
         assert_eq!(pagination.get_limit(), None);
         assert_eq!(pagination.get_offset(), 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn keyset_pagination() {
+        // arrange
+        let params = std::collections::HashMap::from([
+            (String::from("limit"), String::from("2")),
+            (String::from("cursor"), String::from("")),
+        ]);
+
+        // act
+        let pagination = Pagination::new(&params);
+
+        // assert
+        assert!(pagination.is_keyset());
+        assert_eq!(pagination.get_limit(), Some(2));
+        assert_eq!(pagination.get_cursor().unwrap(), None);
+        assert!(!get_pagination(std::collections::HashMap::new()).is_keyset());
+    }
+
+    #[test]
+    fn cursor_round_trip() {
+        // arrange
+        let cursor = Cursor {
+            created_on: chrono::NaiveDate::from_ymd_opt(2023, 3, 1)
+                .unwrap()
+                .and_hms_micro_opt(10, 0, 0, 123_456)
+                .unwrap(),
+            id: 42,
+            direction: Direction::Prev,
+        };
+
+        // act
+        let decoded = Cursor::decode(&cursor.encode());
+
+        // assert
+        assert_eq!(decoded.unwrap(), cursor);
+    }
+
+    #[test]
+    fn invalid_cursor() {
+        // act
+        let garbage = Cursor::decode("not a cursor");
+        let params = std::collections::HashMap::from([
+            (String::from("cursor"), String::from("bm9wZQ")),
+        ]);
+
+        // assert
+        assert!(matches!(garbage, Err(handle_errors::Error::InvalidParameter(name)) if name == "cursor"));
+        assert!(Pagination::new(&params).get_cursor().is_err());
+    }
+
+    #[test]
+    fn cursor_page_links() {
+        // arrange
+        let day = |d: u32| chrono::NaiveDate::from_ymd_opt(2023, 3, d).unwrap().and_hms_opt(0, 0, 0).unwrap();
+        let key = |row: &u32| Some((day(*row), *row as i32));
+        let after_one = Cursor { created_on: day(1), id: 1, direction: Direction::Next };
+        let before_four = Cursor { created_on: day(4), id: 4, direction: Direction::Prev };
+
+        // act
+        let first = CursorPage::new(vec![1, 2, 3], Some(2), None, 5, key);
+        let middle = CursorPage::new(vec![2, 3, 4], Some(2), Some(&after_one), 5, key);
+        let last = CursorPage::new(vec![4, 5], Some(2), Some(&after_one), 5, key);
+        let back = CursorPage::new(vec![3, 2, 1], Some(2), Some(&before_four), 5, key);
+
+        // assert
+        assert_eq!(first.items, vec![1, 2]);
+        assert_eq!(first.prev_cursor, None);
+        assert_eq!(Cursor::decode(&first.next_cursor.unwrap()).unwrap().id, 2);
+        assert_eq!(middle.items, vec![2, 3]);
+        assert_eq!(Cursor::decode(&middle.prev_cursor.unwrap()).unwrap().id, 2);
+        assert_eq!(Cursor::decode(&middle.next_cursor.unwrap()).unwrap().id, 3);
+        assert_eq!(last.next_cursor, None);
+        assert_eq!(back.items, vec![2, 3]);
+        assert_eq!(back.total, 5);
+        let prev = Cursor::decode(&back.prev_cursor.unwrap()).unwrap();
+        assert_eq!((prev.id, prev.direction), (2, Direction::Prev));
+        assert_eq!(Cursor::decode(&back.next_cursor.unwrap()).unwrap().id, 3);
+    }
+
+    #[test]
+    fn cursor_page_link_header() {
+        // arrange
+        let page = CursorPage::<u32> {
+            items: vec![],
+            next_cursor: Some(String::from("abc")),
+            prev_cursor: Some(String::from("xyz")),
+            total: 0,
+        };
+        let params = vec![
+            (String::from("tag"), String::from("c++")),
+            (String::from("cursor"), String::from("old")),
+            (String::from("limit"), String::from("2")),
+        ];
+
+        // act
+        let header = page.link_header("/questions", &params);
+        let none = CursorPage::<u32> { next_cursor: None, prev_cursor: None, ..page }
+            .link_header("/questions", &params);
+
+        // assert
+        assert_eq!(
+            header.unwrap(),
+            "</questions?tag=c%2B%2B&limit=2&cursor=abc>; rel=\"next\", \
+            </questions?tag=c%2B%2B&limit=2&cursor=xyz>; rel=\"prev\""
+        );
+        assert_eq!(none, None);
+    }
+}
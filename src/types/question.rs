@@ -24,6 +24,18 @@ pub struct Question {
     pub title: String,
     pub content: String,
     pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub created_on: Option<chrono::NaiveDateTime>,
+    /// Up minus down votes, kept by the store and ignored in request bodies.
+    #[serde(default, skip_deserializing)]
+    pub score: i32,
+    /// Answer the asker marked as the solution, set through
+    /// `POST /questions/{id}/accept/{answer_id}` and ignored in request bodies.
+    #[serde(default, skip_deserializing)]
+    pub accepted_answer_id: Option<crate::types::answer::AnswerId>,
+    /// Filled in by the store, ignored in request bodies.
+    #[serde(default, skip_deserializing)]
+    pub author: Option<crate::types::account::Author>,
 }
 impl std::fmt::Display for Question {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -50,3 +62,349 @@ impl std::fmt::Display for NewQuestion {
         )
     }
 }
+
+/// How the requested tags must match a question's tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TagMatch {
+    /// The question carries at least one of the tags
+    #[default]
+    Any,
+    /// The question carries every one of the tags
+    All,
+}
+
+/// Restricts which questions a listing returns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuestionFilter {
+    pub tags: Vec<String>,
+    pub tag_match: TagMatch,
+    /// Earliest `created_on`, inclusive
+    pub start: Option<chrono::NaiveDateTime>,
+    /// Latest `created_on`, inclusive
+    pub end: Option<chrono::NaiveDateTime>,
+    /// Whether the question has an accepted answer
+    pub answered: Option<bool>,
+}
+impl QuestionFilter {
+    /// Reads repeated `tag` parameters, an optional `tag_match=any|all`,
+    /// optional `start`/`end` dates (`2023-03-01` or `2023-03-01T12:00:00`)
+    /// and an optional `answered=true|false`.
+    pub fn new(params: &[(String, String)]) -> Result<Self, handle_errors::Error> {
+        let tags = params
+            .iter()
+            .filter(|(key, value)| key == "tag" && !value.is_empty())
+            .map(|(_, value)| value.clone())
+            .collect();
+
+        let tag_match = match get_param(params, "tag_match")? {
+            Some("all") => TagMatch::All,
+            Some("any") | None => TagMatch::Any,
+            Some(_) => return Err(handle_errors::Error::InvalidParameter("tag_match".to_string())),
+        };
+
+        let start = get_param(params, "start")?
+            .map(|value| parse_date("start", value, false))
+            .transpose()?;
+        let end = get_param(params, "end")?
+            .map(|value| parse_date("end", value, true))
+            .transpose()?;
+        if let (Some(start), Some(end)) = (start, end) {
+            if start > end {
+                return Err(handle_errors::Error::InvalidRange);
+            }
+        }
+
+        let answered = match get_param(params, "answered")? {
+            Some("true") => Some(true),
+            Some("false") => Some(false),
+            Some(_) => return Err(handle_errors::Error::InvalidParameter("answered".to_string())),
+            None => None,
+        };
+
+        Ok(QuestionFilter { tags, tag_match, start, end, answered })
+    }
+
+    pub fn with_tag(tag: String) -> Self {
+        QuestionFilter {
+            tags: vec![tag],
+            ..QuestionFilter::default()
+        }
+    }
+
+    /// Whether a question with `tags` passes this filter.
+    pub fn matches_tags(&self, tags: Option<&Vec<String>>) -> bool {
+        if self.tags.is_empty() {
+            return true;
+        }
+        let tags = tags.map(Vec::as_slice).unwrap_or_default();
+        match self.tag_match {
+            TagMatch::Any => self.tags.iter().any(|tag| tags.contains(tag)),
+            TagMatch::All => self.tags.iter().all(|tag| tags.contains(tag)),
+        }
+    }
+
+    /// Whether a question created at `created_on` falls inside `start`/`end`.
+    pub fn matches_created_on(&self, created_on: Option<chrono::NaiveDateTime>) -> bool {
+        match created_on {
+            Some(created_on) => self.start.is_none_or(|start| created_on >= start)
+                && self.end.is_none_or(|end| created_on <= end),
+            None => self.start.is_none() && self.end.is_none(),
+        }
+    }
+
+    /// Whether a question with `accepted_answer_id` passes the `answered` filter.
+    pub fn matches_answered(&self, accepted_answer_id: Option<&crate::types::answer::AnswerId>) -> bool {
+        self.answered.is_none_or(|answered| answered == accepted_answer_id.is_some())
+    }
+
+    /// Whether `question` passes every part of this filter.
+    pub fn matches(&self, question: &Question) -> bool {
+        self.matches_tags(question.tags.as_ref())
+            && self.matches_created_on(question.created_on)
+            && self.matches_answered(question.accepted_answer_id.as_ref())
+    }
+}
+
+/// Column a question listing is ordered by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortField {
+    #[default]
+    Id,
+    CreatedOn,
+    Title,
+    AnswerCount,
+    Score,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Ordering of a question listing; ties are always broken by id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QuestionSort {
+    pub field: SortField,
+    pub order: SortOrder,
+}
+impl QuestionSort {
+    /// Reads `sort=created_on|title|answer_count|score` and `order=asc|desc`.
+    /// `order` is only accepted together with `sort`.
+    pub fn new(params: &[(String, String)]) -> Result<Self, handle_errors::Error> {
+        let field = match get_param(params, "sort")? {
+            Some("created_on") => SortField::CreatedOn,
+            Some("title") => SortField::Title,
+            Some("answer_count") => SortField::AnswerCount,
+            Some("score") => SortField::Score,
+            Some(_) => return Err(handle_errors::Error::InvalidParameter("sort".to_string())),
+            None => SortField::Id,
+        };
+        let order = match get_param(params, "order")? {
+            Some(_) if field == SortField::Id => return Err(handle_errors::Error::MissingParameters),
+            Some("asc") => SortOrder::Asc,
+            Some("desc") => SortOrder::Desc,
+            Some(_) => return Err(handle_errors::Error::InvalidParameter("order".to_string())),
+            None => SortOrder::Asc,
+        };
+
+        Ok(QuestionSort { field, order })
+    }
+}
+
+/// Last value given for `key`; present but empty counts as missing.
+fn get_param<'a>(
+    params: &'a [(String, String)],
+    key: &str,
+) -> Result<Option<&'a str>, handle_errors::Error> {
+    match params.iter().rev().find(|(k, _)| k == key) {
+        Some((_, value)) if value.trim().is_empty() => Err(handle_errors::Error::MissingParameters),
+        Some((_, value)) => Ok(Some(value.trim())),
+        None => Ok(None),
+    }
+}
+
+/// Parses a date or date-time; a bare date covers the whole day.
+fn parse_date(
+    key: &str,
+    value: &str,
+    end_of_day: bool,
+) -> Result<chrono::NaiveDateTime, handle_errors::Error> {
+    if let Ok(date_time) = value.parse::<chrono::NaiveDateTime>() {
+        return Ok(date_time);
+    }
+    let date = value
+        .parse::<chrono::NaiveDate>()
+        .map_err(|_| handle_errors::Error::InvalidParameter(key.to_string()))?;
+    let date_time = if end_of_day {
+        date.and_hms_nano_opt(23, 59, 59, 999_999_999)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(date_time.expect("valid time of day"))
+}
+
+
+#[cfg(test)]
+mod question_filter_tests {
+    use super::{QuestionFilter, QuestionSort, SortField, SortOrder, TagMatch};
+
+    fn params(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn repeated_tags() {
+        // arrange
+        let params = params(&[("tag", "rust"), ("limit", "1"), ("tag", "warp")]);
+
+        // act
+        let filter = QuestionFilter::new(&params).unwrap();
+
+        // assert
+        assert_eq!(filter.tags, vec!["rust".to_string(), "warp".to_string()]);
+        assert_eq!(filter.tag_match, TagMatch::Any);
+    }
+
+    #[test]
+    fn all_tags_must_match() {
+        // arrange
+        let params = params(&[("tag", "rust"), ("tag", "warp"), ("tag_match", "all")]);
+        let rust_only = vec!["rust".to_string()];
+        let both = vec!["warp".to_string(), "rust".to_string()];
+
+        // act
+        let filter = QuestionFilter::new(&params).unwrap();
+
+        // assert
+        assert_eq!(filter.tag_match, TagMatch::All);
+        assert!(!filter.matches_tags(Some(&rust_only)));
+        assert!(filter.matches_tags(Some(&both)));
+        assert!(!filter.matches_tags(None));
+    }
+
+    #[test]
+    fn any_tag_matches() {
+        // arrange
+        let rust_only = vec!["rust".to_string()];
+        let other = vec!["go".to_string()];
+
+        // act
+        let filter = QuestionFilter::new(&params(&[("tag", "rust"), ("tag", "warp")])).unwrap();
+
+        // assert
+        assert!(filter.matches_tags(Some(&rust_only)));
+        assert!(!filter.matches_tags(Some(&other)));
+    }
+
+    #[test]
+    fn invalid_tag_match() {
+        // act
+        let filter = QuestionFilter::new(&params(&[("tag", "rust"), ("tag_match", "some")]));
+
+        // assert
+        assert!(matches!(filter, Err(handle_errors::Error::InvalidParameter(key)) if key == "tag_match"));
+    }
+
+    #[test]
+    fn no_tags_matches_everything() {
+        // act
+        let filter = QuestionFilter::new(&[]).unwrap();
+
+        // assert
+        assert!(filter.matches_tags(None));
+        assert!(filter.matches_created_on(None));
+    }
+
+    #[test]
+    fn date_range() {
+        // arrange
+        let params = params(&[("start", "2023-03-01"), ("end", "2023-03-02")]);
+        let day = |d: u32, h: u32| chrono::NaiveDate::from_ymd_opt(2023, 3, d).unwrap().and_hms_opt(h, 0, 0);
+
+        // act
+        let filter = QuestionFilter::new(&params).unwrap();
+
+        // assert
+        assert_eq!(filter.start, day(1, 0));
+        assert!(!filter.matches_created_on(day(1, 0).map(|d| d - chrono::Duration::seconds(1))));
+        assert!(filter.matches_created_on(day(1, 0)));
+        assert!(filter.matches_created_on(day(2, 23)));
+        assert!(!filter.matches_created_on(day(3, 0)));
+        assert!(!filter.matches_created_on(None));
+    }
+
+    #[test]
+    fn date_time_range() {
+        // act
+        let filter = QuestionFilter::new(&params(&[("end", "2023-03-02T12:30:00")])).unwrap();
+
+        // assert
+        assert_eq!(filter.start, None);
+        assert_eq!(filter.end.unwrap().to_string(), "2023-03-02 12:30:00");
+    }
+
+    #[test]
+    fn inverted_date_range() {
+        // act
+        let result = QuestionFilter::new(&params(&[("start", "2023-03-02"), ("end", "2023-03-01")]));
+
+        // assert
+        assert!(matches!(result, Err(handle_errors::Error::InvalidRange)));
+    }
+
+    #[test]
+    fn invalid_and_empty_dates() {
+        // act
+        let invalid = QuestionFilter::new(&params(&[("start", "yesterday")]));
+        let empty = QuestionFilter::new(&params(&[("end", "")]));
+
+        // assert
+        assert!(matches!(invalid, Err(handle_errors::Error::InvalidParameter(key)) if key == "start"));
+        assert!(matches!(empty, Err(handle_errors::Error::MissingParameters)));
+    }
+
+    #[test]
+    fn answered() {
+        // act
+        let answered = QuestionFilter::new(&params(&[("answered", "true")])).unwrap();
+        let unanswered = QuestionFilter::new(&params(&[("answered", "false")])).unwrap();
+        let invalid = QuestionFilter::new(&params(&[("answered", "yes")]));
+
+        // assert
+        assert!(answered.matches_answered(Some(&crate::types::answer::AnswerId(1))));
+        assert!(!answered.matches_answered(None));
+        assert!(unanswered.matches_answered(None));
+        assert!(QuestionFilter::default().matches_answered(None));
+        assert!(matches!(invalid, Err(handle_errors::Error::InvalidParameter(key)) if key == "answered"));
+    }
+
+    #[test]
+    fn sort_and_order() {
+        // act
+        let default = QuestionSort::new(&[]).unwrap();
+        let sort = QuestionSort::new(&params(&[("sort", "answer_count"), ("order", "desc")])).unwrap();
+        let sort_only = QuestionSort::new(&params(&[("sort", "title")])).unwrap();
+        let by_score = QuestionSort::new(&params(&[("sort", "score"), ("order", "desc")])).unwrap();
+
+        // assert
+        assert_eq!(default, QuestionSort { field: SortField::Id, order: SortOrder::Asc });
+        assert_eq!(sort, QuestionSort { field: SortField::AnswerCount, order: SortOrder::Desc });
+        assert_eq!(sort_only, QuestionSort { field: SortField::Title, order: SortOrder::Asc });
+        assert_eq!(by_score, QuestionSort { field: SortField::Score, order: SortOrder::Desc });
+    }
+
+    #[test]
+    fn invalid_sort_and_order() {
+        // act
+        let unknown_sort = QuestionSort::new(&params(&[("sort", "votes")]));
+        let unknown_order = QuestionSort::new(&params(&[("sort", "title"), ("order", "up")]));
+        let order_without_sort = QuestionSort::new(&params(&[("order", "desc")]));
+
+        // assert
+        assert!(matches!(unknown_sort, Err(handle_errors::Error::InvalidParameter(key)) if key == "sort"));
+        assert!(matches!(unknown_order, Err(handle_errors::Error::InvalidParameter(key)) if key == "order"));
+        assert!(matches!(order_without_sort, Err(handle_errors::Error::MissingParameters)));
+    }
+}
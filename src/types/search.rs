@@ -0,0 +1,52 @@
+/// Marks the start of a matched term inside a search snippet.
+pub const HIGHLIGHT_START: &str = "<mark>";
+/// Marks the end of a matched term inside a search snippet.
+pub const HIGHLIGHT_STOP: &str = "</mark>";
+
+/// Characters escaped in snippets, with their HTML entities.
+pub const HTML_ESCAPES: [(char, &str); 5] = [
+    ('&', "&amp;"),
+    ('<', "&lt;"),
+    ('>', "&gt;"),
+    ('"', "&quot;"),
+    ('\'', "&#39;"),
+];
+
+/// Escapes `text` for HTML, so that only the highlight markup of a snippet
+/// takes effect when it is rendered.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match HTML_ESCAPES.iter().find(|(special, _)| *special == c) {
+            Some((_, entity)) => escaped.push_str(entity),
+            None => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A search hit. The snippets are HTML: the stored text is escaped and
+/// matched terms are wrapped in `HIGHLIGHT_START` and `HIGHLIGHT_STOP`.
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
+pub struct QuestionSearchResult {
+    pub question: crate::types::question::Question,
+    pub rank: f32,
+    pub title_snippet: String,
+    pub content_snippet: String,
+    pub answer_snippet: Option<String>,
+}
+
+
+#[cfg(test)]
+mod search_tests {
+    use super::escape_html;
+
+    #[test]
+    fn markup_is_escaped() {
+        // act
+        let escaped = escape_html("<img src=x onerror=\"alert('&')\">");
+
+        // assert
+        assert_eq!(escaped, "&lt;img src=x onerror=&quot;alert(&#39;&amp;&#39;)&quot;&gt;");
+    }
+}
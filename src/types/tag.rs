@@ -0,0 +1,5 @@
+#[derive(Debug, serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq)]
+pub struct Tag {
+    pub name: String,
+    pub question_count: i64,
+}
@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// TOTP secret of an account. Until the first code is confirmed the secret
+/// is pending and logins do not ask for codes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TwoFactor {
+    pub secret: String,
+    pub enabled: bool,
+    /// Time step of the last code used, codes of this or earlier steps
+    /// are not accepted again.
+    pub last_used_step: Option<i64>,
+}
+
+/// Reply to `POST /account/2fa/setup`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// Body of `POST /account/2fa/confirm`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TwoFactorCode {
+    pub code: String,
+}
+
+/// Reply to `POST /account/2fa/confirm`, the only time the recovery codes
+/// are revealed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Body of `DELETE /account/2fa`, confirmed by the password or a TOTP or
+/// recovery code.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TwoFactorDisable {
+    pub password: Option<String>,
+    pub code: Option<String>,
+}
+
+/// Reply to a login with the right password when the account uses two
+/// factors; the token is exchanged at `POST /login/2fa`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TwoFactorChallenge {
+    pub two_factor_required: bool,
+    pub two_factor_token: String,
+    pub expires_in: i64,
+}
+
+/// Body of `POST /login/2fa`, with a TOTP code or a recovery code.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TwoFactorLogin {
+    pub two_factor_token: String,
+    pub code: String,
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// Vote of an account on a question or answer.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteDirection {
+    Up,
+    Down,
+    /// Takes an earlier vote back
+    Clear,
+}
+impl VoteDirection {
+    /// What the vote adds to the score: 1, -1 or nothing.
+    pub fn value(self) -> i16 {
+        match self {
+            VoteDirection::Up => 1,
+            VoteDirection::Down => -1,
+            VoteDirection::Clear => 0,
+        }
+    }
+}
+
+/// Body of `POST /questions/{id}/vote` and `POST /answers/{id}/vote`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct NewVote {
+    pub direction: VoteDirection,
+}
+
+/// Reply to a vote, with the score it left the question or answer at.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct VoteResult {
+    pub direction: VoteDirection,
+    pub score: i32,
+}
@@ -0,0 +1,266 @@
+//! Rules for e-mail addresses, passwords and profiles chosen by users.
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use handle_errors::FieldError;
+
+use crate::types::account::Profile;
+
+const COMMON_PASSWORDS: &str = include_str!("../common_passwords.txt");
+/// Longest password accepted, hashing longer ones only costs time.
+const MAX_PASSWORD_LENGTH: usize = 128;
+/// Length of the `email` column.
+const MAX_EMAIL_LENGTH: usize = 255;
+/// Length of the `display_name` column.
+const MAX_DISPLAY_NAME_LENGTH: usize = 50;
+const MAX_BIO_LENGTH: usize = 1000;
+const MAX_AVATAR_URL_LENGTH: usize = 2048;
+
+/// Password requirements, see the `password_*` options in `Config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordRules {
+    pub min_length: usize,
+    /// Lowest `password_strength` score accepted, from 0 to 4.
+    pub min_score: u8,
+}
+
+impl PasswordRules {
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        PasswordRules {
+            min_length: config.password_min_length,
+            min_score: config.password_min_score,
+        }
+    }
+
+    /// Problems of `password` for the account with `email`, under `field`.
+    pub fn check(&self, field: &str, password: &str, email: &str) -> Vec<FieldError> {
+        let length = password.chars().count();
+        let error = |code: &str, message: String| FieldError {
+            field: field.to_string(),
+            code: code.to_string(),
+            message,
+        };
+
+        if length < self.min_length {
+            return vec![error("too_short", format!("must have at least {} characters", self.min_length))];
+        }
+        if length > MAX_PASSWORD_LENGTH {
+            return vec![error("too_long", format!("must have at most {} characters", MAX_PASSWORD_LENGTH))];
+        }
+        if is_common_password(password) {
+            return vec![error("common", "is one of the most common passwords".to_string())];
+        }
+        if password_strength(password, email) < self.min_score {
+            return vec![error("too_weak", "is too easy to guess, use more words or characters".to_string())];
+        }
+
+        Vec::new()
+    }
+}
+
+/// Trimmed, lower case address, the form addresses are stored and looked up in.
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// Problems of an already normalized address, under `field`.
+pub fn check_email(field: &str, email: &str) -> Vec<FieldError> {
+    let error = |code: &str, message: &str| vec![FieldError {
+        field: field.to_string(),
+        code: code.to_string(),
+        message: message.to_string(),
+    }];
+
+    if email.is_empty() {
+        error("required", "must not be empty")
+    } else if email.len() > MAX_EMAIL_LENGTH {
+        error("too_long", "must have at most 255 characters")
+    } else if !email_address::EmailAddress::is_valid(email) || !email.contains('@') {
+        error("invalid", "is not a valid e-mail address")
+    } else {
+        Vec::new()
+    }
+}
+
+/// Profile with every field trimmed and blank fields cleared.
+pub fn normalize_profile(profile: Profile) -> Profile {
+    let normalize = |field: Option<String>| field
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    Profile {
+        display_name: normalize(profile.display_name),
+        bio: normalize(profile.bio),
+        avatar_url: normalize(profile.avatar_url),
+    }
+}
+
+/// Problems of an already normalized profile.
+pub fn check_profile(profile: &Profile) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut error = |field: &str, code: &str, message: String| errors.push(FieldError {
+        field: field.to_string(),
+        code: code.to_string(),
+        message,
+    });
+
+    if let Some(display_name) = &profile.display_name {
+        if display_name.chars().count() > MAX_DISPLAY_NAME_LENGTH {
+            error("display_name", "too_long", format!("must have at most {} characters", MAX_DISPLAY_NAME_LENGTH));
+        } else if display_name.contains('@') {
+            error("display_name", "invalid", "must not contain an e-mail address".to_string());
+        }
+    }
+    if let Some(bio) = &profile.bio {
+        if bio.chars().count() > MAX_BIO_LENGTH {
+            error("bio", "too_long", format!("must have at most {} characters", MAX_BIO_LENGTH));
+        }
+    }
+    if let Some(avatar_url) = &profile.avatar_url {
+        if avatar_url.len() > MAX_AVATAR_URL_LENGTH {
+            error("avatar_url", "too_long", format!("must have at most {} characters", MAX_AVATAR_URL_LENGTH));
+        } else if !is_web_url(avatar_url) {
+            error("avatar_url", "invalid", "must be an http or https URL".to_string());
+        }
+    }
+
+    errors
+}
+
+fn is_web_url(url: &str) -> bool {
+    match url.parse::<warp::http::Uri>() {
+        Ok(uri) => matches!(uri.scheme_str(), Some("http" | "https")) && uri.host().is_some_and(|host| !host.is_empty()),
+        Err(_) => false,
+    }
+}
+
+/// Fails with field-level errors if there are any.
+pub fn validate(errors: Vec<FieldError>) -> Result<(), handle_errors::Error> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(handle_errors::Error::ValidationFailed(errors))
+    }
+}
+
+fn is_common_password(password: &str) -> bool {
+    static PASSWORDS: OnceLock<HashSet<String>> = OnceLock::new();
+    PASSWORDS
+        .get_or_init(|| {
+            COMMON_PASSWORDS
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_lowercase)
+                .collect()
+        })
+        .contains(&password.to_lowercase())
+}
+
+/// Rough guessability score from 0 (trivial) to 4 (strong), in the spirit
+/// of zxcvbn: estimates entropy from the kinds of characters used and
+/// discounts repeats, runs like `abc` or `321` and parts of the address.
+pub fn password_strength(password: &str, email: &str) -> u8 {
+    let lower = password.to_lowercase();
+    let chars = lower.chars().collect::<Vec<_>>();
+
+    let mut effective = 0.0;
+    for (i, c) in chars.iter().enumerate() {
+        let predictable = i > 0 && {
+            let step = *c as i64 - chars[i - 1] as i64;
+            step == 0 || step.abs() == 1
+        };
+        effective += if predictable { 0.25 } else { 1.0 };
+    }
+
+    let local_part = email.split('@').next().unwrap_or_default();
+    if local_part.chars().count() >= 3 && lower.contains(local_part) {
+        effective -= local_part.chars().count() as f64 * 0.75;
+    }
+
+    let mut charset = 0;
+    if password.chars().any(|c| c.is_ascii_lowercase()) { charset += 26; }
+    if password.chars().any(|c| c.is_ascii_uppercase()) { charset += 26; }
+    if password.chars().any(|c| c.is_ascii_digit()) { charset += 10; }
+    if password.chars().any(|c| c.is_ascii_punctuation() || c == ' ') { charset += 33; }
+    if !password.is_ascii() { charset += 100; }
+
+    let bits = effective.max(0.0) * f64::from(charset.max(1)).log2();
+    match bits {
+        b if b < 20.0 => 0,
+        b if b < 30.0 => 1,
+        b if b < 40.0 => 2,
+        b if b < 55.0 => 3,
+        _ => 4,
+    }
+}
+
+
+#[cfg(test)]
+mod validation_tests {
+    use super::*;
+
+    const RULES: PasswordRules = PasswordRules { min_length: 10, min_score: 3 };
+
+    fn codes(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().map(|e| e.code).collect()
+    }
+
+    #[test]
+    fn emails() {
+        // assert
+        assert_eq!(normalize_email("  Jane.Doe@Example.COM "), "jane.doe@example.com");
+        assert!(check_email("email", "jane.doe@example.com").is_empty());
+        assert!(check_email("email", "\"quoted name\"@example.com").is_empty());
+        assert_eq!(codes(check_email("email", "")), vec!["required"]);
+        assert_eq!(codes(check_email("email", "jane.doe")), vec!["invalid"]);
+        assert_eq!(codes(check_email("email", "jane@@example.com")), vec!["invalid"]);
+        assert_eq!(codes(check_email("email", &format!("{}@example.com", "a".repeat(250)))), vec!["too_long"]);
+    }
+
+    #[test]
+    fn passwords() {
+        // assert
+        assert!(RULES.check("password", "correct horse battery", "jane@example.com").is_empty());
+        assert!(RULES.check("password", "Tr0ub4dor&3x", "jane@example.com").is_empty());
+        assert_eq!(codes(RULES.check("password", "short", "jane@example.com")), vec!["too_short"]);
+        assert_eq!(codes(RULES.check("password", &"a".repeat(129), "jane@example.com")), vec!["too_long"]);
+        assert_eq!(codes(RULES.check("password", "Password123!", "jane@example.com")), vec!["common"]);
+        assert_eq!(codes(RULES.check("password", "abcdefghijklm", "jane@example.com")), vec!["too_weak"]);
+        assert_eq!(codes(RULES.check("password", "aaaaaaaaaaaaaa", "jane@example.com")), vec!["too_weak"]);
+        assert_eq!(codes(RULES.check("password", "jonathan.smith1", "jonathan.smith@example.com")), vec!["too_weak"]);
+    }
+
+    #[test]
+    fn profiles() {
+        // arrange
+        let profile = |display_name: &str, bio: &str, avatar_url: &str| normalize_profile(Profile {
+            display_name: Some(display_name.to_string()),
+            bio: Some(bio.to_string()),
+            avatar_url: Some(avatar_url.to_string()),
+        });
+        let fields = |errors: Vec<FieldError>| errors.into_iter().map(|e| (e.field, e.code)).collect::<Vec<_>>();
+
+        // act
+        let blank = profile("  ", "", " ");
+        let valid = profile(" Jane ", "Rustacean", "https://example.com/jane.png");
+        let invalid = profile("jane@example.com", &"b".repeat(1001), "javascript:alert(1)");
+
+        // assert
+        assert_eq!(blank, Profile::default());
+        assert_eq!(valid.display_name.as_deref(), Some("Jane"));
+        assert!(check_profile(&valid).is_empty());
+        assert_eq!(fields(check_profile(&invalid)), vec![
+            ("display_name".to_string(), "invalid".to_string()),
+            ("bio".to_string(), "too_long".to_string()),
+            ("avatar_url".to_string(), "invalid".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn strength_grows_with_variety() {
+        // assert
+        assert!(password_strength("kqzvbwmtrp", "") < password_strength("kQz7bW!mTr", ""));
+        assert_eq!(password_strength("", ""), 0);
+    }
+}